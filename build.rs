@@ -0,0 +1,32 @@
+use std::process::Command;
+use std::time::{SystemTime, UNIX_EPOCH};
+
+fn main() {
+    // rebuild when migrations change, they are embedded in the binary
+    println!("cargo:rerun-if-changed=migrations");
+
+    // build info for /version, GIT_SHA can be passed in where .git isn't available
+    println!("cargo:rerun-if-changed=.git/HEAD");
+    println!("cargo:rerun-if-changed=.git/refs/heads");
+    println!("cargo:rerun-if-env-changed=GIT_SHA");
+
+    let git_sha = std::env::var("GIT_SHA").ok().or_else(|| {
+        Command::new("git")
+            .args(["rev-parse", "--short=12", "HEAD"])
+            .output()
+            .ok()
+            .filter(|output| output.status.success())
+            .and_then(|output| String::from_utf8(output.stdout).ok())
+            .map(|sha| sha.trim().to_string())
+    });
+    let build_timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or_default();
+
+    println!(
+        "cargo:rustc-env=BUILD_GIT_SHA={}",
+        git_sha.unwrap_or_else(|| "unknown".to_string())
+    );
+    println!("cargo:rustc-env=BUILD_TIMESTAMP={}", build_timestamp);
+}
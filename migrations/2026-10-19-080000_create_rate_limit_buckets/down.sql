@@ -0,0 +1,2 @@
+DROP FUNCTION rate_limit_refill(DOUBLE PRECISION, TIMESTAMP, DOUBLE PRECISION, DOUBLE PRECISION);
+DROP TABLE rate_limit_buckets;
@@ -0,0 +1,16 @@
+CREATE TABLE rate_limit_buckets (
+    bucket_key VARCHAR PRIMARY KEY,
+    tokens DOUBLE PRECISION NOT NULL,
+    allowed BOOLEAN NOT NULL,
+    updated_at TIMESTAMP NOT NULL DEFAULT CURRENT_TIMESTAMP
+);
+
+-- Tokens held by a bucket after refilling it for the time elapsed since `updated_at`
+CREATE OR REPLACE FUNCTION rate_limit_refill(
+    tokens DOUBLE PRECISION,
+    updated_at TIMESTAMP,
+    capacity DOUBLE PRECISION,
+    refill_per_second DOUBLE PRECISION
+) RETURNS DOUBLE PRECISION AS $$
+    SELECT LEAST(capacity, tokens + EXTRACT(EPOCH FROM (NOW()::TIMESTAMP - updated_at))::DOUBLE PRECISION * refill_per_second);
+$$ LANGUAGE sql STABLE;
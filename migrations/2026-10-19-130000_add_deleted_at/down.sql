@@ -0,0 +1,6 @@
+-- what is still in the trash is lost
+DELETE FROM news WHERE deleted_at IS NOT NULL;
+DELETE FROM categories WHERE deleted_at IS NOT NULL;
+
+ALTER TABLE categories DROP COLUMN deleted_at;
+ALTER TABLE news DROP COLUMN deleted_at;
@@ -0,0 +1,6 @@
+-- deleted articles and categories stay in the trash until they are restored or purged
+ALTER TABLE news ADD COLUMN deleted_at TIMESTAMP;
+ALTER TABLE categories ADD COLUMN deleted_at TIMESTAMP;
+
+CREATE INDEX news_trash_idx ON news (deleted_at) WHERE deleted_at IS NOT NULL;
+CREATE INDEX categories_trash_idx ON categories (deleted_at) WHERE deleted_at IS NOT NULL;
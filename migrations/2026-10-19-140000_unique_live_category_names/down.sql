@@ -0,0 +1,12 @@
+-- of the categories sharing a name, the live one or else the latest trashed one is kept
+DELETE FROM categories c
+WHERE c.deleted_at IS NOT NULL
+  AND EXISTS (
+    SELECT 1 FROM categories other
+    WHERE other.name = c.name
+      AND other.id <> c.id
+      AND (other.deleted_at IS NULL OR other.id > c.id)
+  );
+
+DROP INDEX categories_name_key;
+ALTER TABLE categories ADD CONSTRAINT categories_name_key UNIQUE (name);
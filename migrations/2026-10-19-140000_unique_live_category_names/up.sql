@@ -0,0 +1,3 @@
+-- a trashed category gives up its name, a new one can take it
+ALTER TABLE categories DROP CONSTRAINT categories_name_key;
+CREATE UNIQUE INDEX categories_name_key ON categories (name) WHERE deleted_at IS NULL;
@@ -0,0 +1,2 @@
+-- two signups racing for the same name can't both win
+CREATE UNIQUE INDEX users_username_key ON users (username);
@@ -0,0 +1,209 @@
+use std::collections::HashMap;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use serde::{Deserialize, Serialize};
+
+use crate::cli::CliResult;
+use crate::models::news::NewsCategory;
+use crate::schema::{categories, news, news_categories, users};
+
+// articles and categories used by `seed`
+const SAMPLE_CONTENT: &str = include_str!("sample_content.json");
+
+/// Portable dump of categories and news, keyed by names instead of ids.
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct ContentExport {
+    pub categories: Vec<ExportedCategory>,
+    pub news: Vec<ExportedNews>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedCategory {
+    pub name: String,
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct ExportedNews {
+    pub title: String,
+    pub content: String,
+    // username of the author, the default author is used when it doesn't exist
+    pub author: Option<String>,
+    #[serde(default)]
+    pub categories: Vec<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub updated_at: Option<NaiveDateTime>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct ImportSummary {
+    pub categories: usize,
+    pub news: usize,
+}
+
+pub fn export_content(conn: &mut PgConnection) -> CliResult<ContentExport> {
+    // what is in the trash is left out
+    let categories = categories::table
+        .filter(categories::deleted_at.is_null())
+        .order(categories::name)
+        .select((categories::name, categories::description))
+        .load::<(String, Option<String>)>(conn)?
+        .into_iter()
+        .map(|(name, description)| ExportedCategory { name, description })
+        .collect();
+
+    let mut links: HashMap<i32, Vec<String>> = HashMap::new();
+    for (news_id, category) in news_categories::table
+        .inner_join(categories::table)
+        .filter(categories::deleted_at.is_null())
+        .order(categories::name)
+        .select((news_categories::news_id, categories::name))
+        .load::<(i32, String)>(conn)?
+    {
+        links.entry(news_id).or_default().push(category);
+    }
+
+    let news = news::table
+        .inner_join(users::table)
+        .filter(news::deleted_at.is_null())
+        .order(news::id)
+        .select((
+            news::id,
+            news::title,
+            news::content,
+            users::username,
+            news::created_at,
+            news::updated_at,
+        ))
+        .load::<(i32, String, String, String, NaiveDateTime, NaiveDateTime)>(conn)?
+        .into_iter()
+        .map(
+            |(id, title, content, author, created_at, updated_at)| ExportedNews {
+                title,
+                content,
+                author: Some(author),
+                categories: links.remove(&id).unwrap_or_default(),
+                created_at: Some(created_at),
+                updated_at: Some(updated_at),
+            },
+        )
+        .collect();
+
+    Ok(ContentExport { categories, news })
+}
+
+// add the content in one transaction, categories that already exist are reused and trashed
+// ones restored
+pub fn import_content(
+    conn: &mut PgConnection,
+    content: &ContentExport,
+    default_author: &str,
+) -> CliResult<ImportSummary> {
+    conn.transaction(|conn| {
+        let authors: HashMap<String, i32> = users::table
+            .select((users::username, users::id))
+            .load::<(String, i32)>(conn)?
+            .into_iter()
+            .collect();
+        let default_author_id = *authors
+            .get(default_author)
+            .ok_or_else(|| format!("Author {} not found", default_author))?;
+
+        let mut summary = ImportSummary::default();
+        let now = Utc::now().naive_utc();
+
+        // categories named by articles are created too
+        let mut wanted: Vec<(&str, Option<&str>)> = content
+            .categories
+            .iter()
+            .map(|category| (category.name.as_str(), category.description.as_deref()))
+            .collect();
+        for article in &content.news {
+            wanted.extend(article.categories.iter().map(|name| (name.as_str(), None)));
+        }
+
+        let mut category_ids: HashMap<String, i32> = categories::table
+            .filter(categories::deleted_at.is_null())
+            .select((categories::name, categories::id))
+            .load::<(String, i32)>(conn)?
+            .into_iter()
+            .collect();
+
+        for (name, description) in wanted {
+            if category_ids.contains_key(name) {
+                continue;
+            }
+            // a category in the trash is taken back out instead of created again
+            let trashed = categories::table
+                .filter(categories::name.eq(name))
+                .filter(categories::deleted_at.is_not_null())
+                .order(categories::deleted_at.desc())
+                .select(categories::id)
+                .first::<i32>(conn)
+                .optional()?;
+            let id = match trashed {
+                Some(id) => diesel::update(categories::table.find(id))
+                    .set((
+                        categories::deleted_at.eq(None::<NaiveDateTime>),
+                        categories::updated_at.eq(now),
+                    ))
+                    .returning(categories::id)
+                    .get_result::<i32>(conn)?,
+                None => diesel::insert_into(categories::table)
+                    .values((
+                        categories::name.eq(name),
+                        categories::description.eq(description),
+                        categories::created_at.eq(now),
+                        categories::updated_at.eq(now),
+                    ))
+                    .returning(categories::id)
+                    .get_result::<i32>(conn)?,
+            };
+            category_ids.insert(name.to_string(), id);
+            summary.categories += 1;
+        }
+
+        for article in &content.news {
+            let author_id = article
+                .author
+                .as_ref()
+                .and_then(|author| authors.get(author).copied())
+                .unwrap_or(default_author_id);
+
+            let news_id = diesel::insert_into(news::table)
+                .values((
+                    news::title.eq(&article.title),
+                    news::content.eq(&article.content),
+                    news::author_id.eq(author_id),
+                    news::created_at.eq(article.created_at.unwrap_or(now)),
+                    news::updated_at.eq(article.updated_at.unwrap_or(now)),
+                ))
+                .returning(news::id)
+                .get_result::<i32>(conn)?;
+
+            let links: Vec<NewsCategory> = article
+                .categories
+                .iter()
+                .map(|name| NewsCategory {
+                    news_id,
+                    category_id: category_ids[name],
+                })
+                .collect();
+            diesel::insert_into(news_categories::table)
+                .values(&links)
+                .on_conflict_do_nothing()
+                .execute(conn)?;
+
+            summary.news += 1;
+        }
+
+        Ok(summary)
+    })
+}
+
+// add a handful of sample articles for local development
+pub fn seed(conn: &mut PgConnection, author: &str) -> CliResult<ImportSummary> {
+    let content: ContentExport = serde_json::from_str(SAMPLE_CONTENT)?;
+    import_content(conn, &content, author)
+}
@@ -0,0 +1,76 @@
+use chrono::{Duration, Utc};
+use diesel::prelude::*;
+
+use crate::cli::CliResult;
+use crate::models::webhook::DELIVERY_PENDING;
+use crate::schema::{
+    categories, news, outbox_events, rate_limit_buckets, sessions, webhook_deliveries,
+};
+
+// buckets idle this long are full again, so dropping them changes nothing
+const STALE_BUCKET_HOURS: i64 = 24;
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct CleanupSummary {
+    pub sessions: usize,
+    pub rate_limit_buckets: usize,
+    pub outbox_events: usize,
+}
+
+// delete sessions that ended more than `retention_days` ago, idle rate limit buckets and
+// webhook events sent out that long ago, their delivery log goes with them
+pub fn cleanup(conn: &mut PgConnection, retention_days: i64) -> CliResult<CleanupSummary> {
+    let now = Utc::now().naive_utc();
+    let cutoff = now - Duration::days(retention_days);
+
+    let sessions = diesel::delete(
+        sessions::table.filter(
+            sessions::expires_at
+                .lt(cutoff)
+                .or(sessions::revoked_at.lt(cutoff)),
+        ),
+    )
+    .execute(conn)?;
+
+    let rate_limit_buckets = diesel::delete(
+        rate_limit_buckets::table
+            .filter(rate_limit_buckets::updated_at.lt(now - Duration::hours(STALE_BUCKET_HOURS))),
+    )
+    .execute(conn)?;
+
+    // events with deliveries still to be attempted are kept
+    let outbox_events = diesel::delete(
+        outbox_events::table
+            .filter(outbox_events::dispatched_at.lt(cutoff))
+            .filter(diesel::dsl::not(diesel::dsl::exists(
+                webhook_deliveries::table
+                    .filter(webhook_deliveries::event_id.eq(outbox_events::id))
+                    .filter(webhook_deliveries::status.eq(DELIVERY_PENDING)),
+            ))),
+    )
+    .execute(conn)?;
+
+    Ok(CleanupSummary {
+        sessions,
+        rate_limit_buckets,
+        outbox_events,
+    })
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+pub struct PurgeSummary {
+    pub news: usize,
+    pub categories: usize,
+}
+
+// delete articles and categories that went to the trash more than `retention_days` ago, their
+// category links go with them
+pub fn purge_trash(conn: &mut PgConnection, retention_days: i64) -> CliResult<PurgeSummary> {
+    let cutoff = Utc::now().naive_utc() - Duration::days(retention_days);
+
+    let news = diesel::delete(news::table.filter(news::deleted_at.lt(cutoff))).execute(conn)?;
+    let categories = diesel::delete(categories::table.filter(categories::deleted_at.lt(cutoff)))
+        .execute(conn)?;
+
+    Ok(PurgeSummary { news, categories })
+}
@@ -0,0 +1,187 @@
+use std::io::{BufRead, IsTerminal, Write};
+use std::path::PathBuf;
+
+use clap::{Parser, Subcommand};
+
+use crate::config::Config;
+use crate::db;
+use crate::utils::password::PasswordPolicy;
+
+pub mod content;
+pub mod maintenance;
+pub mod users;
+
+pub type CliResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+#[derive(Debug, Parser)]
+#[command(version, about = "News REST API")]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// Start the HTTP server (the default)
+    Serve,
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Create an admin user, the password is read from stdin
+    CreateAdmin { username: String },
+    /// Set a new password read from stdin and sign the user out everywhere
+    ResetPassword { username: String },
+    /// Give a user admin rights
+    Promote { username: String },
+    /// Take admin rights away and sign the user out everywhere
+    Demote { username: String },
+    /// Add sample categories and articles
+    Seed {
+        /// Username the sample articles are attributed to
+        #[arg(long)]
+        author: String,
+    },
+    /// Write categories and news as JSON
+    Export {
+        /// File to write, stdout when omitted
+        #[arg(long)]
+        output: Option<PathBuf>,
+    },
+    /// Add categories and news from an export
+    Import {
+        input: PathBuf,
+        /// Username for articles whose author doesn't exist here
+        #[arg(long)]
+        author: String,
+    },
+    /// Delete old sessions, idle rate limit buckets and sent webhook events
+    Cleanup {
+        /// Keep ended sessions and sent webhook events for this many days,
+        /// `maintenance.retention_days` when not given
+        #[arg(long, value_parser = clap::value_parser!(i64).range(0..))]
+        retention_days: Option<i64>,
+    },
+}
+
+#[derive(Debug, Subcommand)]
+pub enum MigrateAction {
+    /// Apply all pending migrations
+    Up,
+    /// Revert the most recently applied migration
+    Down,
+    /// List migrations and whether they have been applied
+    Status,
+}
+
+// prompt without echo on a terminal, read a line when piped
+fn read_password() -> CliResult<String> {
+    if std::io::stdin().is_terminal() {
+        return Ok(rpassword::prompt_password("Password: ")?);
+    }
+
+    let mut password = String::new();
+    std::io::stdin().lock().read_line(&mut password)?;
+    Ok(password.trim_end_matches(['\r', '\n']).to_string())
+}
+
+/// Runs every command except `serve`.
+pub fn run(config: &Config, command: Command) -> CliResult<()> {
+    let pool = db::establish_connection(&config.database);
+    let mut conn = pool.get()?;
+    let conn = &mut conn;
+
+    match command {
+        Command::Serve => unreachable!("serve is handled by main"),
+        Command::Migrate { action } => migrate(conn, action)?,
+        Command::CreateAdmin { username } => {
+            let policy = PasswordPolicy::from_config(&config.password)?;
+            let id = users::create_admin(conn, &policy, &username, &read_password()?)?;
+            println!("Created admin {} with id {}", username, id);
+        }
+        Command::ResetPassword { username } => {
+            let policy = PasswordPolicy::from_config(&config.password)?;
+            let revoked = users::reset_password(conn, &policy, &username, &read_password()?)?;
+            println!("Password reset, {} sessions revoked", revoked);
+        }
+        Command::Promote { username } => {
+            users::set_admin(conn, &username, true)?;
+            println!("{} is now an admin, they need to log in again", username);
+        }
+        Command::Demote { username } => {
+            let revoked = users::set_admin(conn, &username, false)?;
+            println!(
+                "{} is no longer an admin, {} sessions revoked",
+                username, revoked
+            );
+        }
+        Command::Seed { author } => {
+            let summary = content::seed(conn, &author)?;
+            println!(
+                "Added {} categories and {} articles",
+                summary.categories, summary.news
+            );
+        }
+        Command::Export { output } => {
+            let export = content::export_content(conn)?;
+            match output {
+                Some(path) => serde_json::to_writer_pretty(std::fs::File::create(path)?, &export)?,
+                None => {
+                    let mut stdout = std::io::stdout().lock();
+                    serde_json::to_writer_pretty(&mut stdout, &export)?;
+                    writeln!(stdout)?;
+                }
+            }
+        }
+        Command::Import { input, author } => {
+            let export = serde_json::from_reader(std::fs::File::open(input)?)?;
+            let summary = content::import_content(conn, &export, &author)?;
+            println!(
+                "Added {} categories and {} articles",
+                summary.categories, summary.news
+            );
+        }
+        Command::Cleanup { retention_days } => {
+            // the same retention as the background cleanup unless told otherwise
+            let retention_days = retention_days.unwrap_or(config.maintenance.retention_days);
+            let summary = maintenance::cleanup(conn, retention_days)?;
+            println!(
+                "Deleted {} sessions, {} rate limit buckets and {} webhook events",
+                summary.sessions, summary.rate_limit_buckets, summary.outbox_events
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn migrate(conn: &mut db::DBConnection, action: MigrateAction) -> CliResult<()> {
+    match action {
+        MigrateAction::Up => {
+            let applied = db::run_migrations(conn)?;
+            if applied.is_empty() {
+                println!("Database is up to date");
+            }
+            for version in applied {
+                println!("Applied {}", version);
+            }
+        }
+        MigrateAction::Down => {
+            let version = db::revert_migration(conn)?;
+            println!("Reverted {}", version);
+        }
+        MigrateAction::Status => {
+            for migration in db::migration_status(conn)? {
+                let state = if migration.applied {
+                    "applied"
+                } else {
+                    "pending"
+                };
+                println!("{:<8} {}", state, migration.name);
+            }
+        }
+    }
+
+    Ok(())
+}
@@ -0,0 +1,92 @@
+use chrono::Utc;
+use diesel::prelude::*;
+
+use crate::cli::CliResult;
+use crate::models::user::NewUser;
+use crate::schema::{sessions, users};
+use crate::utils::password::{hash_password, PasswordPolicy};
+
+fn find_user(conn: &mut PgConnection, username: &str) -> CliResult<i32> {
+    users::table
+        .filter(users::username.eq(username))
+        .select(users::id)
+        .first::<i32>(conn)
+        .optional()?
+        .ok_or_else(|| format!("User {} not found", username).into())
+}
+
+// sign the user out everywhere, their tokens carry the old role
+fn revoke_sessions(conn: &mut PgConnection, user_id: i32) -> QueryResult<usize> {
+    diesel::update(
+        sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::revoked_at.is_null()),
+    )
+    .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+    .execute(conn)
+}
+
+// create an admin user, returning its id
+pub fn create_admin(
+    conn: &mut PgConnection,
+    policy: &PasswordPolicy,
+    username: &str,
+    password: &str,
+) -> CliResult<i32> {
+    if find_user(conn, username).is_ok() {
+        return Err(format!("User {} already exists, use promote instead", username).into());
+    }
+    policy.check(username, password)?;
+
+    let id = diesel::insert_into(users::table)
+        .values(&NewUser {
+            username: username.to_string(),
+            password: hash_password(password)?,
+            is_admin: true,
+        })
+        .returning(users::id)
+        .get_result::<i32>(conn)?;
+
+    Ok(id)
+}
+
+// set a new password and end the user's sessions, returning how many were revoked
+pub fn reset_password(
+    conn: &mut PgConnection,
+    policy: &PasswordPolicy,
+    username: &str,
+    password: &str,
+) -> CliResult<usize> {
+    let user_id = find_user(conn, username)?;
+    policy.check(username, password)?;
+    let password_hash = hash_password(password)?;
+
+    let revoked = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::password.eq(password_hash),
+                users::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+        revoke_sessions(conn, user_id)
+    })?;
+
+    Ok(revoked)
+}
+
+// grant or remove admin rights, returning how many sessions were revoked
+pub fn set_admin(conn: &mut PgConnection, username: &str, is_admin: bool) -> CliResult<usize> {
+    let user_id = find_user(conn, username)?;
+
+    let revoked = conn.transaction::<_, diesel::result::Error, _>(|conn| {
+        diesel::update(users::table.find(user_id))
+            .set((
+                users::is_admin.eq(is_admin),
+                users::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(conn)?;
+        revoke_sessions(conn, user_id)
+    })?;
+
+    Ok(revoked)
+}
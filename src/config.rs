@@ -0,0 +1,626 @@
+use std::collections::HashMap;
+use std::env;
+use std::path::PathBuf;
+
+use config::{Environment, File, FileFormat, Source};
+use serde::Deserialize;
+use thiserror::Error;
+
+use crate::utils::oidc::OidcConfig;
+
+// prefix for environment overrides, e.g. NEWS_API__SERVER__HOST=0.0.0.0
+const ENV_PREFIX: &str = "NEWS_API";
+const DEFAULT_CONFIG_FILE: &str = "config.toml";
+// HS256 keys shorter than the hash output weaken the signature
+const MIN_JWT_SECRET_LENGTH: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum ConfigError {
+    #[error("failed to load configuration: {0}")]
+    Load(#[from] config::ConfigError),
+    #[error("invalid configuration:\n  - {}", .0.join("\n  - "))]
+    Invalid(Vec<String>),
+}
+
+/// Application settings, loaded once at startup and shared as `web::Data<Config>`.
+#[derive(Clone, Deserialize)]
+pub struct Config {
+    #[serde(default)]
+    pub server: ServerConfig,
+    pub database: DatabaseConfig,
+    pub jwt: JwtConfig,
+    #[serde(default)]
+    pub cors: CorsConfig,
+    #[serde(default)]
+    pub limits: LimitsConfig,
+    #[serde(default)]
+    pub rate_limit: RateLimitConfig,
+    #[serde(default)]
+    pub password: PasswordConfig,
+    #[serde(default)]
+    pub metrics: MetricsConfig,
+    #[serde(default)]
+    pub graphql: GraphqlConfig,
+    #[serde(default)]
+    pub concurrency: ConcurrencyConfig,
+    #[serde(default)]
+    pub http_cache: HttpCacheConfig,
+    #[serde(default)]
+    pub response_cache: ResponseCacheConfig,
+    #[serde(default)]
+    pub webhooks: WebhookConfig,
+    #[serde(default)]
+    pub news_stream: NewsStreamConfig,
+    #[serde(default)]
+    pub edit_locks: EditLockConfig,
+    #[serde(default)]
+    pub trash: TrashConfig,
+    #[serde(default)]
+    pub logging: LoggingConfig,
+    #[serde(default)]
+    pub maintenance: MaintenanceConfig,
+    // staff single sign-on stays disabled without a provider
+    #[serde(default)]
+    pub oidc: Option<OidcConfig>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ServerConfig {
+    pub host: String,
+    pub port: u16,
+    // defaults to the number of CPUs
+    pub workers: Option<usize>,
+    // time in-flight requests and background tasks get to finish on shutdown
+    pub shutdown_timeout_secs: u64,
+}
+
+impl Default for ServerConfig {
+    fn default() -> Self {
+        ServerConfig {
+            host: "127.0.0.1".to_string(),
+            port: 8080,
+            workers: None,
+            shutdown_timeout_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+pub struct DatabaseConfig {
+    pub url: String,
+    #[serde(default = "default_max_connections")]
+    pub max_connections: u32,
+    #[serde(default)]
+    pub min_idle: Option<u32>,
+    #[serde(default = "default_connection_timeout_secs")]
+    pub connection_timeout_secs: u64,
+    #[serde(default = "default_idle_timeout_secs")]
+    pub idle_timeout_secs: Option<u64>,
+    #[serde(default = "default_max_lifetime_secs")]
+    pub max_lifetime_secs: Option<u64>,
+    // apply pending migrations before serving
+    #[serde(default)]
+    pub migrate_on_startup: bool,
+}
+
+fn default_max_connections() -> u32 {
+    10
+}
+
+fn default_connection_timeout_secs() -> u64 {
+    30
+}
+
+fn default_idle_timeout_secs() -> Option<u64> {
+    Some(600)
+}
+
+fn default_max_lifetime_secs() -> Option<u64> {
+    Some(1800)
+}
+
+// no Debug, the secret must not end up in logs
+#[derive(Clone, Deserialize)]
+pub struct JwtConfig {
+    pub secret: String,
+    // how long issued tokens and their sessions stay valid
+    #[serde(default = "default_token_lifetime_hours")]
+    pub token_lifetime_hours: i64,
+}
+
+fn default_token_lifetime_hours() -> i64 {
+    24
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct CorsConfig {
+    // browser origins allowed to call the API, "*" allows any
+    pub allowed_origins: Vec<String>,
+    pub max_age_secs: usize,
+}
+
+impl Default for CorsConfig {
+    fn default() -> Self {
+        CorsConfig {
+            allowed_origins: Vec::new(),
+            max_age_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LimitsConfig {
+    pub json_payload_bytes: usize,
+}
+
+impl Default for LimitsConfig {
+    fn default() -> Self {
+        LimitsConfig {
+            json_payload_bytes: 256 * 1024,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum RateLimitBackend {
+    // per instance
+    Memory,
+    // shared by all replicas
+    Postgres,
+}
+
+#[derive(Debug, Clone, Copy, Deserialize)]
+pub struct RateLimitSettings {
+    pub capacity: u32,
+    pub refill_per_second: f64,
+}
+
+impl RateLimitSettings {
+    const fn new(capacity: u32, refill_per_second: f64) -> Self {
+        RateLimitSettings {
+            capacity,
+            refill_per_second,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct RateLimitConfig {
+    pub backend: RateLimitBackend,
+    pub auth: RateLimitSettings,
+    pub user: RateLimitSettings,
+    pub me: RateLimitSettings,
+    pub admin: RateLimitSettings,
+}
+
+impl Default for RateLimitConfig {
+    fn default() -> Self {
+        RateLimitConfig {
+            backend: RateLimitBackend::Memory,
+            // tight limit against credential stuffing
+            auth: RateLimitSettings::new(10, 10.0 / 60.0),
+            user: RateLimitSettings::new(60, 1.0),
+            me: RateLimitSettings::new(60, 1.0),
+            admin: RateLimitSettings::new(120, 2.0),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct PasswordConfig {
+    pub min_length: usize,
+    pub max_length: usize,
+    // extra breached passwords, one per line, on top of the built-in list
+    pub breached_passwords_file: Option<PathBuf>,
+}
+
+impl Default for PasswordConfig {
+    fn default() -> Self {
+        PasswordConfig {
+            min_length: 8,
+            max_length: 128,
+            breached_passwords_file: None,
+        }
+    }
+}
+
+// no Debug, the token must not end up in logs
+#[derive(Clone, Deserialize)]
+#[serde(default)]
+pub struct MetricsConfig {
+    pub enabled: bool,
+    // bearer token Prometheus has to send, open when unset
+    pub token: Option<String>,
+}
+
+impl Default for MetricsConfig {
+    fn default() -> Self {
+        MetricsConfig {
+            enabled: true,
+            token: None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct GraphqlConfig {
+    // serve the GraphiQL playground on /graphiql, meant for development
+    pub playground: bool,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(default)]
+pub struct ConcurrencyConfig {
+    // reject updates and deletes that don't send If-Match with 428
+    pub require_if_match: bool,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct HttpCacheConfig {
+    // Cache-Control of the news list and of single articles, left out when empty
+    pub list_cache_control: String,
+    pub detail_cache_control: String,
+    // tag responses with Surrogate-Key so the CDN can purge them by article and category
+    pub surrogate_keys: bool,
+}
+
+impl Default for HttpCacheConfig {
+    fn default() -> Self {
+        // caches keep the body but ask every time, unchanged articles cost a 304
+        HttpCacheConfig {
+            list_cache_control: "public, no-cache".to_string(),
+            detail_cache_control: "public, no-cache".to_string(),
+            surrogate_keys: true,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct ResponseCacheConfig {
+    // keep hot read responses in memory, changes made through this instance purge them
+    pub enabled: bool,
+    // most responses kept, the least recently used go first
+    pub capacity: usize,
+    // bounds how long changes made through other replicas stay invisible
+    pub ttl_secs: u64,
+}
+
+impl Default for ResponseCacheConfig {
+    fn default() -> Self {
+        ResponseCacheConfig {
+            enabled: false,
+            capacity: 1000,
+            ttl_secs: 30,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct WebhookConfig {
+    // run the dispatcher in this process, every replica may
+    pub enabled: bool,
+    pub poll_interval_secs: u64,
+    // events queued and deliveries sent per poll
+    pub batch_size: i64,
+    pub timeout_secs: u64,
+    // failed deliveries are dead after this many attempts
+    pub max_attempts: i32,
+    // the first retry waits this long, every further one twice as long up to max_backoff_secs
+    pub backoff_secs: u64,
+    pub max_backoff_secs: u64,
+}
+
+impl Default for WebhookConfig {
+    fn default() -> Self {
+        WebhookConfig {
+            enabled: true,
+            poll_interval_secs: 5,
+            batch_size: 100,
+            timeout_secs: 10,
+            max_attempts: 8,
+            backoff_secs: 30,
+            max_backoff_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    // one JSON object per line, for log shippers
+    Json,
+    // human readable, for local development
+    Pretty,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct LoggingConfig {
+    pub format: LogFormat,
+    // tracing filter directives, RUST_LOG takes precedence
+    pub level: String,
+    // OTLP/HTTP traces endpoint, needs the `otlp` feature
+    pub otlp_endpoint: Option<String>,
+    pub service_name: String,
+}
+
+impl Default for LoggingConfig {
+    fn default() -> Self {
+        LoggingConfig {
+            format: LogFormat::Json,
+            level: "info".to_string(),
+            otlp_endpoint: None,
+            service_name: "news-api".to_string(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct NewsStreamConfig {
+    // the latest events, kept for clients resuming with Last-Event-ID
+    pub log_size: usize,
+    // idle streams get a comment this often so proxies don't close them
+    pub keep_alive_secs: u64,
+}
+
+impl Default for NewsStreamConfig {
+    fn default() -> Self {
+        NewsStreamConfig {
+            log_size: 500,
+            keep_alive_secs: 15,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct EditLockConfig {
+    // saves are rejected unless the editor holds the lock of the article
+    pub enforce: bool,
+    // a lock that isn't renewed within this time is free again
+    pub ttl_secs: u64,
+}
+
+impl Default for EditLockConfig {
+    fn default() -> Self {
+        EditLockConfig {
+            enforce: false,
+            ttl_secs: 120,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct TrashConfig {
+    // deleted articles and categories can be restored for this many days
+    pub retention_days: i64,
+    // how often the trash is checked for what is past that
+    pub purge_interval_secs: u64,
+}
+
+impl Default for TrashConfig {
+    fn default() -> Self {
+        TrashConfig {
+            retention_days: 30,
+            purge_interval_secs: 3600,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize)]
+#[serde(default)]
+pub struct MaintenanceConfig {
+    // run the cleanup in-process this often, disabled when unset
+    pub cleanup_interval_secs: Option<u64>,
+    // keep ended sessions and sent webhook events for this many days
+    pub retention_days: i64,
+}
+
+impl Default for MaintenanceConfig {
+    fn default() -> Self {
+        MaintenanceConfig {
+            cleanup_interval_secs: None,
+            retention_days: 30,
+        }
+    }
+}
+
+impl Config {
+    /// Loads `CONFIG_FILE` (or `config.toml` when present) overlaid with the environment.
+    pub fn load() -> Result<Self, ConfigError> {
+        let file = match env::var("CONFIG_FILE") {
+            Ok(path) => File::new(&path, FileFormat::Toml).required(true),
+            Err(_) => File::new(DEFAULT_CONFIG_FILE, FileFormat::Toml).required(false),
+        };
+
+        Self::from_sources(file, env::vars().collect())
+    }
+
+    // build from a file source and a set of environment variables
+    pub fn from_sources<S>(file: S, vars: HashMap<String, String>) -> Result<Self, ConfigError>
+    where
+        S: Source + Send + Sync + 'static,
+    {
+        // the variables the app has always been deployed with
+        let jwt_secret = vars
+            .get("JWT_SECRET")
+            .or_else(|| vars.get("JWT_TOKEN"))
+            .cloned();
+        let database_url = vars.get("DATABASE_URL").cloned();
+
+        let config: Config = config::Config::builder()
+            .add_source(file)
+            .set_override_option("database.url", database_url)?
+            .set_override_option("jwt.secret", jwt_secret)?
+            .add_source(
+                Environment::with_prefix(ENV_PREFIX)
+                    .separator("__")
+                    .try_parsing(true)
+                    .list_separator(",")
+                    .with_list_parse_key("cors.allowed_origins")
+                    .with_list_parse_key("oidc.admin_groups")
+                    .with_list_parse_key("oidc.allowed_groups")
+                    .source(Some(vars)),
+            )
+            .build()?
+            .try_deserialize()?;
+
+        config.validate()?;
+        Ok(config)
+    }
+
+    // collect every problem so a bad deploy is fixed in one go
+    pub fn validate(&self) -> Result<(), ConfigError> {
+        let mut problems = Vec::new();
+
+        if self.server.workers == Some(0) {
+            problems.push("server.workers must be at least 1".to_string());
+        }
+
+        let database = &self.database;
+        if database.url.trim().is_empty() {
+            problems.push("database.url is required (or set DATABASE_URL)".to_string());
+        }
+        if database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if database
+            .min_idle
+            .is_some_and(|min| min > database.max_connections)
+        {
+            problems.push("database.min_idle must not exceed database.max_connections".to_string());
+        }
+        if database.connection_timeout_secs == 0 {
+            problems.push("database.connection_timeout_secs must be at least 1".to_string());
+        }
+
+        if self.jwt.secret.len() < MIN_JWT_SECRET_LENGTH {
+            problems.push(format!(
+                "jwt.secret must be at least {} characters (or set JWT_SECRET)",
+                MIN_JWT_SECRET_LENGTH
+            ));
+        }
+        if self.jwt.token_lifetime_hours <= 0 {
+            problems.push("jwt.token_lifetime_hours must be positive".to_string());
+        }
+
+        for origin in &self.cors.allowed_origins {
+            if origin != "*" && reqwest::Url::parse(origin).is_err() {
+                problems.push(format!(
+                    "cors.allowed_origins: {:?} is not a valid origin",
+                    origin
+                ));
+            }
+        }
+
+        if self.limits.json_payload_bytes == 0 {
+            problems.push("limits.json_payload_bytes must be at least 1".to_string());
+        }
+
+        let rate_limits = &self.rate_limit;
+        for (scope, settings) in [
+            ("auth", rate_limits.auth),
+            ("user", rate_limits.user),
+            ("me", rate_limits.me),
+            ("admin", rate_limits.admin),
+        ] {
+            if settings.capacity == 0 || settings.refill_per_second <= 0.0 {
+                problems.push(format!(
+                    "rate_limit.{} needs a positive capacity and refill_per_second",
+                    scope
+                ));
+            }
+        }
+
+        if self.password.min_length == 0 || self.password.min_length > self.password.max_length {
+            problems
+                .push("password.min_length must be between 1 and password.max_length".to_string());
+        }
+
+        if self.logging.otlp_endpoint.is_some() && !cfg!(feature = "otlp") {
+            problems
+                .push("logging.otlp_endpoint needs a build with the `otlp` feature".to_string());
+        }
+
+        let response_cache = &self.response_cache;
+        if response_cache.enabled && (response_cache.capacity == 0 || response_cache.ttl_secs == 0)
+        {
+            problems.push(
+                "response_cache needs a capacity and ttl_secs of at least 1 when enabled"
+                    .to_string(),
+            );
+        }
+
+        let webhooks = &self.webhooks;
+        if webhooks.poll_interval_secs == 0
+            || webhooks.batch_size < 1
+            || webhooks.timeout_secs == 0
+            || webhooks.max_attempts < 1
+        {
+            problems.push(
+                "webhooks.poll_interval_secs, batch_size, timeout_secs and max_attempts must be at least 1"
+                    .to_string(),
+            );
+        }
+        // a failing endpoint would otherwise be retried on every poll
+        if webhooks.backoff_secs == 0 || webhooks.max_backoff_secs < webhooks.backoff_secs {
+            problems.push(
+                "webhooks.backoff_secs must be at least 1 and not exceed max_backoff_secs"
+                    .to_string(),
+            );
+        }
+
+        if self.news_stream.log_size == 0 || self.news_stream.keep_alive_secs == 0 {
+            problems
+                .push("news_stream.log_size and keep_alive_secs must be at least 1".to_string());
+        }
+
+        if self.edit_locks.ttl_secs == 0 {
+            problems.push("edit_locks.ttl_secs must be at least 1".to_string());
+        }
+
+        if self.trash.retention_days < 0 {
+            problems.push("trash.retention_days must not be negative".to_string());
+        }
+        if self.trash.purge_interval_secs == 0 {
+            problems.push("trash.purge_interval_secs must be at least 1".to_string());
+        }
+
+        if self.maintenance.cleanup_interval_secs == Some(0) {
+            problems.push("maintenance.cleanup_interval_secs must be at least 1".to_string());
+        }
+        if self.maintenance.retention_days < 0 {
+            problems.push("maintenance.retention_days must not be negative".to_string());
+        }
+
+        if let Some(oidc) = &self.oidc {
+            for (key, value) in [
+                ("issuer_url", &oidc.issuer_url),
+                ("client_id", &oidc.client_id),
+                ("redirect_url", &oidc.redirect_url),
+            ] {
+                if value.trim().is_empty() {
+                    problems.push(format!("oidc.{} is required when oidc is configured", key));
+                }
+            }
+        }
+
+        if problems.is_empty() {
+            Ok(())
+        } else {
+            Err(ConfigError::Invalid(problems))
+        }
+    }
+}
@@ -1,9 +1,131 @@
+use std::time::Duration;
+
+use actix_web::error::BlockingError;
+use actix_web::web;
+use diesel::migration::{Migration, MigrationSource};
+use diesel::pg::Pg;
+use diesel::sql_types::BigInt;
 use diesel::{prelude::*, r2d2::ConnectionManager};
+use diesel_migrations::{embed_migrations, EmbeddedMigrations, MigrationHarness};
+use thiserror::Error;
+
+use crate::config::DatabaseConfig;
 
 pub type DBPool = r2d2::Pool<ConnectionManager<PgConnection>>;
+pub type DBConnection = r2d2::PooledConnection<ConnectionManager<PgConnection>>;
+
+pub fn establish_connection(config: &DatabaseConfig) -> DBPool {
+    let manager = ConnectionManager::<PgConnection>::new(&config.url);
+    r2d2::Pool::builder()
+        .max_size(config.max_connections)
+        .min_idle(config.min_idle)
+        .connection_timeout(Duration::from_secs(config.connection_timeout_secs))
+        .idle_timeout(config.idle_timeout_secs.map(Duration::from_secs))
+        .max_lifetime(config.max_lifetime_secs.map(Duration::from_secs))
+        .build(manager)
+        .unwrap_or_else(|e| panic!("Failed to create pool: {}", e))
+}
+
+/// The query never ran, no connection was available or the blocking pool is gone.
+#[derive(Debug, Error)]
+pub enum PoolError {
+    #[error("{0}")]
+    Connection(#[from] r2d2::Error),
+    #[error("{0}")]
+    Blocking(#[from] BlockingError),
+}
+
+/// Runs `query` with a pooled connection on the blocking thread pool, so slow
+/// queries (and waiting for a free connection) don't stall the async workers.
+pub async fn run<T, E, F>(pool: &DBPool, query: F) -> Result<T, E>
+where
+    F: FnOnce(&mut PgConnection) -> Result<T, E> + Send + 'static,
+    T: Send + 'static,
+    E: From<PoolError> + Send + 'static,
+{
+    let pool = pool.clone();
+    web::block(move || {
+        let mut conn = pool.get().map_err(PoolError::from)?;
+        query(&mut conn)
+    })
+    .await
+    .map_err(PoolError::from)?
+}
+
+/// Migrations compiled into the binary, so deploys don't need the diesel CLI.
+pub const MIGRATIONS: EmbeddedMigrations = embed_migrations!("migrations");
+
+// arbitrary key for the migration advisory lock, shared by every replica
+const MIGRATION_LOCK_KEY: i64 = 0x006e_6577_736d_6967;
+
+pub type MigrationResult<T> = Result<T, Box<dyn std::error::Error + Send + Sync>>;
+
+/// A migration and whether it has been applied.
+#[derive(Debug)]
+pub struct MigrationStatus {
+    pub name: String,
+    pub applied: bool,
+}
+
+// hold the advisory lock while migrating so replicas starting together don't race
+fn with_migration_lock<T>(
+    conn: &mut PgConnection,
+    migrate: impl FnOnce(&mut PgConnection) -> MigrationResult<T>,
+) -> MigrationResult<T> {
+    diesel::sql_query("SELECT pg_advisory_lock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(conn)?;
+
+    let result = migrate(conn);
+
+    diesel::sql_query("SELECT pg_advisory_unlock($1)")
+        .bind::<BigInt, _>(MIGRATION_LOCK_KEY)
+        .execute(conn)?;
+
+    result
+}
+
+// apply every pending migration, returning the versions that ran
+pub fn run_migrations(conn: &mut PgConnection) -> MigrationResult<Vec<String>> {
+    with_migration_lock(conn, |conn| {
+        let applied = conn.run_pending_migrations(MIGRATIONS)?;
+        Ok(applied.iter().map(ToString::to_string).collect())
+    })
+}
+
+// revert the most recently applied migration
+pub fn revert_migration(conn: &mut PgConnection) -> MigrationResult<String> {
+    with_migration_lock(conn, |conn| {
+        let reverted = conn.revert_last_migration(MIGRATIONS)?;
+        Ok(reverted.to_string())
+    })
+}
+
+pub fn migration_status(conn: &mut PgConnection) -> MigrationResult<Vec<MigrationStatus>> {
+    let applied: Vec<String> = conn
+        .applied_migrations()?
+        .iter()
+        .map(ToString::to_string)
+        .collect();
+
+    Ok(MigrationSource::<Pg>::migrations(&MIGRATIONS)?
+        .iter()
+        .map(|migration| MigrationStatus {
+            name: migration.name().to_string(),
+            applied: applied.contains(&migration.name().version().to_string()),
+        })
+        .collect())
+}
+
+// newest applied migration, None on an empty database
+pub fn schema_version(conn: &mut PgConnection) -> MigrationResult<Option<String>> {
+    Ok(conn
+        .applied_migrations()?
+        .iter()
+        .map(ToString::to_string)
+        .max())
+}
 
-pub fn establish_connection() -> DBPool {
-    let database_url = std::env::var("DATABASE_URL").expect("DATABASE_URL must be set");
-    let manager = ConnectionManager::<PgConnection>::new(database_url);
-    r2d2::Pool::builder().build(manager).expect("Failed to create pool.")
+pub fn has_pending_migrations(conn: &mut PgConnection) -> MigrationResult<bool> {
+    conn.has_pending_migration(MIGRATIONS)
 }
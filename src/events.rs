@@ -0,0 +1,65 @@
+use std::sync::{Arc, RwLock};
+
+/// A change to the published content, announced after it was saved.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ContentEvent {
+    NewsCreated { id: i32 },
+    NewsUpdated { id: i32 },
+    NewsDeleted { id: i32 },
+    // taken out of the trash
+    NewsRestored { id: i32 },
+    CategoryCreated { id: i32 },
+    CategoryUpdated { id: i32 },
+    CategoryDeleted { id: i32 },
+    CategoryRestored { id: i32 },
+}
+
+impl ContentEvent {
+    /// The surrogate keys of the responses the change makes stale. Articles are tagged with
+    /// their categories, so a category change reaches the articles filed under it.
+    pub fn keys(&self) -> Vec<String> {
+        match *self {
+            ContentEvent::NewsCreated { .. } => vec!["news-list".to_string()],
+            ContentEvent::NewsUpdated { id }
+            | ContentEvent::NewsDeleted { id }
+            | ContentEvent::NewsRestored { id } => {
+                vec!["news-list".to_string(), format!("news-{}", id)]
+            }
+            ContentEvent::CategoryCreated { .. } => vec!["category-list".to_string()],
+            ContentEvent::CategoryUpdated { id }
+            | ContentEvent::CategoryDeleted { id }
+            | ContentEvent::CategoryRestored { id } => {
+                vec!["category-list".to_string(), format!("category-{}", id)]
+            }
+        }
+    }
+}
+
+/// Something that reacts to content changes, called on the thread that made the change.
+pub trait Subscriber: Send + Sync {
+    fn notify(&self, event: &ContentEvent);
+}
+
+/// Hands every change to the subscribers before the write responds, so a client never reads
+/// what it just changed from a stale copy. Shared by all workers as `web::Data<EventBus>`.
+#[derive(Default)]
+pub struct EventBus {
+    subscribers: RwLock<Vec<Arc<dyn Subscriber>>>,
+}
+
+impl EventBus {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn subscribe(&self, subscriber: Arc<dyn Subscriber>) {
+        self.subscribers.write().unwrap().push(subscriber);
+    }
+
+    pub fn publish(&self, event: ContentEvent) {
+        tracing::debug!(?event, "Content changed");
+        for subscriber in self.subscribers.read().unwrap().iter() {
+            subscriber.notify(&event);
+        }
+    }
+}
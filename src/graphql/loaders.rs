@@ -0,0 +1,123 @@
+use std::collections::HashMap;
+use std::hash::Hash;
+
+use actix_web::web;
+use async_graphql::dataloader::Loader;
+
+use crate::graphql::run;
+use crate::models::category::Category;
+use crate::models::news::News;
+use crate::models::user::User;
+use crate::repository::{NewsRepository, UserRepository};
+
+// one query per relation and request, however many articles a response lists
+
+pub struct AuthorLoader(pub web::Data<dyn UserRepository>);
+
+impl Loader<i32> for AuthorLoader {
+    type Value = User;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, ids: &[i32]) -> Result<HashMap<i32, User>, Self::Error> {
+        let ids = ids.to_vec();
+        let users = run(&self.0, move |repo| repo.find_many(&ids)).await?;
+        Ok(users.into_iter().map(|user| (user.id, user)).collect())
+    }
+}
+
+/// One page of the articles of an author or category.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct NewsPageKey {
+    pub id: i32,
+    pub first: i32,
+    pub offset: i32,
+}
+
+pub struct NewsByAuthor(pub web::Data<dyn NewsRepository>);
+
+impl Loader<NewsPageKey> for NewsByAuthor {
+    type Value = Vec<News>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[NewsPageKey],
+    ) -> Result<HashMap<NewsPageKey, Vec<News>>, Self::Error> {
+        let mut pages = HashMap::new();
+        for ((first, offset), author_ids) in by_page(keys) {
+            let news = run(&self.0, move |repo| {
+                repo.by_authors(&author_ids, offset.into(), first.into())
+            })
+            .await?;
+            for (author_id, news) in group(news.into_iter().map(|news| (news.author_id, news))) {
+                pages.insert(
+                    NewsPageKey {
+                        id: author_id,
+                        first,
+                        offset,
+                    },
+                    news,
+                );
+            }
+        }
+        Ok(pages)
+    }
+}
+
+pub struct CategoriesByNews(pub web::Data<dyn NewsRepository>);
+
+impl Loader<i32> for CategoriesByNews {
+    type Value = Vec<Category>;
+    type Error = async_graphql::Error;
+
+    async fn load(&self, news_ids: &[i32]) -> Result<HashMap<i32, Vec<Category>>, Self::Error> {
+        let news_ids = news_ids.to_vec();
+        let links = run(&self.0, move |repo| repo.categories_of(&news_ids)).await?;
+        Ok(group(links))
+    }
+}
+
+pub struct NewsByCategory(pub web::Data<dyn NewsRepository>);
+
+impl Loader<NewsPageKey> for NewsByCategory {
+    type Value = Vec<News>;
+    type Error = async_graphql::Error;
+
+    async fn load(
+        &self,
+        keys: &[NewsPageKey],
+    ) -> Result<HashMap<NewsPageKey, Vec<News>>, Self::Error> {
+        let mut pages = HashMap::new();
+        for ((first, offset), category_ids) in by_page(keys) {
+            let links = run(&self.0, move |repo| {
+                repo.news_in(&category_ids, offset.into(), first.into())
+            })
+            .await?;
+            for (category_id, news) in group(links) {
+                pages.insert(
+                    NewsPageKey {
+                        id: category_id,
+                        first,
+                        offset,
+                    },
+                    news,
+                );
+            }
+        }
+        Ok(pages)
+    }
+}
+
+// one query per page size, the same page of every author or category is read together
+fn by_page(keys: &[NewsPageKey]) -> HashMap<(i32, i32), Vec<i32>> {
+    group(keys.iter().map(|key| ((key.first, key.offset), key.id)))
+}
+
+// keeps the order the repository returned the rows in
+fn group<K: Eq + Hash, T>(rows: impl IntoIterator<Item = (K, T)>) -> HashMap<K, Vec<T>> {
+    let mut groups: HashMap<K, Vec<T>> = HashMap::new();
+    for (key, row) in rows {
+        groups.entry(key).or_default().push(row);
+    }
+    groups
+}
@@ -0,0 +1,158 @@
+use std::sync::Arc;
+
+use actix_web::{web, HttpMessage, HttpRequest};
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{EmptySubscription, ErrorExtensions, Schema};
+
+use crate::config::Config;
+use crate::events::EventBus;
+use crate::middleware::rate_limit::{RateLimitKey, RateLimitPolicy, RateLimitStore};
+use crate::repository::{self, CategoryRepository, NewsRepository, UserRepository};
+use crate::utils::edit_rooms::EditRooms;
+use crate::utils::error_response::AppError;
+use crate::utils::jwt::Claims;
+
+mod loaders;
+mod mutation;
+mod query;
+mod types;
+
+use loaders::{AuthorLoader, CategoriesByNews, NewsByAuthor, NewsByCategory};
+pub use mutation::Mutation;
+pub use query::Query;
+
+pub type NewsSchema = Schema<Query, Mutation, EmptySubscription>;
+
+// deep enough for news -> categories -> news -> author, the rest is abuse
+const MAX_DEPTH: usize = 8;
+const MAX_COMPLEXITY: usize = 250;
+
+pub fn schema() -> NewsSchema {
+    Schema::build(Query, Mutation, EmptySubscription)
+        .limit_depth(MAX_DEPTH)
+        .limit_complexity(MAX_COMPLEXITY)
+        .finish()
+}
+
+/// Registers the schema as `web::Data<NewsSchema>`.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.app_data(web::Data::new(schema()));
+}
+
+// marks requests that came in over GET, which must not change anything
+pub struct ReadOnly;
+
+/// The per-user bucket of the REST writes, mutations take from it too since they share the
+/// endpoint with the public queries.
+pub struct WriteLimit {
+    store: Arc<dyn RateLimitStore>,
+    policy: Arc<RateLimitPolicy>,
+}
+
+impl WriteLimit {
+    fn new(config: &Config, store: Arc<dyn RateLimitStore>) -> Self {
+        let settings = config.rate_limit.admin;
+        WriteLimit {
+            store,
+            policy: Arc::new(RateLimitPolicy::new(
+                "admin",
+                settings.capacity,
+                settings.refill_per_second,
+                RateLimitKey::User,
+            )),
+        }
+    }
+
+    // fails open like the rate limit middleware, a broken store shouldn't stop the writes
+    async fn acquire(&self, user_id: i32) -> Result<(), AppError> {
+        let (store, policy) = (self.store.clone(), self.policy.clone());
+        let decision = web::block(move || store.acquire(&policy.user_bucket(user_id), &policy))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|decision| decision);
+        match decision {
+            Ok(decision) if !decision.allowed => {
+                Err(AppError::RateLimitedError(decision.retry_after))
+            }
+            Ok(_) => Ok(()),
+            Err(e) => {
+                log::error!("Rate limit store error: {}", e);
+                Ok(())
+            }
+        }
+    }
+}
+
+fn app_data<T: ?Sized + 'static>(req: &HttpRequest) -> Result<web::Data<T>, AppError> {
+    req.app_data::<web::Data<T>>()
+        .cloned()
+        .ok_or_else(|| AppError::DatabaseError("No repository registered".into()))
+}
+
+/// Adds what the resolvers need to a request: the repositories, fresh dataloaders so nothing is
+/// cached across requests, the config and write limit, the event bus and the caller's claims when
+/// they signed in.
+pub fn prepare(
+    req: &HttpRequest,
+    request: async_graphql::Request,
+) -> Result<async_graphql::Request, AppError> {
+    let news = app_data::<dyn NewsRepository>(req)?;
+    let categories = app_data::<dyn CategoryRepository>(req)?;
+    let users = app_data::<dyn UserRepository>(req)?;
+    let spawn = |task| {
+        actix_web::rt::spawn(task);
+    };
+
+    let mut request = request
+        .data(DataLoader::new(AuthorLoader(users.clone()), spawn))
+        .data(DataLoader::new(NewsByAuthor(news.clone()), spawn))
+        .data(DataLoader::new(CategoriesByNews(news.clone()), spawn))
+        .data(DataLoader::new(NewsByCategory(news.clone()), spawn))
+        .data(news)
+        .data(categories)
+        .data(users);
+    if let Some(config) = req.app_data::<web::Data<Config>>() {
+        if let Some(store) = req.app_data::<web::Data<dyn RateLimitStore>>() {
+            request = request.data(WriteLimit::new(config, store.clone().into_inner()));
+        }
+        request = request.data(config.clone());
+    }
+    if let Some(events) = req.app_data::<web::Data<EventBus>>() {
+        request = request.data(events.clone());
+    }
+    if let Some(edit_rooms) = req.app_data::<web::Data<EditRooms>>() {
+        request = request.data(edit_rooms.clone());
+    }
+    if let Some(claims) = req.extensions().get::<Claims>() {
+        request = request.data(claims.clone());
+    }
+    Ok(request)
+}
+
+// the problem detail becomes the message, its code goes to `extensions.code`
+fn error(error: AppError) -> async_graphql::Error {
+    let problem = error.problem();
+    async_graphql::Error::new(problem.detail).extend_with(|_, extensions| {
+        extensions.set("code", problem.code);
+        if let Some(errors) = problem.errors.clone() {
+            if let Ok(errors) = async_graphql::Value::from_json(errors) {
+                extensions.set("errors", errors);
+            }
+        }
+        if let Some(current) = problem.current.clone() {
+            if let Ok(current) = async_graphql::Value::from_json(current) {
+                extensions.set("current", current);
+            }
+        }
+    })
+}
+
+// `repository::run` for resolvers
+async fn run<R, T, F>(repository: &web::Data<R>, call: F) -> async_graphql::Result<T>
+where
+    R: ?Sized + Send + Sync + 'static,
+    T: Send + 'static,
+    F: FnOnce(&R) -> Result<T, AppError> + Send + 'static,
+{
+    repository::run(repository, call).await.map_err(error)
+}
@@ -0,0 +1,197 @@
+use actix_web::http::header::{EntityTag, IfMatch};
+use actix_web::web;
+use async_graphql::{Context, InputObject, Object, Result};
+use chrono::NaiveDateTime;
+
+use crate::config::Config;
+use crate::events::{ContentEvent, EventBus};
+use crate::graphql::types::NewsNode;
+use crate::graphql::{error, run, ReadOnly, WriteLimit};
+use crate::models::news::{NewNews, NewsChangeset};
+use crate::repository::NewsRepository;
+use crate::utils::edit_rooms::EditRooms;
+use crate::utils::error_response::AppError;
+use crate::utils::jwt::Claims;
+use crate::utils::metrics::{record_article_created, record_article_deleted};
+use crate::utils::precondition;
+
+/// Same fields as `POST /api/v1/news`.
+#[derive(InputObject)]
+pub struct CreateNewsInput {
+    #[graphql(validator(min_length = 1, max_length = 100))]
+    pub title: String,
+    #[graphql(validator(min_length = 1))]
+    pub content: String,
+    pub author_id: i32,
+    #[graphql(default)]
+    pub category_ids: Vec<i32>,
+}
+
+/// Fields that are left out keep their value, `categoryIds` replaces the links.
+#[derive(InputObject)]
+pub struct UpdateNewsInput {
+    #[graphql(validator(min_length = 1, max_length = 100))]
+    pub title: Option<String>,
+    #[graphql(validator(min_length = 1))]
+    pub content: Option<String>,
+    pub category_ids: Option<Vec<i32>>,
+}
+
+pub struct Mutation;
+
+// the caller, when they may write at all, API keys without the write scope can't POST
+fn writer<'a>(ctx: &Context<'a>) -> Result<&'a Claims> {
+    if ctx.data_opt::<ReadOnly>().is_some() {
+        return Err(error(AppError::BadRequestError(
+            "Mutations have to be sent with POST".into(),
+        )));
+    }
+    ctx.data_opt::<Claims>()
+        .ok_or_else(|| error(AppError::UnauthorizedError("No token provided".into())))
+}
+
+// a writer that still has tokens in the bucket their REST writes take from
+async fn throttled<'a>(ctx: &Context<'a>) -> Result<&'a Claims> {
+    let claims = writer(ctx)?;
+    if let Some(limit) = ctx.data_opt::<WriteLimit>() {
+        limit.acquire(claims.sub).await.map_err(error)?;
+    }
+    Ok(claims)
+}
+
+// fails unless the caller may change the article, the rules of the REST handlers. Returns the
+// version the write has to find, `ifVersion` works like If-Match there.
+async fn editable(
+    ctx: &Context<'_>,
+    id: i32,
+    if_version: Option<String>,
+) -> Result<Option<NaiveDateTime>> {
+    let claims = writer(ctx)?;
+    let repository = ctx.data::<web::Data<dyn NewsRepository>>()?;
+    let news = run(repository, move |repo| repo.find(id))
+        .await?
+        .ok_or_else(|| error(AppError::NotFoundError("News not found!".into())))?;
+
+    if !claims.can_edit(news.author_id) {
+        return Err(error(AppError::ForbiddenError("Not authorized!".into())));
+    }
+
+    let if_match = match if_version {
+        Some(version) => {
+            let tag = format!("\"{}\"", version)
+                .parse::<EntityTag>()
+                .map_err(|_| error(AppError::BadRequestError("Invalid ifVersion".into())))?;
+            Some(IfMatch::Items(vec![tag]))
+        }
+        None => None,
+    };
+    let required = ctx
+        .data_opt::<web::Data<Config>>()
+        .is_some_and(|config| config.concurrency.require_if_match);
+    precondition::check(if_match, required, &news).map_err(error)
+}
+
+// when edit locks are enforced, saves need the lock of the article like in the REST handler
+fn lock_held(ctx: &Context<'_>, id: i32) -> Result<()> {
+    let enforce = ctx
+        .data_opt::<web::Data<Config>>()
+        .is_some_and(|config| config.edit_locks.enforce);
+    match ctx.data_opt::<web::Data<EditRooms>>() {
+        Some(edit_rooms) if enforce => edit_rooms.check_holder(id, writer(ctx)?).map_err(error),
+        _ => Ok(()),
+    }
+}
+
+// tells the response cache and the other subscribers, like the REST handlers do
+fn publish(ctx: &Context<'_>, event: ContentEvent) {
+    if let Some(events) = ctx.data_opt::<web::Data<EventBus>>() {
+        events.publish(event);
+    }
+}
+
+// a conditional write found nothing, tells whether the article is gone or changed
+async fn missed(ctx: &Context<'_>, id: i32, not_found: &str) -> Result<async_graphql::Error> {
+    let repository = ctx.data::<web::Data<dyn NewsRepository>>()?;
+    let current = run(repository, move |repo| repo.find(id)).await?;
+    Ok(error(precondition::missed(current, not_found)))
+}
+
+/// Mirrors the `/api/v1/news` writes and their authorization.
+#[Object]
+impl Mutation {
+    /// Admins only.
+    async fn create_news(&self, ctx: &Context<'_>, input: CreateNewsInput) -> Result<NewsNode> {
+        if !throttled(ctx).await?.is_admin {
+            return Err(error(AppError::ForbiddenError(
+                "Admin access required".into(),
+            )));
+        }
+
+        let repository = ctx.data::<web::Data<dyn NewsRepository>>()?;
+        let new_news = NewNews {
+            title: input.title,
+            content: input.content,
+            author_id: input.author_id,
+        };
+        let news = run(repository, move |repo| {
+            repo.create(new_news, &input.category_ids)
+        })
+        .await?;
+        record_article_created();
+        publish(ctx, ContentEvent::NewsCreated { id: news.id });
+
+        Ok(NewsNode(news))
+    }
+
+    /// The author or an admin, `ifVersion` is the `version` the change is based on.
+    async fn update_news(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        input: UpdateNewsInput,
+        if_version: Option<String>,
+    ) -> Result<NewsNode> {
+        throttled(ctx).await?;
+        let version = editable(ctx, id, if_version).await?;
+        lock_held(ctx, id)?;
+
+        let repository = ctx.data::<web::Data<dyn NewsRepository>>()?;
+        let changeset = NewsChangeset {
+            title: input.title,
+            content: input.content,
+            updated_at: chrono::Utc::now().naive_utc(),
+        };
+        let news = run(repository, move |repo| {
+            repo.update(id, changeset, input.category_ids.as_deref(), version)
+        })
+        .await?;
+
+        match news {
+            Some(news) => {
+                publish(ctx, ContentEvent::NewsUpdated { id });
+                Ok(NewsNode(news))
+            }
+            None => Err(missed(ctx, id, "News not found!").await?),
+        }
+    }
+
+    /// The author or an admin, true once the article is gone. `ifVersion` as for updates.
+    async fn delete_news(
+        &self,
+        ctx: &Context<'_>,
+        id: i32,
+        if_version: Option<String>,
+    ) -> Result<bool> {
+        throttled(ctx).await?;
+        let version = editable(ctx, id, if_version).await?;
+
+        let repository = ctx.data::<web::Data<dyn NewsRepository>>()?;
+        if !run(repository, move |repo| repo.delete(id, version)).await? {
+            return Err(missed(ctx, id, "News not found").await?);
+        }
+        record_article_deleted();
+        publish(ctx, ContentEvent::NewsDeleted { id });
+
+        Ok(true)
+    }
+}
@@ -0,0 +1,80 @@
+use actix_web::web;
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{Context, Object, Result};
+
+use crate::graphql::loaders::{AuthorLoader, NewsByAuthor, NewsPageKey};
+use crate::graphql::run;
+use crate::graphql::types::{AuthorNode, CategoryNode, NewsFilterInput, NewsNode, NewsPage};
+use crate::repository::{CategoryRepository, NewsFilter, NewsRepository, UserRepository};
+
+pub struct Query;
+
+/// Everything here is public, like the `/api/v1` reads.
+#[Object]
+impl Query {
+    /// Articles, newest first.
+    async fn news(
+        &self,
+        ctx: &Context<'_>,
+        filter: Option<NewsFilterInput>,
+        #[graphql(default = 20, validator(minimum = 1, maximum = 100))] first: i32,
+        #[graphql(default = 0, validator(minimum = 0))] offset: i32,
+    ) -> Result<NewsPage> {
+        let filter: NewsFilter = filter.unwrap_or_default().into();
+        let repository = ctx.data::<web::Data<dyn NewsRepository>>()?;
+        let (news, total_count) = run(repository, move |repo| {
+            repo.page(&filter, offset.into(), first.into())
+        })
+        .await?;
+
+        Ok(NewsPage {
+            items: news.into_iter().map(NewsNode).collect(),
+            total_count,
+        })
+    }
+
+    /// One article, null when there is no such article.
+    async fn article(&self, ctx: &Context<'_>, id: i32) -> Result<Option<NewsNode>> {
+        let repository = ctx.data::<web::Data<dyn NewsRepository>>()?;
+        let news = run(repository, move |repo| repo.find(id)).await?;
+        Ok(news.map(NewsNode))
+    }
+
+    /// All categories, by name.
+    async fn categories(&self, ctx: &Context<'_>) -> Result<Vec<CategoryNode>> {
+        let repository = ctx.data::<web::Data<dyn CategoryRepository>>()?;
+        let categories = run(repository, |repo| repo.list()).await?;
+        Ok(categories.into_iter().map(CategoryNode).collect())
+    }
+
+    async fn category(&self, ctx: &Context<'_>, id: i32) -> Result<Option<CategoryNode>> {
+        let repository = ctx.data::<web::Data<dyn CategoryRepository>>()?;
+        let category = run(repository, move |repo| repo.find(id)).await?;
+        Ok(category.map(CategoryNode))
+    }
+
+    /// Users who wrote at least one article, by username.
+    async fn authors(&self, ctx: &Context<'_>) -> Result<Vec<AuthorNode>> {
+        let repository = ctx.data::<web::Data<dyn UserRepository>>()?;
+        let authors = run(repository, |repo| repo.authors()).await?;
+        Ok(authors.into_iter().map(AuthorNode).collect())
+    }
+
+    /// Null for users who haven't written anything.
+    async fn author(&self, ctx: &Context<'_>, id: i32) -> Result<Option<AuthorNode>> {
+        let Some(user) = ctx.data::<DataLoader<AuthorLoader>>()?.load_one(id).await? else {
+            return Ok(None);
+        };
+        // their latest article is enough to tell
+        let key = NewsPageKey {
+            id,
+            first: 1,
+            offset: 0,
+        };
+        let news = ctx
+            .data::<DataLoader<NewsByAuthor>>()?
+            .load_one(key)
+            .await?;
+        Ok(news.map(|_| AuthorNode(user)))
+    }
+}
@@ -0,0 +1,179 @@
+use async_graphql::dataloader::DataLoader;
+use async_graphql::{Context, InputObject, Object, Result, SimpleObject};
+use chrono::NaiveDateTime;
+
+use crate::graphql::loaders::{
+    AuthorLoader, CategoriesByNews, NewsByAuthor, NewsByCategory, NewsPageKey,
+};
+use crate::models::category::Category;
+use crate::models::news::News;
+use crate::models::user::User;
+use crate::repository::NewsFilter;
+use crate::utils::precondition::etag;
+
+pub struct NewsNode(pub News);
+
+/// An article.
+#[Object(name = "News")]
+impl NewsNode {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn title(&self) -> &str {
+        &self.0.title
+    }
+
+    async fn content(&self) -> &str {
+        &self.0.content
+    }
+
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> NaiveDateTime {
+        self.0.updated_at
+    }
+
+    /// Changes with every edit, pass it as `ifVersion` so edits made meanwhile aren't overwritten.
+    async fn version(&self) -> String {
+        etag(self.0.updated_at).tag().to_string()
+    }
+
+    async fn author(&self, ctx: &Context<'_>) -> Result<AuthorNode> {
+        ctx.data::<DataLoader<AuthorLoader>>()?
+            .load_one(self.0.author_id)
+            .await?
+            .map(AuthorNode)
+            .ok_or_else(|| format!("Author {} not found", self.0.author_id).into())
+    }
+
+    /// The categories the article is filed under, by name.
+    async fn categories(&self, ctx: &Context<'_>) -> Result<Vec<CategoryNode>> {
+        let categories = ctx
+            .data::<DataLoader<CategoriesByNews>>()?
+            .load_one(self.0.id)
+            .await?;
+        Ok(categories
+            .unwrap_or_default()
+            .into_iter()
+            .map(CategoryNode)
+            .collect())
+    }
+}
+
+pub struct CategoryNode(pub Category);
+
+/// An article category.
+#[Object(name = "Category")]
+impl CategoryNode {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn name(&self) -> &str {
+        &self.0.name
+    }
+
+    async fn description(&self) -> Option<&str> {
+        self.0.description.as_deref()
+    }
+
+    async fn created_at(&self) -> NaiveDateTime {
+        self.0.created_at
+    }
+
+    async fn updated_at(&self) -> NaiveDateTime {
+        self.0.updated_at
+    }
+
+    /// The ETag `GET /api/v1/categories/{id}` sends.
+    async fn version(&self) -> String {
+        etag(self.0.updated_at).tag().to_string()
+    }
+
+    /// Articles in the category, newest first.
+    // paged like `Query::news` and in the query, and costs what a full page would, so the
+    // complexity limit also bounds lists inside lists
+    #[graphql(complexity = "first as usize * child_complexity")]
+    async fn news(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 20, validator(minimum = 1, maximum = 100))] first: i32,
+        #[graphql(default = 0, validator(minimum = 0))] offset: i32,
+    ) -> Result<Vec<NewsNode>> {
+        let key = NewsPageKey {
+            id: self.0.id,
+            first,
+            offset,
+        };
+        let news = ctx
+            .data::<DataLoader<NewsByCategory>>()?
+            .load_one(key)
+            .await?;
+        Ok(news.unwrap_or_default().into_iter().map(NewsNode).collect())
+    }
+}
+
+// only what is public about a user
+pub struct AuthorNode(pub User);
+
+/// A user who writes articles.
+#[Object(name = "Author")]
+impl AuthorNode {
+    async fn id(&self) -> i32 {
+        self.0.id
+    }
+
+    async fn username(&self) -> &str {
+        &self.0.username
+    }
+
+    /// Their articles, newest first.
+    #[graphql(complexity = "first as usize * child_complexity")]
+    async fn news(
+        &self,
+        ctx: &Context<'_>,
+        #[graphql(default = 20, validator(minimum = 1, maximum = 100))] first: i32,
+        #[graphql(default = 0, validator(minimum = 0))] offset: i32,
+    ) -> Result<Vec<NewsNode>> {
+        let key = NewsPageKey {
+            id: self.0.id,
+            first,
+            offset,
+        };
+        let news = ctx
+            .data::<DataLoader<NewsByAuthor>>()?
+            .load_one(key)
+            .await?;
+        Ok(news.unwrap_or_default().into_iter().map(NewsNode).collect())
+    }
+}
+
+/// One page of articles.
+#[derive(SimpleObject)]
+pub struct NewsPage {
+    pub items: Vec<NewsNode>,
+    /// Articles matching the filter, on all pages.
+    pub total_count: i64,
+}
+
+/// Every filter that is set has to match.
+#[derive(InputObject, Default)]
+pub struct NewsFilterInput {
+    pub author_id: Option<i32>,
+    pub category_id: Option<i32>,
+    /// Part of the title, case insensitive.
+    pub title: Option<String>,
+}
+
+impl From<NewsFilterInput> for NewsFilter {
+    fn from(input: NewsFilterInput) -> Self {
+        NewsFilter {
+            author_id: input.author_id,
+            category_id: input.category_id,
+            title: input.title,
+        }
+    }
+}
@@ -1,20 +1,24 @@
-use crate::models::category::{CategoryChangeset, UpdateCategoryRequest, UpdateCategoryResponse};
-use crate::models::news::News;
-use crate::schema::categories::{self, dsl::*};
-use crate::schema::news;
-use crate::schema::news::dsl::*;
-use crate::schema::news_categories;
-use crate::utils::error_response::AppError;
+use crate::config::Config;
+use crate::events::{ContentEvent, EventBus};
+use crate::models::category::{
+    CategoryChangeset, CreateCategoryRequest, NewCategory, UpdateCategoryRequest,
+    UpdateCategoryResponse,
+};
+use crate::models::news::{NewNews, News, NewsChangeset};
+use crate::repository::{self, CategoryRepository, NewsRepository};
+use crate::utils::edit_rooms::EditRooms;
+use crate::utils::error_response::{AppError, Problem};
 use crate::utils::jwt::Claims;
-use crate::{db::DBPool, models::category::Category, models::news::NewsCategory};
+use crate::utils::metrics::{record_article_created, record_article_deleted};
+use crate::utils::precondition;
+use actix_web::http::header::ETAG;
 use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
-use chrono::Utc;
-use diesel::prelude::*;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
+use utoipa::ToSchema;
 use validator::Validate;
 
-#[derive(Deserialize)]
+#[derive(Deserialize, ToSchema)]
 pub struct NewsWithCategories {
     pub title: String,
     pub content: String,
@@ -26,46 +30,36 @@ pub struct NewsWithCategories {
         CREATE FUNCTION
 */
 
+#[utoipa::path(
+    post,
+    path = "/api/v1/news",
+    tag = "news",
+    request_body = NewsWithCategories,
+    responses(
+        (status = 201, description = "News created"),
+        (status = 422, description = "Unknown author or category", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
 pub async fn create_news(
-    pool: web::Data<DBPool>,
+    news_repository: web::Data<dyn NewsRepository>,
+    events: web::Data<EventBus>,
     news_data: web::Json<NewsWithCategories>,
 ) -> Result<HttpResponse, AppError> {
-    let mut conn = pool
-        .get()
-        .map_err(|e| AppError::DatabaseError(format!("Failed to get DB connection: {}", e)))?;
-
-    // Perform the transaction
-    let new_news = conn.transaction::<_, AppError, _>(|conn| {
-        // Insert the news item
-        let new_news = diesel::insert_into(news::table)
-            .values((
-                news::title.eq(&news_data.title),
-                news::content.eq(&news_data.content),
-                news::author_id.eq(news_data.author_id),
-                news::created_at.eq(Utc::now().naive_utc()),
-                news::updated_at.eq(Utc::now().naive_utc()),
-            ))
-            .get_result::<News>(conn)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to insert news: {}", e)))?;
-
-        // Prepare category associations
-        let category_entries: Vec<NewsCategory> = news_data
-            .category_ids
-            .iter()
-            .map(|&category_id| NewsCategory {
-                news_id: new_news.id,
-                category_id,
-            })
-            .collect();
-
-        // Insert into `news_categories`
-        diesel::insert_into(news_categories::table)
-            .values(&category_entries)
-            .execute(conn)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to insert categories: {}", e)))?;
-
-        Ok(new_news)
-    })?;
+    let news_data = news_data.into_inner();
+    let category_ids = news_data.category_ids;
+    let new_news = NewNews {
+        title: news_data.title,
+        content: news_data.content,
+        author_id: news_data.author_id,
+    };
+
+    // insert the news item together with its categories
+    let links = category_ids.clone();
+    let new_news =
+        repository::run(&news_repository, move |repo| repo.create(new_news, &links)).await?;
+    record_article_created();
+    events.publish(ContentEvent::NewsCreated { id: new_news.id });
 
     // Create successful response
     let response = json!({
@@ -77,7 +71,7 @@ pub async fn create_news(
             "author_id": new_news.author_id,
             "created_at": new_news.created_at,
             "updated_at": new_news.updated_at,
-            "categories": news_data.category_ids
+            "categories": category_ids
         }
     });
 
@@ -85,36 +79,35 @@ pub async fn create_news(
 }
 
 // create category
+#[utoipa::path(
+    post,
+    path = "/api/v1/categories",
+    tag = "categories",
+    request_body = CreateCategoryRequest,
+    responses(
+        (status = 201, description = "Category created"),
+        (status = 409, description = "The name is taken", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid category", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
 pub async fn create_category(
-    pool: web::Data<DBPool>,
-    category_data: web::Json<Category>,
+    category_repository: web::Data<dyn CategoryRepository>,
+    events: web::Data<EventBus>,
+    category_data: web::Json<CreateCategoryRequest>,
 ) -> Result<HttpResponse, AppError> {
-    // Get database connection
     // Validate input
-    if let Err(errors) = category_data.validate() {
-        return Ok(HttpResponse::BadRequest().json(errors));
-    }
+    category_data.validate()?;
 
-    let mut conn = pool
-        .get()
-        .map_err(|e| AppError::DatabaseError(format!("Failed to get DB connection: {}", e)))?;
-
-    // Construct the category item
-    let new_category = Category {
-        id: 0, // This will be replaced by the database
-        name: category_data.name.clone(),
-        description: category_data.description.clone(),
-        created_at: Utc::now().naive_utc(),
-        updated_at: Utc::now().naive_utc(),
+    let category_data = category_data.into_inner();
+    let new_category = NewCategory {
+        name: category_data.name,
+        description: category_data.description,
     };
 
-    // Perform insertion within a transaction
-    let result = conn.transaction::<_, AppError, _>(|conn| {
-        diesel::insert_into(categories::table)
-            .values(&new_category)
-            .get_result::<Category>(conn)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to create category: {}", e)))
-    })?;
+    let result =
+        repository::run(&category_repository, move |repo| repo.create(new_category)).await?;
+    events.publish(ContentEvent::CategoryCreated { id: result.id });
 
     Ok(HttpResponse::Created().json(json!({
         "message": "Category created successfully",
@@ -127,7 +120,7 @@ pub async fn create_category(
 */
 
 // struct for news update object
-#[derive(Debug, Deserialize, Validate)]
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct UpdateNewsRequest {
     #[validate(length(
         min = 1,
@@ -141,55 +134,42 @@ pub struct UpdateNewsRequest {
     pub category_ids: Option<Vec<i32>>,
 }
 
-#[derive(AsChangeset)]
-#[diesel(table_name = news)]
-struct NewsChangeset<'a> {
-    title: Option<&'a str>,
-    content: Option<&'a str>,
-    updated_at: chrono::NaiveDateTime,
-}
-
 // update response object
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UpdateNewsResponse {
     pub message: String,
     pub news: News,
 }
 
-// update the category in the news
-fn update_news_categories(
-    conn: &mut PgConnection,
-    news_ids: i32,
-    categoriy_ids: &[i32],
-) -> QueryResult<()> {
-    use crate::schema::news_categories::dsl::*;
-
-    // delete current categories
-    diesel::delete(news_categories.filter(news_id.eq(news_id))).execute(conn)?;
-
-    // get categories entries
-    let new_entries: Vec<NewsCategory> = categoriy_ids
-        .iter()
-        .map(|&cat_id| NewsCategory {
-            news_id: news_ids,
-            category_id: cat_id,
-        })
-        .collect();
-
-    // insert categories
-    diesel::insert_into(news_categories)
-        .values(&new_entries)
-        .execute(conn)?;
-
-    Ok(())
-}
-
 // update news
+#[utoipa::path(
+    patch,
+    path = "/api/v1/news/{id}",
+    tag = "news",
+    params(
+        ("id" = i32, Path, description = "News id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version the change is based on"),
+    ),
+    request_body = UpdateNewsRequest,
+    responses(
+        (status = 200, description = "News updated, with its new ETag", body = UpdateNewsResponse),
+        (status = 403, description = "Neither the author nor an admin", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such news", body = Problem, content_type = "application/problem+json"),
+        (status = 423, description = "Edit locks are enforced and you don't hold the lock of the article", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since the If-Match version, with the `current` one", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "If-Match is required and missing", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid update or unknown category", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
 pub async fn update_news(
     req: HttpRequest,
     path: web::Path<i32>,
     update_data: web::Json<UpdateNewsRequest>,
-    pool: web::Data<DBPool>,
+    news_repository: web::Data<dyn NewsRepository>,
+    events: web::Data<EventBus>,
+    edit_rooms: web::Data<EditRooms>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     let news_id = path.into_inner();
 
@@ -201,69 +181,86 @@ pub async fn update_news(
         .clone();
 
     // validate input if any fields are provided
-    if let Err(errors) = update_data.validate() {
-        return Ok(HttpResponse::BadRequest().json(errors));
-    }
+    update_data.validate()?;
 
-    let mut conn = pool
-        .get()
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
-
-    // start updating
-    let result = conn.transaction::<_, AppError, _>(|conn| {
-        // Build update query dynamically based on provided fields
-        let existing_news = news
-            .find(news_id)
-            .first::<News>(conn)
-            .optional()
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?
-            .ok_or_else(|| AppError::NotFoundError("News not found!".into()))?;
-
-        // check the user authority, only admin allowed
-        if !user_claims.is_admin && user_claims.sub != existing_news.author_id {
-            return Err(AppError::ForbiddenError("Not authorized!".into()));
-        }
+    let existing_news = repository::run(&news_repository, move |repo| repo.find(news_id))
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("News not found!".into()))?;
 
-        // Build update query dynamically based on provided fields
-        let changeset = NewsChangeset {
-            title: update_data
-                .news_title
-                .as_deref()
-                .or(Some(&existing_news.title)),
-            content: update_data
-                .news_content
-                .as_deref()
-                .or(Some(&existing_news.content)),
-            updated_at: chrono::Utc::now().naive_utc(),
-        };
-
-        // execute update
-        let updated_news: News = diesel::update(news.find(news_id))
-            .set(&changeset)
-            .get_result(conn)
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
-
-        // update categories if provided
-        if let Some(news_categories) = &update_data.category_ids {
-            update_news_categories(conn, news_id, news_categories)
-                .map_err(|e| AppError::DatabaseError(e.to_string()))?;
-        }
+    // check the user authority, only admin allowed
+    if !user_claims.can_edit(existing_news.author_id) {
+        return Err(AppError::ForbiddenError("Not authorized!".into()));
+    }
+    // someone else may be in the middle of editing it
+    if config.edit_locks.enforce {
+        edit_rooms.check_holder(news_id, &user_claims)?;
+    }
 
-        Ok(updated_news)
-    })?;
+    // don't overwrite changes the client hasn't seen
+    let version = precondition::check(
+        precondition::if_match(&req)?,
+        config.concurrency.require_if_match,
+        &existing_news,
+    )?;
+
+    // only the provided fields are changed
+    let update_data = update_data.into_inner();
+    let changeset = NewsChangeset {
+        title: update_data.news_title,
+        content: update_data.news_content,
+        updated_at: chrono::Utc::now().naive_utc(),
+    };
 
-    Ok(HttpResponse::Ok().json(UpdateNewsResponse {
-        message: "News updated successfully".to_string(),
-        news: result,
-    }))
+    let Some(result) = repository::run(&news_repository, move |repo| {
+        repo.update(
+            news_id,
+            changeset,
+            update_data.category_ids.as_deref(),
+            version,
+        )
+    })
+    .await?
+    else {
+        let current = repository::run(&news_repository, move |repo| repo.find(news_id)).await?;
+        return Err(precondition::missed(current, "News not found!"));
+    };
+    events.publish(ContentEvent::NewsUpdated { id: news_id });
+
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, precondition::etag(result.updated_at)))
+        .json(UpdateNewsResponse {
+            message: "News updated successfully".to_string(),
+            news: result,
+        }))
 }
 
-// update category
+// update category, fields that aren't sent are kept
+#[utoipa::path(
+    patch,
+    path = "/api/v1/categories/{id}",
+    tag = "categories",
+    params(
+        ("id" = i32, Path, description = "Category id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version the change is based on"),
+    ),
+    request_body = UpdateCategoryRequest,
+    responses(
+        (status = 200, description = "Category updated, with its new ETag", body = UpdateCategoryResponse),
+        (status = 404, description = "No such category", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The name is taken", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since the If-Match version, with the `current` one", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "If-Match is required and missing", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid update", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
 pub async fn update_category(
     req: HttpRequest,
     path: web::Path<i32>,
     update_data: web::Json<UpdateCategoryRequest>,
-    pool: web::Data<DBPool>,
+    category_repository: web::Data<dyn CategoryRepository>,
+    events: web::Data<EventBus>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     let category_id = path.into_inner();
 
@@ -275,60 +272,78 @@ pub async fn update_category(
         .clone();
 
     // Validate input
-    if let Err(errors) = update_data.validate() {
-        return Ok(HttpResponse::BadRequest().json(errors));
-    }
+    update_data.validate()?;
 
-    // Connect to database
-    let mut conn = pool
-        .get()
-        .map_err(|e| AppError::DatabaseError(e.to_string()))?;
-
-    // Start updating category
-    let result = conn.transaction::<_, AppError, _>(|conn| {
-        // Fetch existing category
-        let existing_category = categories
-            .find(category_id)
-            .first::<Category>(conn)
-            .optional()
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?
+    // Fetch existing category
+    let existing_category =
+        repository::run(&category_repository, move |repo| repo.find(category_id))
+            .await?
             .ok_or_else(|| AppError::NotFoundError("Category not found!".into()))?;
 
-        // Check if user is admin
-        if !user_claims.is_admin {
-            return Err(AppError::ForbiddenError("Only admin can update!".into()));
-        }
+    // Check if user is admin
+    if !user_claims.is_admin {
+        return Err(AppError::ForbiddenError("Only admin can update!".into()));
+    }
+
+    let version = precondition::check(
+        precondition::if_match(&req)?,
+        config.concurrency.require_if_match,
+        &existing_category,
+    )?;
+
+    let update_data = update_data.into_inner();
+    let changeset = CategoryChangeset {
+        name: update_data.name,
+        description: update_data.description,
+        updated_at: chrono::Utc::now().naive_utc(),
+    };
 
-        // Build query dynamically based on provided fields
-        let changeset = CategoryChangeset {
-            name: Some(update_data.name.clone()), // Use the updated name
-            description: Some(update_data.description.clone()), // Use the updated description
-            updated_at: chrono::Utc::now().naive_utc(),
-        };
-
-        // Execute update
-        diesel::update(categories::table.find(category_id))
-            .set(&changeset) // Use the changeset directly
-            .execute(conn)
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
-
-        Ok(existing_category) // Return the updated category
-    })?;
-
-    Ok(HttpResponse::Ok().json(UpdateCategoryResponse {
-        message: "Category updated successfully".to_string(),
-        category: result,
-    }))
+    let Some(result) = repository::run(&category_repository, move |repo| {
+        repo.update(category_id, changeset, version)
+    })
+    .await?
+    else {
+        let current =
+            repository::run(&category_repository, move |repo| repo.find(category_id)).await?;
+        return Err(precondition::missed(current, "Category not found!"));
+    };
+    events.publish(ContentEvent::CategoryUpdated { id: category_id });
+
+    Ok(HttpResponse::Ok()
+        .insert_header((ETAG, precondition::etag(result.updated_at)))
+        .json(UpdateCategoryResponse {
+            message: "Category updated successfully".to_string(),
+            category: result,
+        }))
 }
 
 /*
         DELETE FUNCTION
 */
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/news/{id}",
+    tag = "news",
+    params(
+        ("id" = i32, Path, description = "News id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version the change is based on"),
+    ),
+    responses(
+        (status = 200, description = "News moved to the trash"),
+        (status = 403, description = "Neither the author nor an admin", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such news", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since the If-Match version, with the `current` one", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "If-Match is required and missing", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
 pub async fn delete_news(
     req: HttpRequest,
-    pool: web::Data<DBPool>,
+    news_repository: web::Data<dyn NewsRepository>,
+    events: web::Data<EventBus>,
     news_id: web::Path<i32>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     // extract user claims from JWT
     let user_claims = req
@@ -337,51 +352,61 @@ pub async fn delete_news(
         .ok_or_else(|| AppError::UnauthorizedError("Unauthorized access".into()))?
         .clone();
 
-    let mut conn = pool
-        .get()
-        .map_err(|e| AppError::DatabaseError(format!("Database connection error: {}", e)))?;
-
-    // perform deletion within a transaction
-    conn.transaction::<_, AppError, _>(|conn| {
-        // first check if news exists and user has permission
-        let news_item = news::table
-            .find(*news_id)
-            .first::<News>(conn)
-            .optional()
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?
-            .ok_or_else(|| AppError::NotFoundError("News not found".into()))?;
-
-        // check authorization
-        if !user_claims.is_admin && user_claims.sub != news_item.author_id {
-            return Err(AppError::ForbiddenError(
-                "Not authorized to delete this news".into(),
-            ));
-        }
+    let news_id = news_id.into_inner();
+
+    // first check if news exists and user has permission
+    let news_item = repository::run(&news_repository, move |repo| repo.find(news_id))
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("News not found".into()))?;
 
-        // delete associated categories first
-        diesel::delete(news_categories::table.filter(news_categories::news_id.eq(*news_id)))
-            .execute(conn)
-            .map_err(|e| {
-                AppError::DatabaseError(format!("Failed to delete news categories: {}", e))
-            })?;
+    // check authorization
+    if !user_claims.can_edit(news_item.author_id) {
+        return Err(AppError::ForbiddenError(
+            "Not authorized to delete this news".into(),
+        ));
+    }
 
-        // then delete the news
-        diesel::delete(news::table.filter(news::id.eq(*news_id)))
-            .execute(conn)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to delete news: {}", e)))?;
+    let version = precondition::check(
+        precondition::if_match(&req)?,
+        config.concurrency.require_if_match,
+        &news_item,
+    )?;
 
-        Ok(())
-    })?;
+    // to the trash, it keeps its category links for a restore
+    if !repository::run(&news_repository, move |repo| repo.delete(news_id, version)).await? {
+        let current = repository::run(&news_repository, move |repo| repo.find(news_id)).await?;
+        return Err(precondition::missed(current, "News not found"));
+    }
+    record_article_deleted();
+    events.publish(ContentEvent::NewsDeleted { id: news_id });
 
     Ok(HttpResponse::Ok().json(json!({
         "message": "News deleted successfully"
     })))
 }
 
+#[utoipa::path(
+    delete,
+    path = "/api/v1/categories/{id}",
+    tag = "categories",
+    params(
+        ("id" = i32, Path, description = "Category id"),
+        ("If-Match" = Option<String>, Header, description = "ETag of the version the change is based on"),
+    ),
+    responses(
+        (status = 200, description = "Category moved to the trash, its news are kept"),
+        (status = 404, description = "No such category", body = Problem, content_type = "application/problem+json"),
+        (status = 412, description = "Changed since the If-Match version, with the `current` one", body = Problem, content_type = "application/problem+json"),
+        (status = 428, description = "If-Match is required and missing", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
 pub async fn delete_category(
     req: HttpRequest,
-    pool: web::Data<DBPool>,
+    category_repository: web::Data<dyn CategoryRepository>,
+    events: web::Data<EventBus>,
     category_id: web::Path<i32>,
+    config: web::Data<Config>,
 ) -> Result<HttpResponse, AppError> {
     // extract user claims from JWT and ensure admin
     let user_claims = req
@@ -396,39 +421,28 @@ pub async fn delete_category(
         ));
     }
 
-    let mut conn = pool
-        .get()
-        .map_err(|e| AppError::DatabaseError(format!("Database connection error: {}", e)))?;
-
-    // perform deletion within a transaction
-    conn.transaction::<_, AppError, _>(|conn| {
-        // check if category exists
-        let exists = categories::table
-            .find(*category_id)
-            .count()
-            .get_result::<i64>(conn)
-            .map_err(|e| AppError::DatabaseError(e.to_string()))?;
-
-        if exists == 0 {
-            return Err(AppError::NotFoundError("Category not found".into()));
-        }
-
-        // delete associated news_categories first
-        diesel::delete(
-            news_categories::table.filter(news_categories::category_id.eq(*category_id)),
-        )
-        .execute(conn)
-        .map_err(|e| {
-            AppError::DatabaseError(format!("Failed to delete category associations: {}", e))
-        })?;
-
-        // then delete the category
-        diesel::delete(categories::table.filter(categories::id.eq(*category_id)))
-            .execute(conn)
-            .map_err(|e| AppError::DatabaseError(format!("Failed to delete category: {}", e)))?;
-
-        Ok(())
-    })?;
+    let category_id = category_id.into_inner();
+    let existing_category =
+        repository::run(&category_repository, move |repo| repo.find(category_id))
+            .await?
+            .ok_or_else(|| AppError::NotFoundError("Category not found".into()))?;
+    let version = precondition::check(
+        precondition::if_match(&req)?,
+        config.concurrency.require_if_match,
+        &existing_category,
+    )?;
+
+    // to the trash, its news no longer show it
+    if !repository::run(&category_repository, move |repo| {
+        repo.delete(category_id, version)
+    })
+    .await?
+    {
+        let current =
+            repository::run(&category_repository, move |repo| repo.find(category_id)).await?;
+        return Err(precondition::missed(current, "Category not found"));
+    }
+    events.publish(ContentEvent::CategoryDeleted { id: category_id });
 
     Ok(HttpResponse::Ok().json(json!({
         "message": "Category deleted successfully"
@@ -0,0 +1,160 @@
+use crate::db::{self, DBPool};
+use crate::models::api_key::{
+    ApiKey, CreateApiKeyRequest, CreateApiKeyResponse, NewApiKey, SCOPES,
+};
+use crate::schema::{api_keys, users};
+use crate::utils::api_key::generate_api_key;
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::jwt::Claims;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde_json::json;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+// create a new API key, the plaintext key is only shown in this response
+#[utoipa::path(
+    post,
+    path = "/admin/create-api-key",
+    tag = "api keys",
+    request_body = CreateApiKeyRequest,
+    responses(
+        (status = 201, description = "The key, shown only this once", body = CreateApiKeyResponse),
+        (status = 404, description = "No such owner", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Invalid name, scope or expiry", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn create_api_key(
+    req: HttpRequest,
+    pool: web::Data<DBPool>,
+    key_data: web::Json<CreateApiKeyRequest>,
+) -> Result<HttpResponse, AppError> {
+    // extract user claims from JWT
+    let user_claims = req
+        .extensions()
+        .get::<Claims>()
+        .ok_or_else(|| AppError::UnauthorizedError("Unauthorized access".into()))?
+        .clone();
+
+    // validate input
+    key_data.validate()?;
+
+    if let Some(scope) = key_data
+        .scopes
+        .iter()
+        .find(|scope| !SCOPES.contains(&scope.as_str()))
+    {
+        let mut errors = ValidationErrors::new();
+        errors.add(
+            "scopes",
+            ValidationError::new("unknown_scope")
+                .with_message(format!("Unknown scope: {}", scope).into()),
+        );
+        return Err(errors.into());
+    }
+
+    // keys belong to the caller unless another owner is given
+    let owner_id = key_data.owner_id.unwrap_or(user_claims.sub);
+    let owner_exists = db::run(&pool, move |conn| {
+        users::table
+            .find(owner_id)
+            .count()
+            .get_result::<i64>(conn)
+            .map_err(AppError::from)
+    })
+    .await?;
+
+    if owner_exists == 0 {
+        return Err(AppError::NotFoundError("Owner not found".into()));
+    }
+
+    let key_data = key_data.into_inner();
+    let (key, key_prefix, key_hash) = generate_api_key();
+    let new_key = NewApiKey {
+        owner_id,
+        name: key_data.name,
+        key_prefix,
+        key_hash,
+        scopes: key_data.scopes,
+        expires_at: key_data
+            .expires_in_days
+            .map(|days| Utc::now().naive_utc() + chrono::Duration::days(days)),
+    };
+
+    let api_key = db::run(&pool, move |conn| {
+        diesel::insert_into(api_keys::table)
+            .values(&new_key)
+            .returning(ApiKey::as_returning())
+            .get_result::<ApiKey>(conn)
+            .map_err(|e| AppError::DatabaseError(format!("Failed to create API key: {}", e)))
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(CreateApiKeyResponse {
+        message: "API key created successfully".to_string(),
+        key,
+        api_key,
+    }))
+}
+
+// list all API keys without their secrets
+#[utoipa::path(
+    get,
+    path = "/admin/list-api-keys",
+    tag = "api keys",
+    responses((status = 200, description = "All keys, without their secrets", body = [ApiKey])),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_api_keys(pool: web::Data<DBPool>) -> Result<HttpResponse, AppError> {
+    let keys = db::run(&pool, |conn| {
+        api_keys::table
+            .order(api_keys::created_at.desc())
+            .select(ApiKey::as_select())
+            .load::<ApiKey>(conn)
+            .map_err(AppError::from)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(keys))
+}
+
+// revoke an API key, it stops working immediately
+#[utoipa::path(
+    delete,
+    path = "/admin/revoke-api-key/{id}",
+    tag = "api keys",
+    params(("id" = i32, Path, description = "API key id")),
+    responses(
+        (status = 200, description = "Key revoked"),
+        (status = 404, description = "No such key or already revoked", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn revoke_api_key(
+    pool: web::Data<DBPool>,
+    key_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let key_id = key_id.into_inner();
+    let revoked = db::run(&pool, move |conn| {
+        diesel::update(
+            api_keys::table
+                .find(key_id)
+                .filter(api_keys::revoked_at.is_null()),
+        )
+        .set(api_keys::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+        .map_err(AppError::from)
+    })
+    .await?;
+
+    if revoked == 0 {
+        return Err(AppError::NotFoundError(
+            "API key not found or already revoked".into(),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "API key revoked successfully"
+    })))
+}
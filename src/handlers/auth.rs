@@ -1,58 +1,104 @@
 // src/handlers/auth.rs
-use actix_web::{web, Error, HttpResponse};
-use bcrypt::{hash, verify, DEFAULT_COST};
-use diesel::prelude::*;
+use actix_web::{web, HttpRequest, HttpResponse};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
-use crate::db::DBPool;
-use crate::models::user::{NewUser, User};
-use crate::schema::users::dsl::*;
-use crate::utils::jwt::create_token;
-use bcrypt::BcryptError;
+use crate::config::Config;
+use crate::db::{self, DBPool};
+use crate::models::user::NewUser;
+use crate::repository::{self, UserRepository};
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::password::{
+    hash_password_blocking, verify_dummy_password_blocking, verify_password_blocking,
+    PasswordError, PasswordPolicy,
+};
+use crate::utils::session::{issue_session_token, SessionInfo};
 
-#[derive(Debug, Deserialize)]
+#[derive(Debug, Deserialize, ToSchema)]
 pub struct LoginCredentials {
     pub username: String,
     pub password: String,
+    // shown in the session list, e.g. "Office tablet"
+    #[serde(default)]
+    pub device_label: Option<String>,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct LoginResponse {
-    token: String,
-    is_admin: bool,
+    pub token: String,
+    pub is_admin: bool,
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/login",
+    tag = "auth",
+    request_body = LoginCredentials,
+    responses(
+        (status = 200, description = "Signed in", body = LoginResponse),
+        (status = 401, description = "Invalid credentials", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 pub async fn login(
+    req: HttpRequest,
     credentials: web::Json<LoginCredentials>,
     pool: web::Data<DBPool>,
-) -> Result<HttpResponse, Error> {
-    let conn = &mut pool.get().map_err(|e| {
-        log::error!("Database connection error: {}", e);
-        actix_web::error::ErrorInternalServerError("Database error")
-    })?;
+    user_repository: web::Data<dyn UserRepository>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    let credentials = credentials.into_inner();
 
     // Find user by username
-    let user_result = users
-        .filter(username.eq(&credentials.username))
-        .first::<User>(conn)
-        .map_err(|e| {
-            log::error!("User not found: {}", e);
-            actix_web::error::ErrorUnauthorized("Invalid credentials")
-        })?;
+    let name = credentials.username.clone();
+    let user_result =
+        match repository::run(&user_repository, move |repo| repo.find_by_username(&name)).await? {
+            Some(user) => user,
+            None => {
+                // spend the same time as a real check so usernames can't be probed
+                verify_dummy_password_blocking(credentials.password).await;
+                log::warn!("Login for unknown user: {}", credentials.username);
+                return Err(AppError::UnauthorizedError("Invalid credentials".into()));
+            }
+        };
 
     // Verify password
-    let password_matches =
-        verify(&credentials.password, &user_result.password).map_err(|e: BcryptError| {
-            log::error!("Password verification failed: {}", e);
-            actix_web::error::ErrorInternalServerError("Password verification failed")
-        })?;
+    let verification =
+        verify_password_blocking(credentials.password.clone(), user_result.password.clone())
+            .await
+            .map_err(|e: PasswordError| {
+                AppError::DatabaseError(format!("Password verification failed: {}", e))
+            })?;
+    let password_matches = verification.valid;
+
+    // upgrade bcrypt or outdated argon2 hashes while we have the plaintext
+    if password_matches && verification.needs_rehash {
+        match hash_password_blocking(credentials.password.clone()).await {
+            Ok(new_hash) => {
+                let user_id = user_result.id;
+                if let Err(e) = repository::run(&user_repository, move |repo| {
+                    repo.update_password(user_id, new_hash)
+                })
+                .await
+                {
+                    log::error!("Failed to store rehashed password: {}", e);
+                }
+            }
+            Err(e) => log::error!("Failed to rehash password: {}", e),
+        }
+    }
 
     if password_matches {
-        let token = create_token(user_result.id, &user_result.username, user_result.is_admin)
-            .map_err(|e| {
-                log::error!("Token creation failed: {}", e);
-                actix_web::error::ErrorInternalServerError("Token creation failed")
-            })?;
+        let jwt = config.jwt.clone();
+        let info = SessionInfo::from_request(&req, credentials.device_label.as_deref());
+        let (user_id, name, admin) = (
+            user_result.id,
+            user_result.username.clone(),
+            user_result.is_admin,
+        );
+        let token = db::run(&pool, move |conn| {
+            issue_session_token(conn, &jwt, user_id, &name, admin, info)
+        })
+        .await?;
 
         Ok(HttpResponse::Ok().json(LoginResponse {
             token,
@@ -60,44 +106,57 @@ pub async fn login(
         }))
     } else {
         log::warn!("Invalid credentials for user: {}", credentials.username);
-        Err(actix_web::error::ErrorUnauthorized("Invalid credentials"))
+        Err(AppError::UnauthorizedError("Invalid credentials".into()))
     }
 }
 
+#[utoipa::path(
+    post,
+    path = "/auth/register",
+    tag = "auth",
+    request_body = LoginCredentials,
+    responses(
+        (status = 201, description = "User created"),
+        (status = 400, description = "The password doesn't meet the policy", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "The username is taken", body = Problem, content_type = "application/problem+json"),
+    )
+)]
 pub async fn register(
     user_data: web::Json<LoginCredentials>,
-    pool: web::Data<DBPool>,
-) -> Result<HttpResponse, Error> {
-    let conn = &mut pool.get().map_err(|e| {
-        actix_web::error::ErrorInternalServerError(format!("Database error: {}", e))
-    })?;
+    user_repository: web::Data<dyn UserRepository>,
+    password_policy: web::Data<PasswordPolicy>,
+) -> Result<HttpResponse, AppError> {
+    let user_data = user_data.into_inner();
 
     // Check if username exists
-    let user_exists = users
-        .filter(username.eq(&user_data.username))
-        .first::<User>(conn)
-        .is_ok();
+    let name = user_data.username.clone();
+    let existing =
+        repository::run(&user_repository, move |repo| repo.find_by_username(&name)).await?;
 
-    if user_exists {
-        return Err(actix_web::error::ErrorBadRequest("Username already exists"));
+    if existing.is_some() {
+        return Err(AppError::ConflictError("Username already exists".into()));
     }
 
+    // Check the password policy
+    password_policy
+        .check(&user_data.username, &user_data.password)
+        .map_err(AppError::BadRequestError)?;
+
     // Hash password
-    let password_hash = hash(&user_data.password, DEFAULT_COST)
-        .map_err(|_| actix_web::error::ErrorInternalServerError("Password hashing failed"))?;
+    let password_hash = hash_password_blocking(user_data.password)
+        .await
+        .map_err(|e| AppError::DatabaseError(format!("Password hashing failed: {}", e)))?;
 
-    // Create new user
+    // Create new user, admins are created with the `create-admin` command
     let new_user = NewUser {
-        username: user_data.username.clone(),
+        username: user_data.username,
         password: password_hash,
-        is_admin: true,
+        is_admin: false,
     };
 
-    // Insert into database
-    diesel::insert_into(users)
-        .values(&new_user)
-        .execute(conn)
-        .map_err(|_| actix_web::error::ErrorInternalServerError("Failed to create user"))?;
+    // Insert into database, the unique index turns a concurrent signup with the same name into
+    // a conflict
+    repository::run(&user_repository, move |repo| repo.create(new_user)).await?;
 
     Ok(HttpResponse::Created().json("User created successfully"))
 }
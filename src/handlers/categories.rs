@@ -0,0 +1,85 @@
+use crate::models::category::Category;
+use crate::repository::{self, CategoryRepository};
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::http_cache::Validators;
+use crate::utils::precondition;
+use crate::utils::response_cache::{CacheKey, CachedResponse, ResponseCache};
+use actix_web::http::header::EntityTag;
+use actix_web::{web, HttpRequest, HttpResponse};
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories",
+    tag = "categories",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "The ETag of the copy you have"),
+    ),
+    responses(
+        (status = 200, description = "All categories by name", body = [Category],
+            headers(("ETag" = String, description = "Weak, changes with any category"))),
+        (status = 304, description = "Your copy is current"),
+    ),
+    security((), ("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_categories(
+    req: HttpRequest,
+    response_cache: web::Data<ResponseCache>,
+    category_repository: web::Data<dyn CategoryRepository>,
+) -> Result<HttpResponse, AppError> {
+    let miss = match response_cache.get(CacheKey::CategoryList) {
+        Ok(cached) => return Ok(cached.respond(&req, "")),
+        Err(miss) => miss,
+    };
+
+    let categories = repository::run(&category_repository, |repo| repo.list()).await?;
+    let latest = categories
+        .iter()
+        .map(|category| category.updated_at.and_utc().timestamp_micros())
+        .max();
+    let validators = Validators {
+        etag: EntityTag::new_weak(format!("{}-{}", categories.len(), latest.unwrap_or(0))),
+        last_modified: None,
+    };
+    let response = CachedResponse::json(validators, None, &categories);
+    Ok(response_cache
+        .insert(miss, response, vec!["category-list".to_string()])
+        .respond(&req, ""))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/categories/{id}",
+    tag = "categories",
+    params(("id" = i32, Path, description = "Category id")),
+    responses(
+        (status = 200, description = "The category, the ETag is its version", body = Category,
+            headers(("ETag" = String, description = "Send as If-Match when changing the category"))),
+        (status = 404, description = "No such category", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("bearer" = []), ("api_key" = []))
+)]
+pub async fn get_category(
+    req: HttpRequest,
+    response_cache: web::Data<ResponseCache>,
+    category_repository: web::Data<dyn CategoryRepository>,
+    category_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let category_id = category_id.into_inner();
+    let miss = match response_cache.get(CacheKey::Category(category_id)) {
+        Ok(cached) => return Ok(cached.respond(&req, "")),
+        Err(miss) => miss,
+    };
+
+    let category = repository::run(&category_repository, move |repo| repo.find(category_id))
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("Category not found!".into()))?;
+
+    let validators = Validators {
+        etag: precondition::etag(category.updated_at),
+        last_modified: None,
+    };
+    let response = CachedResponse::json(validators, None, &category);
+    Ok(response_cache
+        .insert(miss, response, vec![format!("category-{}", category_id)])
+        .respond(&req, ""))
+}
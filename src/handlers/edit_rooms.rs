@@ -0,0 +1,118 @@
+use crate::repository::{self, NewsRepository};
+use crate::utils::edit_rooms::{ClientMessage, EditRooms, RoomMessage};
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::jwt::Claims;
+use crate::utils::to_json;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use actix_ws::{Message, MessageStream, Session};
+use futures::StreamExt;
+use tokio::sync::mpsc::UnboundedReceiver;
+
+// join the editing room of an article
+#[utoipa::path(
+    get,
+    path = "/api/v1/news/{id}/room",
+    tag = "news",
+    params(
+        ("id" = i32, Path, description = "News id"),
+        ("access_token" = Option<String>, Query, description = "The bearer token, for browsers that can't send headers with the handshake"),
+    ),
+    responses(
+        (status = 101, description = "A WebSocket of JSON messages. The server sends `presence` (`members`), `lock` (`lock` with `holder` and `expires_at`, or null), `changed` (`news_id`, `change`) and `error` (`code`, `message`); clients send `{\"type\": \"lock\", \"force\": false}` to take or renew the edit lock and `{\"type\": \"unlock\"}` to release it"),
+        (status = 403, description = "Neither the author nor an admin", body = Problem, content_type = "application/problem+json"),
+        (status = 404, description = "No such news", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn join_room(
+    req: HttpRequest,
+    path: web::Path<i32>,
+    body: web::Payload,
+    news_repository: web::Data<dyn NewsRepository>,
+    edit_rooms: web::Data<EditRooms>,
+) -> Result<HttpResponse, AppError> {
+    let news_id = path.into_inner();
+    let claims = req
+        .extensions()
+        .get::<Claims>()
+        .ok_or_else(|| AppError::UnauthorizedError("Unauthorized access".into()))?
+        .clone();
+
+    // only those who may save the article get to see who is editing it
+    let news = repository::run(&news_repository, move |repo| repo.find(news_id))
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("News not found!".into()))?;
+    if !claims.can_edit(news.author_id) {
+        return Err(AppError::ForbiddenError("Not authorized!".into()));
+    }
+
+    let (response, session, incoming) = actix_ws::handle(&req, body)
+        .map_err(|e| AppError::BadRequestError(format!("Not a WebSocket handshake: {}", e)))?;
+    let (connection_id, outgoing) = edit_rooms.join(news_id, &claims);
+    actix_web::rt::spawn(async move {
+        let close = relay(
+            &edit_rooms,
+            news_id,
+            connection_id,
+            session.clone(),
+            incoming,
+            outgoing,
+        )
+        .await;
+        edit_rooms.leave(news_id, connection_id);
+        let _ = session.close(close).await;
+    });
+
+    Ok(response)
+}
+
+// pass messages between the socket and the room until either side is done
+async fn relay(
+    edit_rooms: &EditRooms,
+    news_id: i32,
+    connection_id: u64,
+    mut session: Session,
+    mut incoming: MessageStream,
+    mut outgoing: UnboundedReceiver<RoomMessage>,
+) -> Option<actix_ws::CloseReason> {
+    loop {
+        tokio::select! {
+            message = incoming.next() => {
+                let reply = match message {
+                    Some(Ok(Message::Text(text))) => {
+                        match serde_json::from_str::<ClientMessage>(&text) {
+                            Ok(message) => edit_rooms.handle(news_id, connection_id, message).err(),
+                            Err(e) => Some(AppError::BadRequestError(format!("Invalid message: {}", e))),
+                        }
+                        .map(|e| RoomMessage::error(&e))
+                    }
+                    Some(Ok(Message::Ping(bytes))) => {
+                        if session.pong(&bytes).await.is_err() {
+                            return None;
+                        }
+                        None
+                    }
+                    Some(Ok(Message::Close(reason))) => return reason,
+                    Some(Ok(_)) => None,
+                    Some(Err(_)) | None => return None,
+                };
+                if let Some(reply) = reply {
+                    if send(&mut session, &reply).await.is_err() {
+                        return None;
+                    }
+                }
+            }
+            message = outgoing.recv() => {
+                // the room only drops the sender when the connection left
+                let message = message?;
+                if send(&mut session, &message).await.is_err() {
+                    return None;
+                }
+            }
+        }
+    }
+}
+
+async fn send(session: &mut Session, message: &RoomMessage) -> Result<(), actix_ws::Closed> {
+    session.text(to_json(message)).await
+}
@@ -0,0 +1,69 @@
+use actix_web::http::header::ContentType;
+use actix_web::{web, HttpRequest, HttpResponse};
+use async_graphql::http::{parse_query_string, GraphiQLSource};
+
+use crate::graphql::{self, NewsSchema, ReadOnly};
+use crate::utils::error_response::{AppError, Problem};
+
+// GraphQL errors come back with status 200, in the `errors` of the response
+#[utoipa::path(
+    post,
+    path = "/graphql",
+    tag = "graphql",
+    request_body(content = Object, description = "`query`, `variables` and `operationName`"),
+    responses(
+        (status = 200, description = "`data` and `errors`, each error has `extensions.code`"),
+    ),
+    security((), ("bearer" = []), ("api_key" = []))
+)]
+pub async fn execute(
+    req: HttpRequest,
+    schema: web::Data<NewsSchema>,
+    request: web::Json<async_graphql::Request>,
+) -> Result<HttpResponse, AppError> {
+    let request = graphql::prepare(&req, request.into_inner())?;
+    Ok(HttpResponse::Ok().json(schema.execute(request).await))
+}
+
+// queries only, so read-only API keys can use GraphQL too
+#[utoipa::path(
+    get,
+    path = "/graphql",
+    tag = "graphql",
+    params(
+        ("query" = String, Query, description = "The GraphQL query"),
+        ("variables" = Option<String>, Query, description = "Variables as a JSON object"),
+        ("operationName" = Option<String>, Query),
+    ),
+    responses(
+        (status = 200, description = "`data` and `errors`, mutations are rejected"),
+        (status = 400, description = "No query", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("bearer" = []), ("api_key" = []))
+)]
+pub async fn execute_query(
+    req: HttpRequest,
+    schema: web::Data<NewsSchema>,
+) -> Result<HttpResponse, AppError> {
+    let request = parse_query_string(req.query_string())
+        .map_err(|e| AppError::BadRequestError(format!("Invalid query: {}", e)))?;
+    if request.query.is_empty() {
+        return Err(AppError::BadRequestError("No query given".into()));
+    }
+
+    let request = graphql::prepare(&req, request)?.data(ReadOnly);
+    Ok(HttpResponse::Ok().json(schema.execute(request).await))
+}
+
+// only served when `graphql.playground` is set
+#[utoipa::path(
+    get,
+    path = "/graphiql",
+    tag = "graphql",
+    responses((status = 200, description = "The GraphiQL playground", content_type = "text/html"))
+)]
+pub async fn playground() -> HttpResponse {
+    HttpResponse::Ok()
+        .content_type(ContentType::html())
+        .body(GraphiQLSource::build().endpoint("/graphql").finish())
+}
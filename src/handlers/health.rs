@@ -0,0 +1,92 @@
+use std::time::Duration;
+
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+use crate::db::{has_pending_migrations, schema_version, DBPool};
+use crate::supervisor::Supervisor;
+
+// don't let a saturated pool stall the orchestrator's probe
+const READY_CHECK_TIMEOUT: Duration = Duration::from_secs(2);
+
+// the process is up and serving requests
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    tag = "health",
+    responses((status = 200, description = "The process is alive"))
+)]
+pub async fn healthz() -> HttpResponse {
+    HttpResponse::Ok().json(json!({ "status": "ok" }))
+}
+
+// the database is reachable and the schema is current
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    tag = "health",
+    responses(
+        (status = 200, description = "Ready to serve, with the individual checks"),
+        (status = 503, description = "The database is unreachable or migrations are pending"),
+    )
+)]
+pub async fn readyz(pool: web::Data<DBPool>, supervisor: web::Data<Supervisor>) -> HttpResponse {
+    let pool = pool.into_inner();
+    let database = web::block(move || match pool.get_timeout(READY_CHECK_TIMEOUT) {
+        Err(e) => {
+            log::warn!("Readiness check failed to get a connection: {}", e);
+            (false, json!({ "database": "unreachable" }))
+        }
+        Ok(mut conn) => match has_pending_migrations(&mut conn) {
+            Ok(false) => (true, json!({ "database": "ok", "migrations": "ok" })),
+            Ok(true) => (false, json!({ "database": "ok", "migrations": "pending" })),
+            Err(e) => {
+                log::warn!("Readiness check failed to read migrations: {}", e);
+                (false, json!({ "database": "error" }))
+            }
+        },
+    })
+    .await;
+    let (ready, mut checks) =
+        database.unwrap_or_else(|_| (false, json!({ "database": "unreachable" })));
+    // reported only, requests are still served while a task restarts
+    checks["tasks"] = json!(supervisor.statuses());
+
+    if ready {
+        HttpResponse::Ok().json(json!({ "status": "ok", "checks": checks }))
+    } else {
+        HttpResponse::ServiceUnavailable()
+            .json(json!({ "status": "unavailable", "checks": checks }))
+    }
+}
+
+// build info, the schema version is null when the database is unreachable
+#[utoipa::path(
+    get,
+    path = "/version",
+    tag = "health",
+    responses((status = 200, description = "Package version, git sha, build time and schema version"))
+)]
+pub async fn version(pool: web::Data<DBPool>) -> HttpResponse {
+    let build_time = env!("BUILD_TIMESTAMP")
+        .parse::<i64>()
+        .ok()
+        .and_then(|timestamp| chrono::DateTime::from_timestamp(timestamp, 0));
+    let pool = pool.into_inner();
+    let schema = web::block(move || {
+        pool.get_timeout(READY_CHECK_TIMEOUT)
+            .ok()
+            .and_then(|mut conn| schema_version(&mut conn).ok())
+            .flatten()
+    })
+    .await
+    .ok()
+    .flatten();
+
+    HttpResponse::Ok().json(json!({
+        "version": env!("CARGO_PKG_VERSION"),
+        "git_sha": env!("BUILD_GIT_SHA"),
+        "build_time": build_time,
+        "schema_version": schema,
+    }))
+}
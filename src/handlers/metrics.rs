@@ -0,0 +1,43 @@
+use actix_web::http::header::{ContentType, AUTHORIZATION};
+use actix_web::{web, HttpRequest, HttpResponse};
+
+use crate::config::Config;
+use crate::db::DBPool;
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::metrics::render;
+
+// Prometheus scrape endpoint, guarded by a bearer token when one is configured
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "health",
+    responses(
+        (status = 200, description = "Prometheus text format", content_type = "text/plain"),
+        (status = 401, description = "Missing or wrong metrics token", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn metrics(
+    req: HttpRequest,
+    pool: web::Data<DBPool>,
+    config: web::Data<Config>,
+) -> Result<HttpResponse, AppError> {
+    if let Some(token) = &config.metrics.token {
+        let expected = format!("Bearer {}", token);
+        let provided = req
+            .headers()
+            .get(AUTHORIZATION)
+            .and_then(|value| value.to_str().ok());
+        if provided != Some(expected.as_str()) {
+            return Err(AppError::UnauthorizedError("Invalid metrics token".into()));
+        }
+    }
+
+    let body = render(&pool).map_err(|e| {
+        log::error!("Failed to render metrics: {}", e);
+        AppError::DatabaseError("Failed to render metrics".into())
+    })?;
+
+    Ok(HttpResponse::Ok()
+        .content_type(ContentType::plaintext())
+        .body(body))
+}
@@ -1,3 +1,13 @@
 pub mod admin;
 pub mod news;
+pub mod categories;
+pub mod graphql;
 pub mod auth;
+pub mod api_keys;
+pub mod oidc;
+pub mod sessions;
+pub mod health;
+pub mod metrics;
+pub mod webhooks;
+pub mod edit_rooms;
+pub mod trash;
@@ -1,80 +1,147 @@
-use crate::db::DBPool;
-use crate::models::category::CategorySummary;
-use crate::models::news::{News, NewsDetail, NewsSummary};
-use crate::schema::news::dsl::*;
-use crate::schema::{categories, news_categories};
-use actix_web::{web, HttpResponse};
-use diesel::prelude::*;
-use diesel::QueryDsl;
+use crate::config::Config;
+use crate::models::news::{NewsDetail, NewsSummary};
+use crate::repository::{self, NewsRepository};
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::http_cache::{self, Validators};
+use crate::utils::news_stream::{NewsStream, Resume, StreamEvent, KEEP_ALIVE, RESET};
+use crate::utils::precondition;
+use crate::utils::response_cache::{CacheKey, CachedResponse, ResponseCache};
+use actix_web::http::header::{CacheControl, CacheDirective, EntityTag};
+use actix_web::web::Bytes;
+use actix_web::{web, HttpRequest, HttpResponse};
+use serde::Deserialize;
+use std::collections::VecDeque;
+use std::sync::Arc;
+use std::time::Duration;
+use tokio::sync::broadcast::error::RecvError;
+use utoipa::IntoParams;
 
-pub async fn list_news(pool: web::Data<DBPool>) -> HttpResponse {
-    let mut conn = match pool.get() {
-        Ok(conn) => conn,
-        Err(_) => return HttpResponse::InternalServerError().body("Failed to get DB connection."),
-    };
-
-    // get all news but only title and date only
-    let all_news = news
-        .select((title, created_at))
-        .load::<(String, chrono::NaiveDateTime)>(&mut conn);
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct StreamQuery {
+    /// Only articles filed under this category
+    pub category_id: Option<i32>,
+}
 
-    match all_news {
-        Ok(news_list) => {
-            // collect all data as json response
-            let response: Vec<NewsSummary> = news_list
-                .into_iter()
-                .map(|(news_title, news_created_at)| NewsSummary {
-                    title: news_title,
-                    created_at: news_created_at,
-                })
-                .collect();
+#[utoipa::path(
+    get,
+    path = "/api/v1/news",
+    tag = "news",
+    params(
+        ("If-None-Match" = Option<String>, Header, description = "The ETag of the copy you have"),
+    ),
+    responses(
+        (status = 200, description = "Titles and dates of all news", body = [NewsSummary],
+            headers(
+                ("ETag" = String, description = "Weak, changes with any article"),
+                ("Cache-Control" = String),
+                ("Surrogate-Key" = String, description = "`news-list`"),
+            )),
+        (status = 304, description = "Your copy is current"),
+    ),
+    security((), ("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_news(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    response_cache: web::Data<ResponseCache>,
+    news_repository: web::Data<dyn NewsRepository>,
+) -> Result<HttpResponse, AppError> {
+    let cache = &config.http_cache;
+    let miss = match response_cache.get(CacheKey::NewsList) {
+        Ok(cached) => return Ok(cached.respond(&req, &cache.list_cache_control)),
+        Err(miss) => miss,
+    };
 
-            HttpResponse::Ok().json(response)
-        }
-        Err(e) => {
-            eprintln!("Error fetching news: {:?}", e);
-            HttpResponse::InternalServerError().body("Failed to fetch news.")
-        }
+    // the same list can be serialized differently, so the tag is weak; there's no
+    // Last-Modified because deleting an article doesn't move it forward
+    let (count, latest) = repository::run(&news_repository, |repo| repo.list_version()).await?;
+    let validators = Validators {
+        etag: EntityTag::new_weak(format!(
+            "{}-{}",
+            count,
+            latest.map_or(0, |latest| latest.and_utc().timestamp_micros())
+        )),
+        last_modified: None,
+    };
+    if validators.is_fresh(&req) {
+        return Ok(validators.not_modified(&cache.list_cache_control));
     }
+
+    // get all news but only title and date only
+    let response = repository::run(&news_repository, |repo| repo.list()).await?;
+    let keys = vec!["news-list".to_string()];
+    let response = CachedResponse::json(
+        validators,
+        http_cache::surrogate_key(cache, keys.clone()),
+        &response,
+    );
+    Ok(response_cache
+        .insert(miss, response, keys)
+        .respond(&req, &cache.list_cache_control))
 }
 
 // get news details
-pub async fn get_news_detail(pool: web::Data<DBPool>, news_id: web::Path<i32>) -> HttpResponse {
-    let mut conn = pool.get().expect("Failed to get DB connection.");
-
-    // use dsl to avoid ambiguity
-    use crate::schema::news;
+#[utoipa::path(
+    get,
+    path = "/api/v1/news/{id}",
+    tag = "news",
+    params(
+        ("id" = i32, Path, description = "News id"),
+        ("If-None-Match" = Option<String>, Header, description = "The ETag of the copy you have"),
+        ("If-Modified-Since" = Option<String>, Header, description = "The Last-Modified of the copy you have"),
+    ),
+    responses(
+        (status = 200, description = "The article with its categories, the ETag is its version", body = NewsDetail,
+            headers(
+                ("ETag" = String, description = "Send as If-Match when changing the article"),
+                ("Last-Modified" = String),
+                ("Cache-Control" = String),
+                ("Surrogate-Key" = String, description = "`news-<id>` and `category-<id>` for each of its categories"),
+            )),
+        (status = 304, description = "Your copy is current"),
+        (status = 404, description = "No such news", body = Problem, content_type = "application/problem+json"),
+    ),
+    security((), ("bearer" = []), ("api_key" = []))
+)]
+pub async fn get_news_detail(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    response_cache: web::Data<ResponseCache>,
+    news_repository: web::Data<dyn NewsRepository>,
+    news_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let cache = &config.http_cache;
+    let news_id = news_id.into_inner();
+    let miss = match response_cache.get(CacheKey::News(news_id)) {
+        Ok(cached) => return Ok(cached.respond(&req, &cache.detail_cache_control)),
+        Err(miss) => miss,
+    };
 
     // find the news item by ID
-    let news_item = match news::table
-        .filter(news::id.eq(*news_id))
-        .first::<News>(&mut conn)
-    {
-        Ok(found_news) => found_news,
-        Err(diesel::result::Error::NotFound) => {
-            return HttpResponse::NotFound().body("News not found!")
-        }
-        Err(_) => return HttpResponse::InternalServerError().body("Failed to fetch news!"),
+    let news_item = repository::run(&news_repository, move |repo| repo.find(news_id))
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("News not found!".into()))?;
+
+    // renaming or deleting a category touches its articles, so this covers the categories too
+    let validators = Validators {
+        etag: precondition::etag(news_item.updated_at),
+        last_modified: Some(news_item.updated_at),
     };
+    if validators.is_fresh(&req) {
+        return Ok(validators.not_modified(&cache.detail_cache_control));
+    }
 
     // fetch associated categories
-    let category_list = match news_categories::table
-        .inner_join(categories::table.on(news_categories::category_id.eq(categories::id)))
-        .filter(news_categories::news_id.eq(news_item.id))
-        .select((categories::id, categories::name))
-        .load::<(i32, String)>(&mut conn)
-    {
-        Ok(categories) => categories
-            .into_iter()
-            .map(|(category_id, category_name)| CategorySummary {
-                id: category_id,
-                name: category_name,
-            })
-            .collect::<Vec<CategorySummary>>(),
-        Err(_) => return HttpResponse::InternalServerError().body("Failed to fetch categories!"),
-    };
+    let category_list =
+        repository::run(&news_repository, move |repo| repo.categories(news_id)).await?;
 
     // create response
+    let mut keys = vec![format!("news-{}", news_id)];
+    keys.extend(
+        category_list
+            .iter()
+            .map(|category| format!("category-{}", category.id)),
+    );
     let response = NewsDetail {
         id: news_item.id,
         title: news_item.title,
@@ -85,5 +152,99 @@ pub async fn get_news_detail(pool: web::Data<DBPool>, news_id: web::Path<i32>) -
         categories: category_list,
     };
 
-    HttpResponse::Ok().json(response)
+    let response = CachedResponse::json(
+        validators,
+        http_cache::surrogate_key(cache, keys.clone()),
+        &response,
+    );
+    Ok(response_cache
+        .insert(miss, response, keys)
+        .respond(&req, &cache.detail_cache_control))
+}
+
+// what is left to send on an open stream
+struct StreamState {
+    reset: bool,
+    backlog: VecDeque<Arc<StreamEvent>>,
+    live: tokio::sync::broadcast::Receiver<Arc<StreamEvent>>,
+    keep_alive: tokio::time::Interval,
+    category_id: Option<i32>,
+}
+
+// live article changes as server-sent events
+#[utoipa::path(
+    get,
+    path = "/api/v1/news/stream",
+    tag = "news",
+    params(
+        StreamQuery,
+        ("Last-Event-ID" = Option<String>, Header, description = "The id of the last event you got, to get the ones you missed"),
+    ),
+    responses(
+        (status = 200, description = "`published`, `updated` and `deleted` events as they happen; `reset` when events were missed and the list should be loaded again",
+            content_type = "text/event-stream", body = StreamEvent),
+    ),
+    security((), ("bearer" = []), ("api_key" = []))
+)]
+pub async fn stream_news(
+    req: HttpRequest,
+    config: web::Data<Config>,
+    news_stream: web::Data<NewsStream>,
+    query: web::Query<StreamQuery>,
+) -> HttpResponse {
+    // browsers send it when they reconnect
+    let last_event_id = req
+        .headers()
+        .get("Last-Event-ID")
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.trim().parse::<u64>().ok());
+    let (resume, live) = news_stream.subscribe(last_event_id);
+    let (reset, backlog) = match resume {
+        Resume::Backlog(events) => (false, events.into()),
+        Resume::Reset => (true, VecDeque::new()),
+    };
+
+    let period = Duration::from_secs(config.news_stream.keep_alive_secs);
+    let state = StreamState {
+        reset,
+        backlog,
+        live,
+        keep_alive: tokio::time::interval_at(tokio::time::Instant::now() + period, period),
+        category_id: query.category_id,
+    };
+    let events = futures::stream::unfold(state, |mut state| async move {
+        loop {
+            if state.reset {
+                state.reset = false;
+                return Some((Ok::<_, actix_web::Error>(Bytes::from_static(RESET)), state));
+            }
+            if let Some(event) = state.backlog.pop_front() {
+                if event.matches(state.category_id) {
+                    return Some((Ok(event.frame()), state));
+                }
+                continue;
+            }
+            tokio::select! {
+                received = state.live.recv() => match received {
+                    Ok(event) if event.matches(state.category_id) => {
+                        return Some((Ok(event.frame()), state));
+                    }
+                    Ok(_) => {}
+                    // too slow to keep up, it can't tell what it missed
+                    Err(RecvError::Lagged(_)) => state.reset = true,
+                    Err(RecvError::Closed) => return None,
+                },
+                _ = state.keep_alive.tick() => {
+                    return Some((Ok(Bytes::from_static(KEEP_ALIVE)), state));
+                }
+            }
+        }
+    });
+
+    HttpResponse::Ok()
+        .content_type("text/event-stream")
+        .insert_header(CacheControl(vec![CacheDirective::NoCache]))
+        // proxies like nginx would hold the events back otherwise
+        .insert_header(("X-Accel-Buffering", "no"))
+        .streaming(events)
 }
@@ -0,0 +1,200 @@
+use actix_web::cookie::{time::Duration as CookieDuration, Cookie, SameSite};
+use actix_web::http::header::LOCATION;
+use actix_web::{web, HttpRequest, HttpResponse};
+use diesel::prelude::*;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::Deserialize;
+use utoipa::IntoParams;
+use uuid::Uuid;
+
+use crate::config::Config;
+use crate::db::{self, DBPool};
+use crate::handlers::auth::LoginResponse;
+use crate::models::user::{NewUser, NewUserIdentity, User};
+use crate::schema::{user_identities, users};
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::oidc::{OidcClient, OidcIdentity};
+use crate::utils::password::hash_password;
+use crate::utils::session::{issue_session_token, SessionInfo};
+
+// cookie carrying the pending login between the redirect and the callback
+const LOGIN_COOKIE: &str = "oidc_login";
+const LOGIN_COOKIE_PATH: &str = "/auth/oidc";
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct OidcCallback {
+    pub code: Option<String>,
+    pub state: Option<String>,
+    pub error: Option<String>,
+    pub error_description: Option<String>,
+}
+
+fn oidc_client(oidc: Option<web::Data<OidcClient>>) -> Result<web::Data<OidcClient>, AppError> {
+    oidc.ok_or_else(|| AppError::NotFoundError("Single sign-on is not configured".into()))
+}
+
+// redirect the browser to the identity provider
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/login",
+    tag = "auth",
+    responses(
+        (status = 302, description = "Redirect to the identity provider"),
+        (status = 404, description = "Single sign-on is not configured", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn oidc_login(oidc: Option<web::Data<OidcClient>>) -> Result<HttpResponse, AppError> {
+    let oidc = oidc_client(oidc)?;
+    let (location, pending) = oidc.authorization_request().await?;
+
+    let cookie = Cookie::build(LOGIN_COOKIE, pending)
+        .path(LOGIN_COOKIE_PATH)
+        .http_only(true)
+        .secure(oidc.config.redirect_url.starts_with("https://"))
+        // lax so the cookie survives the top-level redirect back from the provider
+        .same_site(SameSite::Lax)
+        .max_age(CookieDuration::minutes(10))
+        .finish();
+
+    Ok(HttpResponse::Found()
+        .insert_header((LOCATION, location))
+        .cookie(cookie)
+        .finish())
+}
+
+// finish the login, provision the user and issue our own token
+#[utoipa::path(
+    get,
+    path = "/auth/oidc/callback",
+    tag = "auth",
+    params(OidcCallback),
+    responses(
+        (status = 200, description = "Signed in", body = LoginResponse),
+        (status = 401, description = "The sign-in was not completed", body = Problem, content_type = "application/problem+json"),
+        (status = 403, description = "Not in an allowed group", body = Problem, content_type = "application/problem+json"),
+        (status = 502, description = "The identity provider failed", body = Problem, content_type = "application/problem+json"),
+    )
+)]
+pub async fn oidc_callback(
+    req: HttpRequest,
+    query: web::Query<OidcCallback>,
+    pool: web::Data<DBPool>,
+    config: web::Data<Config>,
+    oidc: Option<web::Data<OidcClient>>,
+) -> Result<HttpResponse, AppError> {
+    let oidc = oidc_client(oidc)?;
+
+    if let Some(error) = &query.error {
+        log::warn!(
+            "Identity provider returned {}: {}",
+            error,
+            query.error_description.as_deref().unwrap_or("")
+        );
+        return Err(AppError::UnauthorizedError(
+            "Sign-in was not completed".into(),
+        ));
+    }
+
+    let (Some(code), Some(state)) = (&query.code, &query.state) else {
+        return Err(AppError::UnauthorizedError(
+            "Missing authorization code".into(),
+        ));
+    };
+
+    let cookie = req
+        .cookie(LOGIN_COOKIE)
+        .ok_or_else(|| AppError::UnauthorizedError("Login session expired".into()))?;
+    let pending = oidc.pending_login(cookie.value(), state)?;
+
+    let identity = oidc.exchange_code(code, &pending).await?;
+    if !oidc.is_allowed(&identity) {
+        log::warn!(
+            "Rejected SSO login for {}: not in an allowed group",
+            identity.username
+        );
+        return Err(AppError::ForbiddenError(
+            "Not a member of an allowed group".into(),
+        ));
+    }
+
+    let is_admin = oidc.is_admin(&identity);
+    let jwt = config.jwt.clone();
+    let info = SessionInfo::from_request(&req, Some("Single sign-on"));
+    let (user, token) = db::run(&pool, move |conn| {
+        let user = provision_user(conn, &identity, is_admin)?;
+        let token = issue_session_token(conn, &jwt, user.id, &user.username, user.is_admin, info)?;
+        Ok::<_, AppError>((user, token))
+    })
+    .await?;
+
+    let mut expired = Cookie::build(LOGIN_COOKIE, "")
+        .path(LOGIN_COOKIE_PATH)
+        .finish();
+    expired.make_removal();
+
+    Ok(HttpResponse::Ok().cookie(expired).json(LoginResponse {
+        token,
+        is_admin: user.is_admin,
+    }))
+}
+
+// find the user linked to the identity, creating one on first login
+pub fn provision_user(
+    conn: &mut PgConnection,
+    identity: &OidcIdentity,
+    is_admin: bool,
+) -> Result<User, AppError> {
+    conn.transaction::<_, AppError, _>(|conn| {
+        let linked = user_identities::table
+            .inner_join(users::table)
+            .filter(user_identities::issuer.eq(&identity.issuer))
+            .filter(user_identities::subject.eq(&identity.subject))
+            .select(users::all_columns)
+            .first::<User>(conn)
+            .optional()?;
+
+        // the provider groups decide the role on every login
+        if let Some(user) = linked {
+            if user.is_admin == is_admin {
+                return Ok(user);
+            }
+            return Ok(diesel::update(users::table.find(user.id))
+                .set((
+                    users::is_admin.eq(is_admin),
+                    users::updated_at.eq(chrono::Utc::now().naive_utc()),
+                ))
+                .get_result::<User>(conn)?);
+        }
+
+        // SSO users never log in with a password, give them one nobody knows
+        let password = hash_password(&Uuid::new_v4().to_string())
+            .map_err(|e| AppError::DatabaseError(format!("Password hashing failed: {}", e)))?;
+
+        // never link to an existing local account, that would let the provider take it over. The
+        // unique index catches one created between a lookup and this insert too
+        let user = diesel::insert_into(users::table)
+            .values(&NewUser {
+                username: identity.username.clone(),
+                password,
+                is_admin,
+            })
+            .get_result::<User>(conn)
+            .map_err(|error| match error {
+                DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, _) => {
+                    AppError::ConflictError("Username is already used by another account".into())
+                }
+                error => error.into(),
+            })?;
+
+        diesel::insert_into(user_identities::table)
+            .values(&NewUserIdentity {
+                user_id: user.id,
+                issuer: identity.issuer.clone(),
+                subject: identity.subject.clone(),
+            })
+            .execute(conn)?;
+
+        log::info!("Provisioned SSO user {}", user.username);
+        Ok(user)
+    })
+}
@@ -0,0 +1,137 @@
+use crate::db::{self, DBPool};
+use crate::models::session::{Session, SessionSummary};
+use crate::schema::{sessions, users};
+use crate::utils::error_response::{AppError, Problem};
+use crate::utils::jwt::Claims;
+use actix_web::{web, HttpMessage, HttpRequest, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde_json::json;
+
+fn user_claims(req: &HttpRequest) -> Result<Claims, AppError> {
+    req.extensions()
+        .get::<Claims>()
+        .cloned()
+        .ok_or_else(|| AppError::UnauthorizedError("Unauthorized access".into()))
+}
+
+// list the caller's active sessions
+#[utoipa::path(
+    get,
+    path = "/me/sessions",
+    tag = "sessions",
+    responses((status = 200, description = "Active sessions, newest activity first", body = [SessionSummary])),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_sessions(
+    req: HttpRequest,
+    pool: web::Data<DBPool>,
+) -> Result<HttpResponse, AppError> {
+    let user_claims = user_claims(&req)?;
+
+    let user_id = user_claims.sub;
+    let active = db::run(&pool, move |conn| {
+        sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::revoked_at.is_null())
+            .filter(sessions::expires_at.gt(Utc::now().naive_utc()))
+            .order(sessions::last_seen_at.desc())
+            .select(Session::as_select())
+            .load::<Session>(conn)
+            .map_err(AppError::from)
+    })
+    .await?;
+
+    let response: Vec<SessionSummary> = active
+        .into_iter()
+        .map(|session| SessionSummary {
+            current: user_claims.sid.as_deref() == Some(session.id.as_str()),
+            session,
+        })
+        .collect();
+
+    Ok(HttpResponse::Ok().json(response))
+}
+
+// revoke one of the caller's sessions, revoking the current one logs out
+#[utoipa::path(
+    delete,
+    path = "/me/sessions/{id}",
+    tag = "sessions",
+    params(("id" = String, Path, description = "Session id")),
+    responses(
+        (status = 200, description = "Session revoked"),
+        (status = 404, description = "No such active session", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn revoke_session(
+    req: HttpRequest,
+    pool: web::Data<DBPool>,
+    session_id: web::Path<String>,
+) -> Result<HttpResponse, AppError> {
+    let user_claims = user_claims(&req)?;
+
+    let session_id = session_id.into_inner();
+    let revoked = db::run(&pool, move |conn| {
+        diesel::update(
+            sessions::table
+                .find(session_id)
+                .filter(sessions::user_id.eq(user_claims.sub))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+        .map_err(AppError::from)
+    })
+    .await?;
+
+    if revoked == 0 {
+        return Err(AppError::NotFoundError("Session not found".into()));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Session revoked successfully"
+    })))
+}
+
+// sign a user out everywhere
+#[utoipa::path(
+    delete,
+    path = "/admin/users/{id}/sessions",
+    tag = "sessions",
+    params(("id" = i32, Path, description = "User id")),
+    responses(
+        (status = 200, description = "Sessions revoked, with their count"),
+        (status = 404, description = "No such user", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn revoke_user_sessions(
+    pool: web::Data<DBPool>,
+    user_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let user_id = user_id.into_inner();
+    let revoked = db::run(&pool, move |conn| {
+        let user_exists = users::table.find(user_id).count().get_result::<i64>(conn)?;
+
+        if user_exists == 0 {
+            return Err(AppError::NotFoundError("User not found".into()));
+        }
+
+        diesel::update(
+            sessions::table
+                .filter(sessions::user_id.eq(user_id))
+                .filter(sessions::revoked_at.is_null()),
+        )
+        .set(sessions::revoked_at.eq(Utc::now().naive_utc()))
+        .execute(conn)
+        .map_err(AppError::from)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Sessions revoked successfully",
+        "revoked": revoked
+    })))
+}
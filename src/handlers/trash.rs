@@ -0,0 +1,154 @@
+use crate::events::{ContentEvent, EventBus};
+use crate::models::category::Category;
+use crate::models::news::News;
+use crate::repository::{self, CategoryRepository, NewsRepository};
+use crate::utils::error_response::{AppError, Problem};
+use actix_web::{web, HttpResponse};
+use serde_json::json;
+
+// deleted articles, most recently deleted first
+#[utoipa::path(
+    get,
+    path = "/api/v1/trash/news",
+    tag = "trash",
+    responses(
+        (status = 200, description = "Articles in the trash, with their `deleted_at`", body = [News]),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_trashed_news(
+    news_repository: web::Data<dyn NewsRepository>,
+) -> Result<HttpResponse, AppError> {
+    let news = repository::run(&news_repository, |repo| repo.trashed()).await?;
+    Ok(HttpResponse::Ok().json(news))
+}
+
+// take an article out of the trash, filed under its categories again
+#[utoipa::path(
+    post,
+    path = "/api/v1/trash/news/{id}/restore",
+    tag = "trash",
+    params(("id" = i32, Path, description = "News id")),
+    responses(
+        (status = 200, description = "News restored", body = News),
+        (status = 404, description = "No such news in the trash", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn restore_news(
+    news_repository: web::Data<dyn NewsRepository>,
+    events: web::Data<EventBus>,
+    news_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let news_id = news_id.into_inner();
+    let news = repository::run(&news_repository, move |repo| repo.restore(news_id))
+        .await?
+        .ok_or_else(|| AppError::NotFoundError("News not found in the trash".into()))?;
+    events.publish(ContentEvent::NewsRestored { id: news_id });
+
+    Ok(HttpResponse::Ok().json(news))
+}
+
+// delete a trashed article for good
+#[utoipa::path(
+    delete,
+    path = "/api/v1/trash/news/{id}",
+    tag = "trash",
+    params(("id" = i32, Path, description = "News id")),
+    responses(
+        (status = 200, description = "News deleted permanently"),
+        (status = 404, description = "No such news in the trash", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn purge_news(
+    news_repository: web::Data<dyn NewsRepository>,
+    news_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let news_id = news_id.into_inner();
+    // it was announced as deleted when it went to the trash
+    if !repository::run(&news_repository, move |repo| repo.purge(news_id)).await? {
+        return Err(AppError::NotFoundError(
+            "News not found in the trash".into(),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "News deleted permanently"
+    })))
+}
+
+#[utoipa::path(
+    get,
+    path = "/api/v1/trash/categories",
+    tag = "trash",
+    responses(
+        (status = 200, description = "Categories in the trash, with their `deleted_at`", body = [Category]),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_trashed_categories(
+    category_repository: web::Data<dyn CategoryRepository>,
+) -> Result<HttpResponse, AppError> {
+    let categories = repository::run(&category_repository, |repo| repo.trashed()).await?;
+    Ok(HttpResponse::Ok().json(categories))
+}
+
+// its articles show it again
+#[utoipa::path(
+    post,
+    path = "/api/v1/trash/categories/{id}/restore",
+    tag = "trash",
+    params(("id" = i32, Path, description = "Category id")),
+    responses(
+        (status = 200, description = "Category restored", body = Category),
+        (status = 404, description = "No such category in the trash", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Another category has its name by now", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn restore_category(
+    category_repository: web::Data<dyn CategoryRepository>,
+    events: web::Data<EventBus>,
+    category_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let category_id = category_id.into_inner();
+    let (category, news_ids) =
+        repository::run(&category_repository, move |repo| repo.restore(category_id))
+            .await?
+            .ok_or_else(|| AppError::NotFoundError("Category not found in the trash".into()))?;
+    events.publish(ContentEvent::CategoryRestored { id: category_id });
+    for id in news_ids {
+        events.publish(ContentEvent::NewsUpdated { id });
+    }
+
+    Ok(HttpResponse::Ok().json(category))
+}
+
+// its links to articles go with it
+#[utoipa::path(
+    delete,
+    path = "/api/v1/trash/categories/{id}",
+    tag = "trash",
+    params(("id" = i32, Path, description = "Category id")),
+    responses(
+        (status = 200, description = "Category deleted permanently"),
+        (status = 404, description = "No such category in the trash", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn purge_category(
+    category_repository: web::Data<dyn CategoryRepository>,
+    category_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let category_id = category_id.into_inner();
+    if !repository::run(&category_repository, move |repo| repo.purge(category_id)).await? {
+        return Err(AppError::NotFoundError(
+            "Category not found in the trash".into(),
+        ));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Category deleted permanently"
+    })))
+}
@@ -0,0 +1,264 @@
+use crate::db::{self, DBPool};
+use crate::models::webhook::{
+    CreateWebhookRequest, CreateWebhookResponse, Delivery, NewWebhook, Webhook, DELIVERY_DEAD,
+    DELIVERY_DELIVERED, DELIVERY_PENDING,
+};
+use crate::schema::{webhook_deliveries, webhooks};
+use crate::utils::error_response::{AppError, Problem};
+use crate::webhooks::{generate_secret, is_valid_pattern};
+use actix_web::{web, HttpResponse};
+use chrono::Utc;
+use diesel::prelude::*;
+use serde::Deserialize;
+use serde_json::json;
+use utoipa::IntoParams;
+use validator::{Validate, ValidationError, ValidationErrors};
+
+// how much of the delivery log one request returns
+const DELIVERY_LOG_LIMIT: i64 = 100;
+
+#[derive(Debug, Deserialize, IntoParams)]
+pub struct DeliveryQuery {
+    /// Only deliveries in this state: `pending`, `delivered` or `dead`
+    pub status: Option<String>,
+}
+
+fn invalid(field: &'static str, code: &'static str, message: String) -> AppError {
+    let mut errors = ValidationErrors::new();
+    errors.add(
+        field,
+        ValidationError::new(code).with_message(message.into()),
+    );
+    errors.into()
+}
+
+// register a webhook, the signing secret is only shown in this response
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    request_body = CreateWebhookRequest,
+    responses(
+        (status = 201, description = "The webhook and its signing secret, shown only this once", body = CreateWebhookResponse),
+        (status = 422, description = "Invalid URL or event", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn create_webhook(
+    pool: web::Data<DBPool>,
+    webhook_data: web::Json<CreateWebhookRequest>,
+) -> Result<HttpResponse, AppError> {
+    // validate input
+    webhook_data.validate()?;
+
+    // the dispatcher only speaks http
+    if !webhook_data.url.starts_with("https://") && !webhook_data.url.starts_with("http://") {
+        return Err(invalid(
+            "url",
+            "url",
+            "url must be an http or https URL".to_string(),
+        ));
+    }
+    if let Some(pattern) = webhook_data
+        .events
+        .iter()
+        .find(|pattern| !is_valid_pattern(pattern))
+    {
+        return Err(invalid(
+            "events",
+            "unknown_event",
+            format!("Unknown event: {}", pattern),
+        ));
+    }
+
+    let webhook_data = webhook_data.into_inner();
+    let secret = generate_secret();
+    let new_webhook = NewWebhook {
+        url: webhook_data.url,
+        secret: secret.clone(),
+        events: webhook_data.events,
+    };
+
+    let webhook = db::run(&pool, move |conn| {
+        diesel::insert_into(webhooks::table)
+            .values(&new_webhook)
+            .returning(Webhook::as_returning())
+            .get_result::<Webhook>(conn)
+            .map_err(AppError::from)
+    })
+    .await?;
+
+    Ok(HttpResponse::Created().json(CreateWebhookResponse {
+        message: "Webhook created successfully".to_string(),
+        secret,
+        webhook,
+    }))
+}
+
+// list all webhooks without their secrets
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks",
+    tag = "webhooks",
+    responses((status = 200, description = "All webhooks, without their secrets", body = [Webhook])),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_webhooks(pool: web::Data<DBPool>) -> Result<HttpResponse, AppError> {
+    let hooks = db::run(&pool, |conn| {
+        webhooks::table
+            .order(webhooks::created_at.desc())
+            .select(Webhook::as_select())
+            .load::<Webhook>(conn)
+            .map_err(AppError::from)
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(hooks))
+}
+
+// remove a webhook, deliveries still queued for it are dropped
+#[utoipa::path(
+    delete,
+    path = "/api/v1/webhooks/{id}",
+    tag = "webhooks",
+    params(("id" = i32, Path, description = "Webhook id")),
+    responses(
+        (status = 200, description = "Webhook deleted"),
+        (status = 404, description = "No such webhook", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn delete_webhook(
+    pool: web::Data<DBPool>,
+    webhook_id: web::Path<i32>,
+) -> Result<HttpResponse, AppError> {
+    let webhook_id = webhook_id.into_inner();
+    let deleted = db::run(&pool, move |conn| {
+        diesel::delete(webhooks::table.find(webhook_id))
+            .execute(conn)
+            .map_err(AppError::from)
+    })
+    .await?;
+
+    if deleted == 0 {
+        return Err(AppError::NotFoundError("Webhook not found".into()));
+    }
+
+    Ok(HttpResponse::Ok().json(json!({
+        "message": "Webhook deleted successfully"
+    })))
+}
+
+// the latest deliveries to a webhook, newest first
+#[utoipa::path(
+    get,
+    path = "/api/v1/webhooks/{id}/deliveries",
+    tag = "webhooks",
+    params(("id" = i32, Path, description = "Webhook id"), DeliveryQuery),
+    responses(
+        (status = 200, description = "The latest 100 deliveries, newest first", body = [Delivery]),
+        (status = 404, description = "No such webhook", body = Problem, content_type = "application/problem+json"),
+        (status = 422, description = "Unknown status", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn list_deliveries(
+    pool: web::Data<DBPool>,
+    webhook_id: web::Path<i32>,
+    query: web::Query<DeliveryQuery>,
+) -> Result<HttpResponse, AppError> {
+    let webhook_id = webhook_id.into_inner();
+    let status = query.into_inner().status;
+    if let Some(status) = &status {
+        if ![DELIVERY_PENDING, DELIVERY_DELIVERED, DELIVERY_DEAD].contains(&status.as_str()) {
+            return Err(invalid(
+                "status",
+                "unknown_status",
+                format!("Unknown status: {}", status),
+            ));
+        }
+    }
+
+    let deliveries = db::run(&pool, move |conn| {
+        let exists = webhooks::table
+            .find(webhook_id)
+            .count()
+            .get_result::<i64>(conn)?;
+        if exists == 0 {
+            return Ok(None);
+        }
+
+        let mut query = webhook_deliveries::table
+            .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+            .into_boxed();
+        if let Some(status) = status {
+            query = query.filter(webhook_deliveries::status.eq(status));
+        }
+        query
+            .order(webhook_deliveries::id.desc())
+            .limit(DELIVERY_LOG_LIMIT)
+            .select(Delivery::as_select())
+            .load::<Delivery>(conn)
+            .map(Some)
+            .map_err(AppError::from)
+    })
+    .await?
+    .ok_or_else(|| AppError::NotFoundError("Webhook not found".into()))?;
+
+    Ok(HttpResponse::Ok().json(deliveries))
+}
+
+// send a dead delivery again, starting over with the first backoff
+#[utoipa::path(
+    post,
+    path = "/api/v1/webhooks/{id}/deliveries/{delivery_id}/retry",
+    tag = "webhooks",
+    params(
+        ("id" = i32, Path, description = "Webhook id"),
+        ("delivery_id" = i64, Path, description = "Delivery id"),
+    ),
+    responses(
+        (status = 200, description = "Delivery queued again", body = Delivery),
+        (status = 404, description = "No such delivery", body = Problem, content_type = "application/problem+json"),
+        (status = 409, description = "Delivery is not dead", body = Problem, content_type = "application/problem+json"),
+    ),
+    security(("bearer" = []), ("api_key" = []))
+)]
+pub async fn retry_delivery(
+    pool: web::Data<DBPool>,
+    path: web::Path<(i32, i64)>,
+) -> Result<HttpResponse, AppError> {
+    let (webhook_id, delivery_id) = path.into_inner();
+    let delivery = db::run(&pool, move |conn| {
+        conn.transaction::<_, AppError, _>(|conn| {
+            let Some(status) = webhook_deliveries::table
+                .find(delivery_id)
+                .filter(webhook_deliveries::webhook_id.eq(webhook_id))
+                .select(webhook_deliveries::status)
+                .for_update()
+                .first::<String>(conn)
+                .optional()?
+            else {
+                return Err(AppError::NotFoundError("Delivery not found".into()));
+            };
+            if status != DELIVERY_DEAD {
+                return Err(AppError::ConflictError(format!(
+                    "Only dead deliveries can be retried, this one is {}",
+                    status
+                )));
+            }
+
+            Ok(diesel::update(webhook_deliveries::table.find(delivery_id))
+                .set((
+                    webhook_deliveries::status.eq(DELIVERY_PENDING),
+                    webhook_deliveries::attempts.eq(0),
+                    webhook_deliveries::next_attempt_at.eq(Utc::now().naive_utc()),
+                ))
+                .returning(Delivery::as_returning())
+                .get_result::<Delivery>(conn)?)
+        })
+    })
+    .await?;
+
+    Ok(HttpResponse::Ok().json(delivery))
+}
@@ -1,26 +1,280 @@
-use actix_web::{web, App, HttpServer};
+use std::sync::Arc;
+use std::time::Duration;
+
+use actix_cors::Cors;
+use actix_web::middleware::Condition;
+use actix_web::{http::header, web, App, HttpServer};
+use clap::Parser;
 use dotenvy::dotenv;
 
+use crate::cli::{Cli, Command};
+use crate::config::{Config, CorsConfig, RateLimitBackend};
+use crate::events::EventBus;
+use crate::middleware::metrics::MetricsMiddleware;
+use crate::middleware::rate_limit::{InMemoryStore, PostgresStore, RateLimitStore};
+use crate::middleware::request_tracing::TracingMiddleware;
+use crate::repository::postgres::PgRepository;
+use crate::repository::NewsRepository;
+use crate::supervisor::{Supervisor, TaskResult};
+use crate::utils::edit_rooms::EditRooms;
+use crate::utils::news_stream::NewsStream;
+use crate::utils::oidc::OidcClient;
+use crate::utils::password::PasswordPolicy;
+use crate::utils::response_cache::ResponseCache;
+use crate::webhooks::dispatcher::Dispatcher;
+
+mod cli;
+mod config;
 mod db;
+mod events;
+mod graphql;
 mod handlers;
 mod middleware;
 mod models;
+mod openapi;
+mod repository;
 mod routes;
 mod schema;
+mod supervisor;
+mod telemetry;
+#[cfg(test)]
 mod test;
 mod utils;
+mod webhooks;
+
+// browsers may only call the API from the configured origins
+fn cors(config: &CorsConfig) -> Cors {
+    let mut cors = Cors::default()
+        .allowed_methods(["GET", "POST", "PUT", "PATCH", "DELETE"])
+        .allowed_headers([
+            header::AUTHORIZATION,
+            header::CONTENT_TYPE,
+            header::IF_MATCH,
+            header::IF_NONE_MATCH,
+            header::IF_MODIFIED_SINCE,
+        ])
+        .allowed_header("X-Api-Key")
+        // let scripts read the validators, limits and deprecation notices the API sends
+        .expose_headers([
+            header::ETAG,
+            header::LAST_MODIFIED,
+            header::RETRY_AFTER,
+            header::LINK,
+        ])
+        .expose_headers([
+            "RateLimit-Limit",
+            "RateLimit-Remaining",
+            "RateLimit-Reset",
+            "Deprecation",
+        ])
+        .max_age(config.max_age_secs);
+
+    for origin in &config.allowed_origins {
+        cors = if origin == "*" {
+            cors.allow_any_origin()
+        } else {
+            cors.allowed_origin(origin)
+        };
+    }
+
+    cors
+}
+
+// delete old sessions, idle rate limit buckets and sent webhook events, like the `cleanup` command
+fn spawn_cleanup(supervisor: &Supervisor, pool: db::DBPool, period: Duration, retention_days: i64) {
+    supervisor.spawn("cleanup", move |shutdown| {
+        let pool = pool.clone();
+        supervisor::periodic(shutdown, period, move || {
+            let pool = pool.clone();
+            async move {
+                let summary = web::block(move || -> cli::CliResult<_> {
+                    let mut conn = pool.get()?;
+                    cli::maintenance::cleanup(&mut conn, retention_days)
+                })
+                .await??;
+                tracing::info!(
+                    sessions = summary.sessions,
+                    rate_limit_buckets = summary.rate_limit_buckets,
+                    outbox_events = summary.outbox_events,
+                    "Cleanup finished"
+                );
+                TaskResult::Ok(())
+            }
+        })
+    });
+}
+
+// delete what has been in the trash longer than it is kept
+fn spawn_trash_purge(supervisor: &Supervisor, pool: db::DBPool, config: config::TrashConfig) {
+    let period = Duration::from_secs(config.purge_interval_secs);
+    supervisor.spawn("trash_purge", move |shutdown| {
+        let pool = pool.clone();
+        supervisor::periodic(shutdown, period, move || {
+            let pool = pool.clone();
+            async move {
+                let summary = web::block(move || -> cli::CliResult<_> {
+                    let mut conn = pool.get()?;
+                    cli::maintenance::purge_trash(&mut conn, config.retention_days)
+                })
+                .await??;
+                if summary.news + summary.categories > 0 {
+                    tracing::info!(
+                        news = summary.news,
+                        categories = summary.categories,
+                        "Trash purged"
+                    );
+                }
+                TaskResult::Ok(())
+            }
+        })
+    });
+}
+
+// send the content events recorded in the outbox to the registered webhooks
+fn spawn_webhooks(supervisor: &Supervisor, pool: db::DBPool, config: config::WebhookConfig) {
+    let period = Duration::from_secs(config.poll_interval_secs);
+    let dispatcher = Arc::new(Dispatcher::new(pool, config));
+    supervisor.spawn("webhooks", move |shutdown| {
+        let dispatcher = dispatcher.clone();
+        supervisor::periodic(shutdown, period, move || {
+            let dispatcher = dispatcher.clone();
+            async move {
+                let attempted = dispatcher.run_once().await?;
+                if attempted > 0 {
+                    tracing::debug!(attempted, "Webhook deliveries sent");
+                }
+                TaskResult::Ok(())
+            }
+        })
+    });
+}
+
+// turn article changes into events for the news stream
+fn spawn_news_stream(
+    supervisor: &Supervisor,
+    news_stream: web::Data<NewsStream>,
+    repository: web::Data<dyn NewsRepository>,
+) {
+    supervisor.spawn("news_stream", move |shutdown| {
+        let news_stream = news_stream.clone();
+        let repository = repository.clone();
+        async move { news_stream.run(repository, shutdown).await }
+    });
+}
 
 #[actix_web::main]
 async fn main() -> std::io::Result<()> {
     dotenv().ok();
-    let pool = db::establish_connection();
+    let cli = Cli::parse();
+
+    // refuse to start on a bad config rather than fail on the first request
+    let config = Config::load().unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(1);
+    });
+    let _telemetry = telemetry::init(&config.logging).unwrap_or_else(|e| {
+        eprintln!("Failed to initialise logging: {}", e);
+        std::process::exit(1);
+    });
+
+    match cli.command {
+        None | Some(Command::Serve) => {}
+        Some(command) => return cli::run(&config, command).map_err(std::io::Error::other),
+    }
+
+    let password_policy = web::Data::new(PasswordPolicy::from_config(&config.password)?);
+    let pool = db::establish_connection(&config.database);
+
+    if config.database.migrate_on_startup {
+        let mut conn = pool.get().map_err(std::io::Error::other)?;
+        for version in db::run_migrations(&mut conn).map_err(std::io::Error::other)? {
+            log::info!("Applied migration {}", version);
+        }
+    }
+
+    // share rate limit buckets across workers, or across replicas with postgres
+    let rate_limit_store: Arc<dyn RateLimitStore> = match config.rate_limit.backend {
+        RateLimitBackend::Postgres => Arc::new(PostgresStore::new(pool.clone())),
+        RateLimitBackend::Memory => Arc::new(InMemoryStore::new()),
+    };
+    let rate_limit_store = web::Data::from(rate_limit_store);
+    let repository = Arc::new(PgRepository::new(pool.clone()));
 
-    HttpServer::new(move || {
-        App::new()
+    // one response cache for all workers, purged by the changes any of them makes
+    let events = web::Data::new(EventBus::new());
+    let response_cache = web::Data::new(ResponseCache::new(&config.response_cache));
+    events.subscribe(response_cache.clone().into_inner());
+    let news_stream = web::Data::new(NewsStream::new(&config.news_stream));
+    events.subscribe(news_stream.clone().into_inner());
+    let edit_rooms = web::Data::new(EditRooms::new(&config.edit_locks));
+    events.subscribe(edit_rooms.clone().into_inner());
+
+    // staff single sign-on is only enabled when a provider is configured
+    let oidc_client = config
+        .oidc
+        .clone()
+        .map(|oidc| web::Data::new(OidcClient::new(oidc, config.jwt.secret.clone())));
+
+    let supervisor = web::Data::new(Supervisor::new());
+    let news_repository: Arc<dyn NewsRepository> = repository.clone();
+    spawn_news_stream(
+        &supervisor,
+        news_stream.clone(),
+        web::Data::from(news_repository),
+    );
+    if let Some(interval) = config.maintenance.cleanup_interval_secs {
+        spawn_cleanup(
+            &supervisor,
+            pool.clone(),
+            Duration::from_secs(interval),
+            config.maintenance.retention_days,
+        );
+    }
+    spawn_trash_purge(&supervisor, pool.clone(), config.trash.clone());
+    if config.webhooks.enabled {
+        spawn_webhooks(&supervisor, pool.clone(), config.webhooks.clone());
+    }
+
+    let bind_address = (config.server.host.clone(), config.server.port);
+    let workers = config.server.workers;
+    let shutdown_timeout = config.server.shutdown_timeout_secs;
+    let config = web::Data::new(config);
+    let app_supervisor = supervisor.clone();
+
+    let mut server = HttpServer::new(move || {
+        let mut app = App::new()
+            .wrap(cors(&config.cors))
+            .wrap(Condition::new(config.metrics.enabled, MetricsMiddleware))
+            .wrap(TracingMiddleware)
             .app_data(web::Data::new(pool.clone()))
-            .configure(routes::configure_routes)
-    })
-    .bind("127.0.0.1:8080")?
-    .run()
-    .await
+            .app_data(config.clone())
+            .app_data(password_policy.clone())
+            .app_data(rate_limit_store.clone())
+            .app_data(app_supervisor.clone())
+            .app_data(events.clone())
+            .app_data(response_cache.clone())
+            .app_data(news_stream.clone())
+            .app_data(edit_rooms.clone())
+            .configure(repository::configure(repository.clone()));
+        if let Some(oidc_client) = &oidc_client {
+            app = app.app_data(oidc_client.clone());
+        }
+        app.configure(|cfg| routes::configure_routes(cfg, &config))
+    });
+    if let Some(workers) = workers {
+        server = server.workers(workers);
+    }
+
+    // the server stops accepting on SIGINT/SIGTERM and drains in-flight requests
+    server
+        .shutdown_timeout(shutdown_timeout)
+        .bind(bind_address)?
+        .run()
+        .await?;
+
+    tracing::info!("Server stopped, waiting for background tasks");
+    supervisor
+        .shutdown(Duration::from_secs(shutdown_timeout))
+        .await;
+    Ok(())
 }
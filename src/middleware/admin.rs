@@ -2,7 +2,9 @@ use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Tr
 use actix_web::{Error, HttpMessage};
 use futures::future::{ready, LocalBoxFuture, Ready};
 
+use crate::utils::error_response::AppError;
 use crate::utils::jwt::Claims;
+use crate::utils::metrics::{record_auth_failure, AUTH_NOT_ADMIN};
 
 // Middleware to enforce admin access
 pub struct AdminMiddleware;
@@ -55,9 +57,11 @@ where
                 Ok(res)
             })
         } else {
-            Box::pin(ready(Err(actix_web::error::ErrorForbidden(
-                "Admin access required",
-            ))))
+            record_auth_failure(AUTH_NOT_ADMIN);
+            Box::pin(ready(Err(AppError::ForbiddenError(
+                "Admin access required".into(),
+            )
+            .into())))
         }
     }
 }
@@ -1,13 +1,56 @@
-use crate::utils::jwt::verify_token;
+use std::rc::Rc;
+use std::sync::Arc;
+
+use crate::config::Config;
+use crate::db::{self, DBPool};
+use crate::middleware::rate_limit::{
+    client_ip, InMemoryStore, RateLimitDecision, RateLimitKey, RateLimitPolicy, RateLimitStore,
+};
+use crate::models::api_key::{ApiKeyContext, SCOPE_WRITE};
+use crate::utils::api_key::{api_key_from_headers, authenticate_api_key};
+use crate::utils::error_response::AppError;
+use crate::utils::jwt::{verify_token, Claims};
+use crate::utils::metrics::{
+    record_auth_failure, AUTH_INVALID_API_KEY, AUTH_INVALID_SESSION, AUTH_INVALID_TOKEN,
+    AUTH_MISSING_CREDENTIALS, AUTH_MISSING_SCOPE,
+};
+use crate::utils::session::validate_session;
 use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
-use actix_web::{Error, HttpMessage};
+use actix_web::http::{header, StatusCode};
+use actix_web::{web, Error, HttpMessage};
 use futures::future::{ready, LocalBoxFuture, Ready};
+use serde::Deserialize;
 
-// Middleware to authenticate requests using JWT
+// Middleware to authenticate requests using JWT or an API key
 pub struct AuthMiddleWare;
+
+// Same as AuthMiddleWare, but lets anonymous requests through
+pub struct OptionalAuthMiddleWare;
+
 impl<S, B> Transform<S, ServiceRequest> for AuthMiddleWare
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = AuthMiddleWareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(AuthMiddleWareService {
+            service: Rc::new(service),
+            optional: false,
+            failures: Arc::new(InMemoryStore::new()),
+        }))
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for OptionalAuthMiddleWare
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -18,17 +61,25 @@ where
     type Future = Ready<Result<Self::Transform, Self::InitError>>;
 
     fn new_transform(&self, service: S) -> Self::Future {
-        ready(Ok(AuthMiddleWareService { service }))
+        ready(Ok(AuthMiddleWareService {
+            service: Rc::new(service),
+            optional: true,
+            failures: Arc::new(InMemoryStore::new()),
+        }))
     }
 }
 
 pub struct AuthMiddleWareService<S> {
-    service: S,
+    // shared with the future, credentials are checked before calling it
+    service: Rc<S>,
+    optional: bool,
+    // counts failed credentials when the app doesn't register a rate limit store
+    failures: Arc<dyn RateLimitStore>,
 }
 
 impl<S, B> Service<ServiceRequest> for AuthMiddleWareService<S>
 where
-    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
     S::Future: 'static,
     B: 'static,
 {
@@ -39,40 +90,223 @@ where
     forward_ready!(service);
 
     fn call(&self, req: ServiceRequest) -> Self::Future {
-        // Extract Authorization header
-        let auth_header = req.headers().get("Authorization");
-
-        match auth_header {
-            Some(auth_str) => {
-                let auth_str = auth_str.to_str().unwrap_or("");
-                if !auth_str.starts_with("Bearer ") {
-                    return Box::pin(ready(Err(actix_web::error::ErrorUnauthorized(
-                        "Invalid token format",
-                    ))));
-                }
+        let service = self.service.clone();
+        let optional = self.optional;
+        let failures = self.failures.clone();
 
-                let token = auth_str.trim_start_matches("Bearer ");
+        Box::pin(async move {
+            match authenticate(&req, &failures).await? {
+                Some((claims, api_key)) => {
+                    // keys without the write scope are read-only
+                    if let Some(context) = &api_key {
+                        if !req.method().is_safe() && !context.has_scope(SCOPE_WRITE) {
+                            record_auth_failure(AUTH_MISSING_SCOPE);
+                            return Err(AppError::ForbiddenError(
+                                "API key is missing the write scope".into(),
+                            )
+                            .into());
+                        }
+                    }
 
-                // Verify the JWT token
-                match verify_token(token) {
-                    Ok(claims) => {
-                        // Store claims in request extensions
-                        req.extensions_mut().insert(claims);
-                        let fut = self.service.call(req);
+                    tracing::Span::current().record("user_id", claims.sub);
 
-                        Box::pin(async move {
-                            let res = fut.await?;
-                            Ok(res)
-                        })
+                    // Store claims (and key context) in request extensions
+                    req.extensions_mut().insert(claims);
+                    if let Some(context) = api_key {
+                        req.extensions_mut().insert(context);
                     }
-                    Err(_) => Box::pin(ready(Err(actix_web::error::ErrorUnauthorized(
-                        "Invalid or expired token",
-                    )))),
+                    service.call(req).await
+                }
+                None if optional => service.call(req).await,
+                None => {
+                    record_auth_failure(AUTH_MISSING_CREDENTIALS);
+                    Err(AppError::UnauthorizedError("No token provided".into()).into())
                 }
             }
-            None => Box::pin(ready(Err(actix_web::error::ErrorUnauthorized(
-                "No token provided",
-            )))),
+        })
+    }
+}
+
+// the sessions and API keys behind credentials live in the database
+fn db_pool(req: &ServiceRequest) -> Result<&web::Data<DBPool>, Error> {
+    req.app_data::<web::Data<DBPool>>().ok_or_else(|| {
+        log::error!("Authentication requires a database pool");
+        AppError::DatabaseError("No database pool registered".into()).into()
+    })
+}
+
+fn app_config(req: &ServiceRequest) -> Result<&web::Data<Config>, Error> {
+    req.app_data::<web::Data<Config>>().ok_or_else(|| {
+        log::error!("Authentication requires the app config");
+        AppError::DatabaseError("No app config registered".into()).into()
+    })
+}
+
+// count rejected credentials, database failures aren't the client's fault
+fn rejected(reason: &str, e: AppError) -> AppError {
+    if matches!(e, AppError::UnauthorizedError(_)) {
+        record_auth_failure(reason);
+    }
+    e
+}
+
+enum Credentials {
+    ApiKey(String),
+    Token(String),
+}
+
+// the credentials sent with the request, None when there are none
+fn credentials(req: &ServiceRequest) -> Result<Option<Credentials>, Error> {
+    // API keys are checked first, they can also come in the Authorization header
+    if let Some(key) = api_key_from_headers(req.headers()) {
+        return Ok(Some(Credentials::ApiKey(key.to_string())));
+    }
+
+    // Extract Authorization header
+    match req.headers().get("Authorization") {
+        Some(auth_str) => {
+            let auth_str = auth_str.to_str().unwrap_or("");
+            if !auth_str.starts_with("Bearer ") {
+                record_auth_failure(AUTH_INVALID_TOKEN);
+                return Err(AppError::UnauthorizedError("Invalid token format".into()).into());
+            }
+            let token = auth_str.trim_start_matches("Bearer ").to_string();
+            Ok(Some(Credentials::Token(token)))
+        }
+        None => Ok(websocket_token(req).map(Credentials::Token)),
+    }
+}
+
+// every failed credential costs a lookup, clients that keep sending them are turned away by IP
+struct FailureLimit {
+    store: Arc<dyn RateLimitStore>,
+    policy: Arc<RateLimitPolicy>,
+    key: Arc<str>,
+}
+
+impl FailureLimit {
+    // the store shared through app data, like the rate limit middleware uses
+    fn new(req: &ServiceRequest, fallback: &Arc<dyn RateLimitStore>) -> Result<Self, Error> {
+        let settings = app_config(req)?.rate_limit.auth;
+        let policy = RateLimitPolicy::new(
+            "auth_failures",
+            settings.capacity,
+            settings.refill_per_second,
+            RateLimitKey::Ip,
+        );
+        Ok(FailureLimit {
+            store: req
+                .app_data::<web::Data<dyn RateLimitStore>>()
+                .map(|data| data.clone().into_inner())
+                .unwrap_or_else(|| fallback.clone()),
+            key: format!("{}:ip:{}", policy.scope, client_ip(req)).into(),
+            policy: Arc::new(policy),
+        })
+    }
+
+    // fails open like the rate limit middleware, a broken store shouldn't lock everyone out
+    async fn run(
+        &self,
+        f: fn(&dyn RateLimitStore, &str, &RateLimitPolicy) -> Result<RateLimitDecision, String>,
+    ) -> Option<RateLimitDecision> {
+        let (store, policy, key) = (self.store.clone(), self.policy.clone(), self.key.clone());
+        web::block(move || f(store.as_ref(), &key, &policy))
+            .await
+            .map_err(|e| e.to_string())
+            .and_then(|decision| decision)
+            .map_err(|e| log::error!("Rate limit store error: {}", e))
+            .ok()
+    }
+
+    async fn check(&self) -> Result<(), Error> {
+        match self.run(|store, key, policy| store.peek(key, policy)).await {
+            Some(decision) if !decision.allowed => {
+                Err(AppError::RateLimitedError(decision.retry_after).into())
+            }
+            _ => Ok(()),
+        }
+    }
+
+    async fn record(&self) {
+        self.run(|store, key, policy| store.acquire(key, policy))
+            .await;
+    }
+}
+
+// resolve the request credentials, Ok(None) when there are none
+async fn authenticate(
+    req: &ServiceRequest,
+    failures: &Arc<dyn RateLimitStore>,
+) -> Result<Option<(Claims, Option<ApiKeyContext>)>, Error> {
+    let Some(credentials) = credentials(req)? else {
+        return Ok(None);
+    };
+
+    let limit = FailureLimit::new(req, failures)?;
+    limit.check().await?;
+    let verified = verify(req, credentials).await;
+    if let Err(e) = &verified {
+        if e.as_response_error().status_code() == StatusCode::UNAUTHORIZED {
+            limit.record().await;
+        }
+    }
+    verified.map(Some)
+}
+
+async fn verify(
+    req: &ServiceRequest,
+    credentials: Credentials,
+) -> Result<(Claims, Option<ApiKeyContext>), Error> {
+    let token = match credentials {
+        Credentials::ApiKey(key) => {
+            let (claims, context) =
+                db::run(db_pool(req)?, move |conn| authenticate_api_key(conn, &key))
+                    .await
+                    .map_err(|e| rejected(AUTH_INVALID_API_KEY, e))?;
+            return Ok((claims, Some(context)));
         }
+        Credentials::Token(token) => token,
+    };
+    let token = token.as_str();
+
+    // Verify the JWT token
+    let claims = verify_token(&app_config(req)?.jwt, token).map_err(|_| {
+        record_auth_failure(AUTH_INVALID_TOKEN);
+        AppError::UnauthorizedError("Invalid or expired token".into())
+    })?;
+
+    // the session may have been revoked since the token was issued
+    let session_id = claims.sid.clone().ok_or_else(|| {
+        record_auth_failure(AUTH_INVALID_TOKEN);
+        AppError::UnauthorizedError("Invalid or expired token".into())
+    })?;
+    let user_id = claims.sub;
+    db::run(db_pool(req)?, move |conn| {
+        validate_session(conn, &session_id, user_id)
+    })
+    .await
+    .map_err(|e| rejected(AUTH_INVALID_SESSION, e))?;
+
+    Ok((claims, None))
+}
+
+#[derive(Deserialize)]
+struct AccessToken {
+    access_token: String,
+}
+
+// browsers can't set headers on WebSocket handshakes, those may send the token in the query.
+// Only the path of a request is logged, so it doesn't end up in the logs.
+fn websocket_token(req: &ServiceRequest) -> Option<String> {
+    let upgrade = req
+        .headers()
+        .get(header::UPGRADE)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.eq_ignore_ascii_case("websocket"));
+    if !upgrade {
+        return None;
     }
+    web::Query::<AccessToken>::from_query(req.query_string())
+        .ok()
+        .map(|query| query.into_inner().access_token)
 }
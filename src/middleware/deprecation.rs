@@ -0,0 +1,11 @@
+use actix_web::middleware::DefaultHeaders;
+
+// when the verb-in-path routes were superseded by /api/v1, as an RFC 9745 date
+const DEPRECATED_AT: &str = "@1792368000";
+
+/// Marks the responses of a legacy route as deprecated, pointing to the API docs.
+pub fn deprecated() -> DefaultHeaders {
+    DefaultHeaders::new()
+        .add(("Deprecation", DEPRECATED_AT))
+        .add(("Link", "</docs/>; rel=\"deprecation\""))
+}
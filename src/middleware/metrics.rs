@@ -0,0 +1,73 @@
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::Error;
+use futures::future::{ready, LocalBoxFuture, Ready};
+
+use crate::utils::metrics::{HTTP_REQUESTS_TOTAL, HTTP_REQUEST_DURATION_SECONDS};
+
+// label for requests that don't match any route, keeps scanners from adding series
+const UNMATCHED_ROUTE: &str = "unmatched";
+
+// Middleware to count requests and time them per route pattern
+pub struct MetricsMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for MetricsMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = MetricsMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(MetricsMiddlewareService { service }))
+    }
+}
+
+pub struct MetricsMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for MetricsMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        // resolve the pattern up front, errors from inner middleware don't carry the request
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| UNMATCHED_ROUTE.to_string());
+        let method = req.method().to_string();
+        let started = Instant::now();
+        let fut = self.service.call(req);
+
+        Box::pin(async move {
+            let res = fut.await;
+            let status = match &res {
+                Ok(res) => res.status(),
+                Err(e) => e.as_response_error().status_code(),
+            };
+
+            let labels = [method.as_str(), route.as_str(), status.as_str()];
+            HTTP_REQUESTS_TOTAL.with_label_values(&labels).inc();
+            HTTP_REQUEST_DURATION_SECONDS
+                .with_label_values(&labels)
+                .observe(started.elapsed().as_secs_f64());
+
+            res
+        })
+    }
+}
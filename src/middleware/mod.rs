@@ -1,2 +1,6 @@
 pub mod auth;
 pub mod admin;
+pub mod metrics;
+pub mod rate_limit;
+pub mod request_tracing;
+pub mod deprecation;
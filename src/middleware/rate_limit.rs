@@ -0,0 +1,415 @@
+use std::collections::HashMap;
+use std::rc::Rc;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::{web, Error, HttpMessage, ResponseError};
+use diesel::prelude::*;
+use diesel::sql_types::{Bool, Double, Text};
+use futures::future::{ready, LocalBoxFuture, Ready};
+
+use crate::db::DBPool;
+use crate::models::api_key::ApiKeyContext;
+use crate::utils::error_response::AppError;
+use crate::utils::jwt::Claims;
+
+// drop idle buckets once the in-memory store grows past this many keys,
+// at most once per sweep interval so busy stores don't scan on every request
+const MAX_IDLE_BUCKETS: usize = 10_000;
+const SWEEP_INTERVAL: Duration = Duration::from_secs(10);
+
+/// Identity a token bucket is keyed by.
+///
+/// `User` and `ApiKey` read the identity set by `AuthMiddleWare`, so they need
+/// to be wrapped inside it, and fall back to the client IP for anonymous requests.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RateLimitKey {
+    Ip,
+    User,
+    ApiKey,
+}
+
+/// Token bucket settings for one route scope.
+#[derive(Debug, Clone)]
+pub struct RateLimitPolicy {
+    pub scope: String,
+    pub capacity: u32,
+    pub refill_per_second: f64,
+    pub key: RateLimitKey,
+}
+
+impl RateLimitPolicy {
+    pub fn new(scope: &str, capacity: u32, refill_per_second: f64, key: RateLimitKey) -> Self {
+        RateLimitPolicy {
+            scope: scope.to_string(),
+            capacity,
+            refill_per_second,
+            key,
+        }
+    }
+
+    /// The bucket `RateLimitKey::User` gives a signed-in user, for limits taken outside the
+    /// middleware.
+    pub fn user_bucket(&self, user_id: i32) -> String {
+        format!("{}:user:{}", self.scope, user_id)
+    }
+
+    // seconds until a drained bucket holding `tokens` gets back to `target`
+    fn seconds_until(&self, tokens: f64, target: f64) -> u64 {
+        if tokens >= target || self.refill_per_second <= 0.0 {
+            return 0;
+        }
+        ((target - tokens) / self.refill_per_second).ceil() as u64
+    }
+
+    fn decision(&self, allowed: bool, tokens: f64) -> RateLimitDecision {
+        RateLimitDecision {
+            allowed,
+            limit: self.capacity,
+            remaining: tokens.max(0.0).floor() as u32,
+            reset_after: self.seconds_until(tokens, self.capacity as f64),
+            retry_after: if allowed {
+                0
+            } else {
+                self.seconds_until(tokens, 1.0).max(1)
+            },
+        }
+    }
+}
+
+/// Outcome of taking one token from a bucket.
+#[derive(Debug, Clone, Copy)]
+pub struct RateLimitDecision {
+    pub allowed: bool,
+    pub limit: u32,
+    pub remaining: u32,
+    pub reset_after: u64,
+    pub retry_after: u64,
+}
+
+/// Storage for token buckets, shared between workers through `web::Data`.
+pub trait RateLimitStore: Send + Sync {
+    fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, String>;
+
+    /// Whether `acquire` would allow a request right now, without taking a token.
+    fn peek(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, String>;
+}
+
+// buckets keep the settings they were made with, one store holds every scope
+struct Bucket {
+    tokens: f64,
+    capacity: f64,
+    refill_per_second: f64,
+    updated_at: Instant,
+}
+
+impl Bucket {
+    fn tokens_at(&self, now: Instant) -> f64 {
+        let elapsed = now.duration_since(self.updated_at).as_secs_f64();
+        (self.tokens + elapsed * self.refill_per_second).min(self.capacity)
+    }
+}
+
+struct Buckets {
+    by_key: HashMap<String, Bucket>,
+    swept_at: Instant,
+}
+
+/// Process-local store, good enough for a single instance.
+pub struct InMemoryStore {
+    buckets: Mutex<Buckets>,
+    max_idle_buckets: usize,
+    sweep_interval: Duration,
+}
+
+impl Default for InMemoryStore {
+    fn default() -> Self {
+        InMemoryStore {
+            buckets: Mutex::new(Buckets {
+                by_key: HashMap::new(),
+                swept_at: Instant::now(),
+            }),
+            max_idle_buckets: MAX_IDLE_BUCKETS,
+            sweep_interval: SWEEP_INTERVAL,
+        }
+    }
+}
+
+impl InMemoryStore {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    // sweeps on every request once there are more than `max_idle_buckets`
+    #[cfg(test)]
+    pub fn sweeping_after(max_idle_buckets: usize) -> Self {
+        InMemoryStore {
+            max_idle_buckets,
+            sweep_interval: Duration::ZERO,
+            ..Self::default()
+        }
+    }
+}
+
+impl RateLimitStore for InMemoryStore {
+    fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, String> {
+        let mut buckets = self.buckets.lock().map_err(|e| e.to_string())?;
+        let now = Instant::now();
+
+        // forget buckets that have refilled completely, they'd start out full anyway
+        if buckets.by_key.len() > self.max_idle_buckets
+            && now.duration_since(buckets.swept_at) >= self.sweep_interval
+        {
+            buckets
+                .by_key
+                .retain(|_, bucket| bucket.tokens_at(now) < bucket.capacity);
+            buckets.swept_at = now;
+        }
+
+        let capacity = policy.capacity as f64;
+        let bucket = buckets.by_key.entry(key.to_string()).or_insert(Bucket {
+            tokens: capacity,
+            capacity,
+            refill_per_second: policy.refill_per_second,
+            updated_at: now,
+        });
+
+        // refill for the time elapsed since the last request
+        bucket.tokens = bucket.tokens_at(now);
+        bucket.updated_at = now;
+
+        let allowed = bucket.tokens >= 1.0;
+        if allowed {
+            bucket.tokens -= 1.0;
+        }
+
+        Ok(policy.decision(allowed, bucket.tokens))
+    }
+
+    fn peek(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, String> {
+        let buckets = self.buckets.lock().map_err(|e| e.to_string())?;
+        let tokens = buckets
+            .by_key
+            .get(key)
+            .map(|bucket| bucket.tokens_at(Instant::now()))
+            .unwrap_or(policy.capacity as f64);
+
+        Ok(policy.decision(tokens >= 1.0, tokens))
+    }
+}
+
+#[derive(QueryableByName)]
+struct BucketRow {
+    #[diesel(sql_type = Double)]
+    tokens: f64,
+    #[diesel(sql_type = Bool)]
+    allowed: bool,
+}
+
+/// Postgres-backed store so that every replica sees the same buckets.
+pub struct PostgresStore {
+    pool: DBPool,
+}
+
+impl PostgresStore {
+    pub fn new(pool: DBPool) -> Self {
+        PostgresStore { pool }
+    }
+}
+
+impl RateLimitStore for PostgresStore {
+    fn acquire(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, String> {
+        let mut conn = self.pool.get().map_err(|e| e.to_string())?;
+
+        // refill and take a token in a single statement so concurrent requests can't race
+        let row = diesel::sql_query(
+            "INSERT INTO rate_limit_buckets AS b (bucket_key, tokens, allowed, updated_at) \
+             VALUES ($1, $2 - 1, TRUE, NOW()) \
+             ON CONFLICT (bucket_key) DO UPDATE SET \
+                 tokens = CASE WHEN rate_limit_refill(b.tokens, b.updated_at, $2, $3) >= 1 \
+                     THEN rate_limit_refill(b.tokens, b.updated_at, $2, $3) - 1 \
+                     ELSE rate_limit_refill(b.tokens, b.updated_at, $2, $3) END, \
+                 allowed = rate_limit_refill(b.tokens, b.updated_at, $2, $3) >= 1, \
+                 updated_at = NOW() \
+             RETURNING b.tokens, b.allowed",
+        )
+        .bind::<Text, _>(key)
+        .bind::<Double, _>(policy.capacity as f64)
+        .bind::<Double, _>(policy.refill_per_second)
+        .get_result::<BucketRow>(&mut conn)
+        .map_err(|e| e.to_string())?;
+
+        Ok(policy.decision(row.allowed, row.tokens))
+    }
+
+    fn peek(&self, key: &str, policy: &RateLimitPolicy) -> Result<RateLimitDecision, String> {
+        let mut conn = self.pool.get().map_err(|e| e.to_string())?;
+
+        let row = diesel::sql_query(
+            "SELECT rate_limit_refill(tokens, updated_at, $2, $3) AS tokens, \
+                 rate_limit_refill(tokens, updated_at, $2, $3) >= 1 AS allowed \
+             FROM rate_limit_buckets WHERE bucket_key = $1",
+        )
+        .bind::<Text, _>(key)
+        .bind::<Double, _>(policy.capacity as f64)
+        .bind::<Double, _>(policy.refill_per_second)
+        .get_result::<BucketRow>(&mut conn)
+        .optional()
+        .map_err(|e| e.to_string())?;
+
+        // no bucket yet means a full one
+        Ok(match row {
+            Some(row) => policy.decision(row.allowed, row.tokens),
+            None => policy.decision(true, policy.capacity as f64),
+        })
+    }
+}
+
+/// The address buckets keyed by IP use.
+pub fn client_ip(req: &ServiceRequest) -> String {
+    req.peer_addr()
+        .map(|addr| addr.ip().to_string())
+        .unwrap_or_else(|| "unknown".to_string())
+}
+
+// Middleware enforcing a token bucket per client for the wrapped scope
+pub struct RateLimitMiddleware {
+    policy: Arc<RateLimitPolicy>,
+    fallback: Arc<dyn RateLimitStore>,
+}
+
+impl RateLimitMiddleware {
+    /// Uses the store registered as `web::Data<dyn RateLimitStore>`, or a
+    /// private in-memory store when the app doesn't register one.
+    pub fn new(policy: RateLimitPolicy) -> Self {
+        RateLimitMiddleware {
+            policy: Arc::new(policy),
+            fallback: Arc::new(InMemoryStore::new()),
+        }
+    }
+}
+
+impl<S, B> Transform<S, ServiceRequest> for RateLimitMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = RateLimitMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(RateLimitMiddlewareService {
+            service: Rc::new(service),
+            policy: self.policy.clone(),
+            fallback: self.fallback.clone(),
+        }))
+    }
+}
+
+pub struct RateLimitMiddlewareService<S> {
+    service: Rc<S>,
+    policy: Arc<RateLimitPolicy>,
+    fallback: Arc<dyn RateLimitStore>,
+}
+
+impl<S> RateLimitMiddlewareService<S> {
+    // build the bucket key for this request according to the policy
+    fn bucket_key(&self, req: &ServiceRequest) -> String {
+        let ip = client_ip(req);
+
+        let identity = match self.policy.key {
+            RateLimitKey::Ip => None,
+            RateLimitKey::User => {
+                if let Some(claims) = req.extensions().get::<Claims>() {
+                    return self.policy.user_bucket(claims.sub);
+                }
+                None
+            }
+            RateLimitKey::ApiKey => req
+                .extensions()
+                .get::<ApiKeyContext>()
+                .map(|context| format!("key:{}", context.id)),
+        };
+
+        format!(
+            "{}:{}",
+            self.policy.scope,
+            identity.unwrap_or_else(|| format!("ip:{}", ip))
+        )
+    }
+}
+
+// attach the RateLimit-* headers describing the bucket state
+fn insert_limit_headers(
+    headers: &mut actix_web::http::header::HeaderMap,
+    decision: &RateLimitDecision,
+) {
+    let values = [
+        ("ratelimit-limit", decision.limit as u64),
+        ("ratelimit-remaining", decision.remaining as u64),
+        ("ratelimit-reset", decision.reset_after),
+    ];
+    for (name, value) in values {
+        headers.insert(HeaderName::from_static(name), HeaderValue::from(value));
+    }
+}
+
+impl<S, B> Service<ServiceRequest> for RateLimitMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error> + 'static,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let key = self.bucket_key(&req);
+
+        // prefer the store shared through app data
+        let store = req
+            .app_data::<web::Data<dyn RateLimitStore>>()
+            .map(|data| data.clone().into_inner())
+            .unwrap_or_else(|| self.fallback.clone());
+
+        let policy = self.policy.clone();
+        let service = self.service.clone();
+
+        Box::pin(async move {
+            // the postgres store does a query, keep it off the worker thread
+            let decision = web::block(move || store.acquire(&key, &policy))
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|decision| decision);
+            let decision = match decision {
+                Ok(decision) => decision,
+                Err(e) => {
+                    // fail open, a broken store shouldn't take the API down
+                    log::error!("Rate limit store error: {}", e);
+                    return service.call(req).await;
+                }
+            };
+
+            if !decision.allowed {
+                let error = AppError::RateLimitedError(decision.retry_after);
+                let mut response = error.error_response();
+                insert_limit_headers(response.headers_mut(), &decision);
+
+                return Err(actix_web::error::InternalError::from_response(error, response).into());
+            }
+
+            let mut res = service.call(req).await?;
+            insert_limit_headers(res.headers_mut(), &decision);
+            Ok(res)
+        })
+    }
+}
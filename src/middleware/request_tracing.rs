@@ -0,0 +1,123 @@
+use std::time::Instant;
+
+use actix_web::dev::{forward_ready, Service, ServiceRequest, ServiceResponse, Transform};
+use actix_web::http::header::{HeaderName, HeaderValue};
+use actix_web::Error;
+use futures::future::{ready, LocalBoxFuture, Ready};
+use tracing::field::Empty;
+use tracing::Instrument;
+use uuid::Uuid;
+
+pub const REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+// longest request id we accept from callers
+const MAX_REQUEST_ID_LENGTH: usize = 128;
+
+// keep the caller's id when it is sane, otherwise make one up
+fn request_id(req: &ServiceRequest) -> String {
+    req.headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| {
+            !id.is_empty()
+                && id.len() <= MAX_REQUEST_ID_LENGTH
+                && id.bytes().all(|b| b.is_ascii_graphic())
+        })
+        .map(str::to_owned)
+        .unwrap_or_else(|| Uuid::new_v4().to_string())
+}
+
+// Middleware to run every request in a span tagged with its request id
+pub struct TracingMiddleware;
+
+impl<S, B> Transform<S, ServiceRequest> for TracingMiddleware
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type InitError = ();
+    type Transform = TracingMiddlewareService<S>;
+    type Future = Ready<Result<Self::Transform, Self::InitError>>;
+
+    fn new_transform(&self, service: S) -> Self::Future {
+        ready(Ok(TracingMiddlewareService { service }))
+    }
+}
+
+pub struct TracingMiddlewareService<S> {
+    service: S,
+}
+
+impl<S, B> Service<ServiceRequest> for TracingMiddlewareService<S>
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = Error>,
+    S::Future: 'static,
+    B: 'static,
+{
+    type Response = ServiceResponse<B>;
+    type Error = Error;
+    type Future = LocalBoxFuture<'static, Result<Self::Response, Self::Error>>;
+
+    forward_ready!(service);
+
+    fn call(&self, req: ServiceRequest) -> Self::Future {
+        let id = request_id(&req);
+        let route = req
+            .match_pattern()
+            .unwrap_or_else(|| "unmatched".to_string());
+
+        // user_id is filled in by the auth middleware
+        let span = tracing::info_span!(
+            "http_request",
+            request_id = %id,
+            method = %req.method(),
+            route = %route,
+            path = %req.path(),
+            user_id = Empty,
+            status = Empty,
+            latency_ms = Empty,
+        );
+        #[cfg(feature = "otlp")]
+        crate::telemetry::otlp::set_remote_parent(&span, req.headers());
+
+        let header = HeaderValue::from_str(&id).expect("request ids are visible ASCII");
+        let started = Instant::now();
+        let fut = span.in_scope(|| self.service.call(req));
+
+        Box::pin(
+            async move {
+                let res = fut.await;
+                let status = match &res {
+                    Ok(res) => res.status(),
+                    Err(e) => e.as_response_error().status_code(),
+                };
+
+                let span = tracing::Span::current();
+                span.record("status", status.as_u16());
+                span.record("latency_ms", started.elapsed().as_millis() as u64);
+                if status.is_server_error() {
+                    tracing::error!("request failed");
+                } else {
+                    tracing::info!("request completed");
+                }
+
+                match res {
+                    Ok(mut res) => {
+                        res.headers_mut().insert(REQUEST_ID_HEADER, header);
+                        Ok(res)
+                    }
+                    // errors from inner middleware are rendered here to carry the header too
+                    Err(e) => {
+                        let mut response = e.error_response();
+                        response.headers_mut().insert(REQUEST_ID_HEADER, header);
+                        Err(actix_web::error::InternalError::from_response(e, response).into())
+                    }
+                }
+            }
+            .instrument(span),
+        )
+    }
+}
@@ -0,0 +1,79 @@
+use crate::schema::api_keys;
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
+use validator::Validate;
+
+// scopes an API key can be granted
+pub const SCOPE_READ: &str = "read";
+pub const SCOPE_WRITE: &str = "write";
+pub const SCOPE_ADMIN: &str = "admin";
+pub const SCOPES: [&str; 3] = [SCOPE_READ, SCOPE_WRITE, SCOPE_ADMIN];
+
+// the key hash is never selected
+#[derive(Debug, Queryable, Selectable, Serialize, Clone, ToSchema)]
+#[diesel(table_name = api_keys)]
+pub struct ApiKey {
+    pub id: i32,
+    pub owner_id: i32,
+    pub name: String,
+    pub key_prefix: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+    pub last_used_at: Option<chrono::NaiveDateTime>,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = api_keys)]
+pub struct NewApiKey {
+    pub owner_id: i32,
+    pub name: String,
+    pub key_prefix: String,
+    pub key_hash: String,
+    pub scopes: Vec<String>,
+    pub expires_at: Option<chrono::NaiveDateTime>,
+}
+
+// struct for api key creation request
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateApiKeyRequest {
+    #[validate(length(
+        min = 1,
+        max = 100,
+        message = "name must be between 1 and 100 characters"
+    ))]
+    pub name: String,
+    // defaults to the admin creating the key
+    pub owner_id: Option<i32>,
+    #[validate(length(min = 1, message = "at least one scope is required"))]
+    pub scopes: Vec<String>,
+    #[validate(range(
+        min = 1,
+        max = 3650,
+        message = "expiry must be between 1 and 3650 days"
+    ))]
+    pub expires_in_days: Option<i64>,
+}
+
+// the plaintext key is only ever returned here
+#[derive(Serialize, ToSchema)]
+pub struct CreateApiKeyResponse {
+    pub message: String,
+    pub key: String,
+    pub api_key: ApiKey,
+}
+
+/// Authentication context stored next to `Claims` for requests made with an API key.
+#[derive(Debug, Clone)]
+pub struct ApiKeyContext {
+    pub id: i32,
+    pub scopes: Vec<String>,
+}
+
+impl ApiKeyContext {
+    pub fn has_scope(&self, scope: &str) -> bool {
+        self.scopes.iter().any(|s| s == scope)
+    }
+}
@@ -1,10 +1,11 @@
 use crate::schema::categories;
 use diesel::prelude::{AsChangeset, Insertable, Queryable};
-use serde::{Deserialize, Serialize};
+use serde::{Deserialize, Deserializer, Serialize};
+use utoipa::ToSchema;
 use validator::Validate;
 
-#[derive(Debug, Queryable, Serialize, Deserialize, Insertable, Validate)]
-#[table_name = "categories"]
+#[derive(Debug, Clone, Queryable, Serialize, Deserialize, Insertable, Validate, ToSchema)]
+#[diesel(table_name = categories)]
 pub struct Category {
     pub id: i32,
     #[validate(length(min = 1, message = "Name is required"))]
@@ -13,40 +14,73 @@ pub struct Category {
     pub description: Option<String>,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    // set while the category is in the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+// a new category, the id and timestamps come from the database
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = categories)]
+pub struct NewCategory {
+    pub name: String,
+    pub description: Option<String>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct CategorySummary {
     pub id: i32,
     pub name: String,
 }
 
-#[derive(Debug, Deserialize, Validate)]
+// the id and timestamps are set by the database, clients may still send them
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateCategoryRequest {
+    #[validate(length(min = 1, message = "Name is required"))]
+    pub name: String,
+    #[validate(length(min = 1, message = "Description is required"))]
+    pub description: Option<String>,
+}
+
+#[derive(Debug, Deserialize, Validate, ToSchema)]
 pub struct UpdateCategoryRequest {
     #[validate(length(
         min = 3,
         max = 100,
         message = "Category name must be between 3 and 100 characters!"
     ))]
-    pub name: String,
+    pub name: Option<String>,
 
+    // left out keeps the description, null clears it
     #[validate(length(
         min = 3,
         max = 255,
         message = "Category description must be between 3 and 255 characters!"
     ))]
-    pub description: String,
+    #[serde(default, deserialize_with = "present")]
+    #[schema(value_type = Option<String>, nullable)]
+    pub description: Option<Option<String>>,
+}
+
+// Some for a field that was sent, even as null, `default` covers the one that wasn't
+fn present<'de, D, T>(deserializer: D) -> Result<Option<Option<T>>, D::Error>
+where
+    D: Deserializer<'de>,
+    T: Deserialize<'de>,
+{
+    Option::<T>::deserialize(deserializer).map(Some)
 }
 
-#[derive(AsChangeset)]
+#[derive(Debug, Clone, AsChangeset)]
 #[diesel(table_name = categories)]
 pub struct CategoryChangeset {
-    pub name: Option<String>,        // Use Option<String> for optional updates
-    pub description: Option<String>, // Use Option<String> for optional updates
+    pub name: Option<String>, // Use Option<String> for optional updates
+    // Some(None) clears the description
+    pub description: Option<Option<String>>,
     pub updated_at: chrono::NaiveDateTime,
 }
 
-#[derive(Debug, Serialize)]
+#[derive(Debug, Serialize, ToSchema)]
 pub struct UpdateCategoryResponse {
     pub message: String,
     pub category: Category,
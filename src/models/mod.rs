@@ -1,3 +1,6 @@
 pub mod user;
 pub mod category;
 pub mod news;
+pub mod api_key;
+pub mod session;
+pub mod webhook;
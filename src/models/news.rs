@@ -1,11 +1,14 @@
 use crate::schema::news;
 use crate::schema::news_categories;
-use diesel::prelude::{Insertable, Queryable};
+use diesel::prelude::{AsChangeset, Insertable, Queryable, QueryableByName};
 use serde::{Deserialize, Serialize};
+use utoipa::ToSchema;
 
 use crate::models::category::CategorySummary;
 
-#[derive(Queryable, Serialize, Deserialize, Insertable, Debug, Clone)]
+#[derive(
+    Queryable, QueryableByName, Serialize, Deserialize, Insertable, Debug, Clone, ToSchema,
+)]
 #[diesel(table_name = news)]
 pub struct News {
     pub id: i32,
@@ -14,6 +17,27 @@ pub struct News {
     pub author_id: i32,
     pub created_at: chrono::NaiveDateTime,
     pub updated_at: chrono::NaiveDateTime,
+    // set while the article is in the trash
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub deleted_at: Option<chrono::NaiveDateTime>,
+}
+
+// a new article, the timestamps come from the database defaults
+#[derive(Debug, Clone, Insertable)]
+#[diesel(table_name = news)]
+pub struct NewNews {
+    pub title: String,
+    pub content: String,
+    pub author_id: i32,
+}
+
+// fields left as None keep their current value
+#[derive(Debug, Clone, AsChangeset)]
+#[diesel(table_name = news)]
+pub struct NewsChangeset {
+    pub title: Option<String>,
+    pub content: Option<String>,
+    pub updated_at: chrono::NaiveDateTime,
 }
 
 #[derive(Insertable, Queryable, Serialize, Deserialize)]
@@ -23,7 +47,7 @@ pub struct NewsCategory {
     pub category_id: i32,
 }
 
-#[derive(Queryable, Serialize, Deserialize)]
+#[derive(Queryable, Serialize, Deserialize, ToSchema)]
 pub struct NewsDetail {
     pub id: i32,
     pub title: String,
@@ -35,7 +59,7 @@ pub struct NewsDetail {
 }
 
 // struct for json response list_news
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, ToSchema)]
 pub struct NewsSummary {
     pub title: String,
     pub created_at: chrono::NaiveDateTime,
@@ -0,0 +1,38 @@
+use crate::schema::sessions;
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use serde::Serialize;
+use utoipa::ToSchema;
+
+#[derive(Debug, Queryable, Selectable, Serialize, Clone, ToSchema)]
+#[diesel(table_name = sessions)]
+pub struct Session {
+    pub id: String,
+    pub user_id: i32,
+    pub device_label: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub last_seen_at: chrono::NaiveDateTime,
+    pub expires_at: chrono::NaiveDateTime,
+    pub revoked_at: Option<chrono::NaiveDateTime>,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = sessions)]
+pub struct NewSession {
+    pub id: String,
+    pub user_id: i32,
+    pub device_label: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+    pub expires_at: chrono::NaiveDateTime,
+}
+
+// struct for json response list_sessions
+#[derive(Serialize, ToSchema)]
+pub struct SessionSummary {
+    #[serde(flatten)]
+    pub session: Session,
+    // the session the request was made with
+    pub current: bool,
+}
@@ -1,8 +1,8 @@
-use crate::schema::users;
+use crate::schema::{user_identities, users};
 use diesel::prelude::{Insertable, Queryable};
 use serde::{Deserialize, Serialize};
 
-#[derive(Queryable, Serialize, Deserialize)]
+#[derive(Clone, Queryable, Serialize, Deserialize)]
 pub struct User {
     pub id: i32,
     pub username: String,
@@ -19,3 +19,12 @@ pub struct NewUser {
     pub password: String,
     pub is_admin: bool,
 }
+
+// link between a user and an external identity provider account
+#[derive(Insertable)]
+#[diesel(table_name = user_identities)]
+pub struct NewUserIdentity {
+    pub user_id: i32,
+    pub issuer: String,
+    pub subject: String,
+}
@@ -0,0 +1,81 @@
+use crate::schema::{outbox_events, webhook_deliveries, webhooks};
+use diesel::prelude::{Insertable, Queryable, Selectable};
+use serde::{Deserialize, Serialize};
+use serde_json::Value;
+use utoipa::ToSchema;
+use validator::Validate;
+
+// what a delivery can be waiting for
+pub const DELIVERY_PENDING: &str = "pending";
+pub const DELIVERY_DELIVERED: &str = "delivered";
+// gave up after the last attempt, kept for the delivery log
+pub const DELIVERY_DEAD: &str = "dead";
+
+// the secret is never selected
+#[derive(Debug, Queryable, Selectable, Serialize, Clone, ToSchema)]
+#[diesel(table_name = webhooks)]
+pub struct Webhook {
+    pub id: i32,
+    pub url: String,
+    pub events: Vec<String>,
+    pub created_at: chrono::NaiveDateTime,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webhooks)]
+pub struct NewWebhook {
+    pub url: String,
+    pub secret: String,
+    pub events: Vec<String>,
+}
+
+// struct for webhook registration request
+#[derive(Debug, Deserialize, Validate, ToSchema)]
+pub struct CreateWebhookRequest {
+    #[validate(url(message = "url must be an http or https URL"))]
+    pub url: String,
+    // e.g. `news.created`, `category.*` or `*`
+    #[validate(length(min = 1, message = "at least one event is required"))]
+    pub events: Vec<String>,
+}
+
+// the secret is only ever returned here
+#[derive(Serialize, ToSchema)]
+pub struct CreateWebhookResponse {
+    pub message: String,
+    pub secret: String,
+    pub webhook: Webhook,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = outbox_events)]
+pub struct NewOutboxEvent {
+    pub event_type: String,
+    pub payload: Value,
+}
+
+#[derive(Insertable)]
+#[diesel(table_name = webhook_deliveries)]
+pub struct NewDelivery {
+    pub webhook_id: i32,
+    pub event_id: i64,
+    pub event_type: String,
+}
+
+/// One event sent to one webhook, with the outcome of the latest attempt.
+#[derive(Debug, Queryable, Selectable, Serialize, Clone, ToSchema)]
+#[diesel(table_name = webhook_deliveries)]
+pub struct Delivery {
+    pub id: i64,
+    pub webhook_id: i32,
+    pub event_id: i64,
+    pub event_type: String,
+    // `pending`, `delivered` or `dead`
+    pub status: String,
+    pub attempts: i32,
+    pub next_attempt_at: chrono::NaiveDateTime,
+    pub last_status_code: Option<i32>,
+    pub last_error: Option<String>,
+    pub created_at: chrono::NaiveDateTime,
+    pub delivered_at: Option<chrono::NaiveDateTime>,
+}
@@ -0,0 +1,187 @@
+use actix_web::web;
+use utoipa::openapi::path::{Operation, PathItem};
+use utoipa::openapi::security::{ApiKey, ApiKeyValue, HttpAuthScheme, HttpBuilder, SecurityScheme};
+use utoipa::openapi::{Deprecated, OpenApi as Spec, SecurityRequirement};
+use utoipa::{Modify, OpenApi};
+use utoipa_swagger_ui::SwaggerUi;
+
+use crate::handlers;
+
+// (method, path)
+type Route = (&'static str, &'static str);
+
+// legacy routes next to the /api/v1 operation they copy
+const LEGACY_ROUTES: [(Route, Route); 9] = [
+    (("get", "/api/v1/news"), ("get", "/admin/list-news")),
+    (("get", "/api/v1/news"), ("get", "/user/list-news")),
+    (("post", "/api/v1/news"), ("post", "/admin/create-news")),
+    (
+        ("get", "/api/v1/news/{id}"),
+        ("get", "/admin/news-detail/{id}"),
+    ),
+    (
+        ("patch", "/api/v1/news/{id}"),
+        ("put", "/admin/news-update/{id}"),
+    ),
+    (
+        ("delete", "/api/v1/news/{id}"),
+        ("get", "/admin/delete-news/{id}"),
+    ),
+    (
+        ("post", "/api/v1/categories"),
+        ("post", "/admin/create-category"),
+    ),
+    (
+        ("post", "/api/v1/categories"),
+        ("post", "/user/create-category"),
+    ),
+    (
+        ("delete", "/api/v1/categories/{id}"),
+        ("get", "/admin/delete-category/{id}"),
+    ),
+];
+
+/// The OpenAPI document, generated from the handler annotations.
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "News REST API",
+        description = "Articles, categories and their administration. Errors are `application/problem+json`."
+    ),
+    paths(
+        handlers::health::healthz,
+        handlers::health::readyz,
+        handlers::health::version,
+        handlers::metrics::metrics,
+        handlers::auth::register,
+        handlers::auth::login,
+        handlers::oidc::oidc_login,
+        handlers::oidc::oidc_callback,
+        handlers::news::list_news,
+        handlers::news::get_news_detail,
+        handlers::news::stream_news,
+        handlers::edit_rooms::join_room,
+        handlers::admin::create_news,
+        handlers::admin::update_news,
+        handlers::admin::delete_news,
+        handlers::categories::list_categories,
+        handlers::categories::get_category,
+        handlers::admin::create_category,
+        handlers::admin::update_category,
+        handlers::admin::delete_category,
+        handlers::api_keys::create_api_key,
+        handlers::api_keys::list_api_keys,
+        handlers::api_keys::revoke_api_key,
+        handlers::sessions::list_sessions,
+        handlers::sessions::revoke_session,
+        handlers::sessions::revoke_user_sessions,
+        handlers::webhooks::create_webhook,
+        handlers::webhooks::list_webhooks,
+        handlers::webhooks::delete_webhook,
+        handlers::webhooks::list_deliveries,
+        handlers::webhooks::retry_delivery,
+        handlers::trash::list_trashed_news,
+        handlers::trash::restore_news,
+        handlers::trash::purge_news,
+        handlers::trash::list_trashed_categories,
+        handlers::trash::restore_category,
+        handlers::trash::purge_category,
+        handlers::graphql::execute,
+        handlers::graphql::execute_query,
+        handlers::graphql::playground,
+    ),
+    modifiers(&SecuritySchemes, &LegacyRoutes),
+    tags(
+        (name = "health", description = "Probes, build info and metrics"),
+        (name = "auth", description = "Registration and sign-in"),
+        (name = "news", description = "Articles"),
+        (name = "categories", description = "Article categories"),
+        (name = "api keys", description = "Keys for machine clients"),
+        (name = "sessions", description = "Signed-in devices"),
+        (name = "webhooks", description = "Content events pushed to other services"),
+        (name = "trash", description = "Deleted articles and categories, until they are purged"),
+        (name = "graphql", description = "News, categories and authors in one query"),
+    )
+)]
+pub struct ApiDoc;
+
+struct SecuritySchemes;
+
+impl Modify for SecuritySchemes {
+    fn modify(&self, openapi: &mut Spec) {
+        let components = openapi.components.get_or_insert_with(Default::default);
+        components.add_security_scheme(
+            "bearer",
+            SecurityScheme::Http(
+                HttpBuilder::new()
+                    .scheme(HttpAuthScheme::Bearer)
+                    .bearer_format("JWT")
+                    .build(),
+            ),
+        );
+        components.add_security_scheme(
+            "api_key",
+            SecurityScheme::ApiKey(ApiKey::Header(ApiKeyValue::new("X-Api-Key"))),
+        );
+    }
+}
+
+fn operation<'a>(item: &'a mut PathItem, method: &str) -> &'a mut Option<Operation> {
+    match method {
+        "get" => &mut item.get,
+        "post" => &mut item.post,
+        "put" => &mut item.put,
+        "patch" => &mut item.patch,
+        "delete" => &mut item.delete,
+        _ => unreachable!("unsupported method {}", method),
+    }
+}
+
+struct LegacyRoutes;
+
+impl Modify for LegacyRoutes {
+    fn modify(&self, openapi: &mut Spec) {
+        for ((method, path), (legacy_method, legacy_path)) in LEGACY_ROUTES {
+            let Some(mut copy) = openapi
+                .paths
+                .paths
+                .get_mut(path)
+                .and_then(|item| operation(item, method).clone())
+            else {
+                continue;
+            };
+
+            // operation ids have to be unique, a handler has one route per legacy scope
+            let scope = legacy_path.split('/').nth(1).unwrap_or_default();
+            copy.operation_id = copy.operation_id.map(|id| format!("{}_{}", id, scope));
+            copy.deprecated = Some(Deprecated::True);
+            let public_read = legacy_path.starts_with("/user") && legacy_method == "get";
+            copy.security = Some(if public_read {
+                // anonymous, or any credentials
+                vec![
+                    SecurityRequirement::default(),
+                    SecurityRequirement::new("bearer", Vec::<String>::new()),
+                    SecurityRequirement::new("api_key", Vec::<String>::new()),
+                ]
+            } else {
+                // the /admin scope and /user writes need an admin
+                vec![
+                    SecurityRequirement::new("bearer", Vec::<String>::new()),
+                    SecurityRequirement::new("api_key", Vec::<String>::new()),
+                ]
+            });
+
+            let item = openapi
+                .paths
+                .paths
+                .entry(legacy_path.to_string())
+                .or_default();
+            *operation(item, legacy_method) = Some(copy);
+        }
+    }
+}
+
+/// Serves the document at `/openapi.json` and Swagger UI at `/docs/`.
+pub fn configure(cfg: &mut web::ServiceConfig) {
+    cfg.service(SwaggerUi::new("/docs/{_:.*}").url("/openapi.json", ApiDoc::openapi()));
+}
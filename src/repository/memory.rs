@@ -0,0 +1,606 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap};
+use std::sync::{Mutex, MutexGuard};
+
+use chrono::{NaiveDateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::models::category::{Category, CategoryChangeset, CategorySummary, NewCategory};
+use crate::models::news::{NewNews, News, NewsChangeset, NewsSummary};
+use crate::models::user::{NewUser, User};
+use crate::repository::{CategoryRepository, NewsFilter, NewsRepository, UserRepository};
+use crate::utils::error_response::AppError;
+use crate::webhooks;
+
+#[derive(Default)]
+struct Tables {
+    news: BTreeMap<i32, News>,
+    categories: BTreeMap<i32, Category>,
+    // (news_id, category_id)
+    news_categories: Vec<(i32, i32)>,
+    users: BTreeMap<i32, User>,
+    // (event_type, payload), the events a webhook dispatcher would send
+    outbox: Vec<(String, Value)>,
+    last_id: i32,
+}
+
+impl Tables {
+    fn next_id(&mut self) -> i32 {
+        self.last_id += 1;
+        self.last_id
+    }
+
+    // the foreign keys the database would enforce
+    fn check_links(&self, category_ids: &[i32]) -> Result<(), AppError> {
+        match category_ids
+            .iter()
+            .find(|id| !self.categories.contains_key(id))
+        {
+            Some(id) => Err(AppError::InvalidReferenceError(format!(
+                "Category {} does not exist",
+                id
+            ))),
+            None => Ok(()),
+        }
+    }
+
+    fn is_linked(&self, news_id: i32, category_id: i32) -> bool {
+        self.news_categories.contains(&(news_id, category_id))
+            && self.live_category(category_id).is_some()
+    }
+
+    // the articles that aren't in the trash
+    fn live_news(&self) -> impl Iterator<Item = &News> {
+        self.news.values().filter(|news| news.deleted_at.is_none())
+    }
+
+    fn live_article(&self, id: i32) -> Option<&News> {
+        self.news.get(&id).filter(|news| news.deleted_at.is_none())
+    }
+
+    fn live_category(&self, id: i32) -> Option<&Category> {
+        self.categories
+            .get(&id)
+            .filter(|category| category.deleted_at.is_none())
+    }
+
+    // only live categories hold on to their name
+    fn name_taken(&self, name: &str, except: Option<i32>) -> bool {
+        self.categories.values().any(|existing| {
+            Some(existing.id) != except && existing.deleted_at.is_none() && existing.name == name
+        })
+    }
+
+    fn record_event<T: Serialize>(&mut self, event_type: &str, row: &T) {
+        self.outbox
+            .push((event_type.to_string(), webhooks::payload(row)));
+    }
+
+    // articles show the names of their categories, so they change with them
+    fn touch_linked_news(&mut self, category_id: i32, now: NaiveDateTime) -> Vec<i32> {
+        let mut touched = Vec::new();
+        for (news_id, _) in self
+            .news_categories
+            .iter()
+            .filter(|(_, linked)| *linked == category_id)
+        {
+            if let Some(news) = self.news.get_mut(news_id) {
+                news.updated_at = now;
+                touched.push(*news_id);
+            }
+        }
+        touched
+    }
+}
+
+// a row matches when no version was asked for
+fn is_version(updated_at: NaiveDateTime, version: Option<NaiveDateTime>) -> bool {
+    version.is_none_or(|version| version == updated_at)
+}
+
+// newest first, like the database orders them
+fn newest_first(news: &mut [News]) {
+    news.sort_by_key(|news| Reverse((news.created_at, news.id)));
+}
+
+// `limit` rows of each key after skipping `offset`, in the order they come in
+fn page_per<T>(rows: Vec<T>, key: impl Fn(&T) -> i32, offset: i64, limit: i64) -> Vec<T> {
+    let mut seen: HashMap<i32, i64> = HashMap::new();
+    rows.into_iter()
+        .filter(|row| {
+            let position = seen.entry(key(row)).or_default();
+            *position += 1;
+            *position > offset && *position <= offset + limit
+        })
+        .collect()
+}
+
+/// Keeps everything in memory, for handler tests that shouldn't need Postgres.
+#[derive(Default)]
+pub struct MemoryRepository {
+    tables: Mutex<Tables>,
+}
+
+impl MemoryRepository {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn tables(&self) -> MutexGuard<'_, Tables> {
+        self.tables.lock().unwrap()
+    }
+
+    /// The events recorded so far, oldest first.
+    pub fn outbox(&self) -> Vec<(String, Value)> {
+        self.tables().outbox.clone()
+    }
+}
+
+impl NewsRepository for MemoryRepository {
+    fn list(&self) -> Result<Vec<NewsSummary>, AppError> {
+        Ok(self
+            .tables()
+            .live_news()
+            .map(|news| NewsSummary {
+                title: news.title.clone(),
+                created_at: news.created_at,
+            })
+            .collect())
+    }
+
+    fn list_version(&self) -> Result<(i64, Option<NaiveDateTime>), AppError> {
+        let tables = self.tables();
+        Ok((
+            tables.live_news().count() as i64,
+            tables.live_news().map(|news| news.updated_at).max(),
+        ))
+    }
+
+    fn page(
+        &self,
+        filter: &NewsFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<News>, i64), AppError> {
+        let tables = self.tables();
+        let title = filter.title.as_ref().map(|title| title.to_lowercase());
+        let mut matching: Vec<News> = tables
+            .live_news()
+            .filter(|news| filter.author_id.is_none_or(|id| news.author_id == id))
+            .filter(|news| {
+                filter
+                    .category_id
+                    .is_none_or(|id| tables.is_linked(news.id, id))
+            })
+            .filter(|news| {
+                title
+                    .as_ref()
+                    .is_none_or(|title| news.title.to_lowercase().contains(title))
+            })
+            .cloned()
+            .collect();
+        newest_first(&mut matching);
+
+        let total = matching.len() as i64;
+        let page = matching
+            .into_iter()
+            .skip(offset as usize)
+            .take(limit as usize)
+            .collect();
+        Ok((page, total))
+    }
+
+    fn find(&self, id: i32) -> Result<Option<News>, AppError> {
+        Ok(self.tables().live_article(id).cloned())
+    }
+
+    fn by_authors(
+        &self,
+        author_ids: &[i32],
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<News>, AppError> {
+        let mut news: Vec<News> = self
+            .tables()
+            .live_news()
+            .filter(|news| author_ids.contains(&news.author_id))
+            .cloned()
+            .collect();
+        newest_first(&mut news);
+        Ok(page_per(news, |news| news.author_id, offset, limit))
+    }
+
+    fn categories_of(&self, news_ids: &[i32]) -> Result<Vec<(i32, Category)>, AppError> {
+        let tables = self.tables();
+        let mut links: Vec<(i32, Category)> = tables
+            .news_categories
+            .iter()
+            .filter(|(news_id, _)| news_ids.contains(news_id))
+            .filter_map(|(news_id, category_id)| {
+                let category = tables.live_category(*category_id)?;
+                Some((*news_id, category.clone()))
+            })
+            .collect();
+        links.sort_by(|(_, a), (_, b)| a.name.cmp(&b.name));
+        Ok(links)
+    }
+
+    fn news_in(
+        &self,
+        category_ids: &[i32],
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, News)>, AppError> {
+        let tables = self.tables();
+        let mut links: Vec<(i32, News)> = tables
+            .news_categories
+            .iter()
+            .filter(|(_, category_id)| category_ids.contains(category_id))
+            .filter_map(|(news_id, category_id)| {
+                let news = tables.live_article(*news_id)?;
+                Some((*category_id, news.clone()))
+            })
+            .collect();
+        links.sort_by_key(|(_, news)| Reverse((news.created_at, news.id)));
+        Ok(page_per(
+            links,
+            |(category_id, _)| *category_id,
+            offset,
+            limit,
+        ))
+    }
+
+    fn categories(&self, news_id: i32) -> Result<Vec<CategorySummary>, AppError> {
+        let tables = self.tables();
+        Ok(tables
+            .news_categories
+            .iter()
+            .filter(|(linked_news, _)| *linked_news == news_id)
+            .filter_map(|(_, category_id)| tables.live_category(*category_id))
+            .map(|category| CategorySummary {
+                id: category.id,
+                name: category.name.clone(),
+            })
+            .collect())
+    }
+
+    fn create(&self, new_news: NewNews, category_ids: &[i32]) -> Result<News, AppError> {
+        let mut tables = self.tables();
+        if !tables.users.contains_key(&new_news.author_id) {
+            return Err(AppError::InvalidReferenceError(format!(
+                "User {} does not exist",
+                new_news.author_id
+            )));
+        }
+        tables.check_links(category_ids)?;
+
+        let now = Utc::now().naive_utc();
+        let news = News {
+            id: tables.next_id(),
+            title: new_news.title,
+            content: new_news.content,
+            author_id: new_news.author_id,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        };
+        tables.news.insert(news.id, news.clone());
+        for &category_id in category_ids {
+            tables.news_categories.push((news.id, category_id));
+        }
+        tables.record_event(webhooks::NEWS_CREATED, &news);
+        tables.record_event(webhooks::NEWS_PUBLISHED, &news);
+        Ok(news)
+    }
+
+    fn update(
+        &self,
+        id: i32,
+        changes: NewsChangeset,
+        category_ids: Option<&[i32]>,
+        version: Option<NaiveDateTime>,
+    ) -> Result<Option<News>, AppError> {
+        let mut tables = self.tables();
+        if !tables
+            .live_article(id)
+            .is_some_and(|news| is_version(news.updated_at, version))
+        {
+            return Ok(None);
+        }
+        if let Some(category_ids) = category_ids {
+            tables.check_links(category_ids)?;
+            tables.news_categories.retain(|(news_id, _)| *news_id != id);
+            for &category_id in category_ids {
+                tables.news_categories.push((id, category_id));
+            }
+        }
+
+        let news = tables.news.get_mut(&id).unwrap();
+        if let Some(title) = changes.title {
+            news.title = title;
+        }
+        if let Some(content) = changes.content {
+            news.content = content;
+        }
+        news.updated_at = changes.updated_at;
+        let news = news.clone();
+        tables.record_event(webhooks::NEWS_UPDATED, &news);
+        Ok(Some(news))
+    }
+
+    fn delete(&self, id: i32, version: Option<NaiveDateTime>) -> Result<bool, AppError> {
+        let mut tables = self.tables();
+        if !tables
+            .live_article(id)
+            .is_some_and(|news| is_version(news.updated_at, version))
+        {
+            return Ok(false);
+        }
+        // the category links are kept for a restore
+        let news = tables.news.get_mut(&id).unwrap();
+        news.deleted_at = Some(Utc::now().naive_utc());
+        let news = news.clone();
+        tables.record_event(webhooks::NEWS_DELETED, &news);
+        Ok(true)
+    }
+
+    fn trashed(&self) -> Result<Vec<News>, AppError> {
+        let mut news: Vec<News> = self
+            .tables()
+            .news
+            .values()
+            .filter(|news| news.deleted_at.is_some())
+            .cloned()
+            .collect();
+        news.sort_by_key(|news| Reverse((news.deleted_at, news.id)));
+        Ok(news)
+    }
+
+    fn restore(&self, id: i32) -> Result<Option<News>, AppError> {
+        let mut tables = self.tables();
+        let Some(news) = tables
+            .news
+            .get_mut(&id)
+            .filter(|news| news.deleted_at.is_some())
+        else {
+            return Ok(None);
+        };
+        news.deleted_at = None;
+        news.updated_at = Utc::now().naive_utc();
+        let news = news.clone();
+        tables.record_event(webhooks::NEWS_RESTORED, &news);
+        Ok(Some(news))
+    }
+
+    fn purge(&self, id: i32) -> Result<bool, AppError> {
+        let mut tables = self.tables();
+        if tables
+            .news
+            .get(&id)
+            .is_none_or(|news| news.deleted_at.is_none())
+        {
+            return Ok(false);
+        }
+        tables.news.remove(&id);
+        tables.news_categories.retain(|(news_id, _)| *news_id != id);
+        Ok(true)
+    }
+}
+
+impl CategoryRepository for MemoryRepository {
+    fn list(&self) -> Result<Vec<Category>, AppError> {
+        let mut categories: Vec<Category> = self
+            .tables()
+            .categories
+            .values()
+            .filter(|category| category.deleted_at.is_none())
+            .cloned()
+            .collect();
+        categories.sort_by(|a, b| a.name.cmp(&b.name));
+        Ok(categories)
+    }
+
+    fn find(&self, id: i32) -> Result<Option<Category>, AppError> {
+        Ok(self.tables().live_category(id).cloned())
+    }
+
+    fn create(&self, category: NewCategory) -> Result<Category, AppError> {
+        let mut tables = self.tables();
+        if tables.name_taken(&category.name, None) {
+            return Err(AppError::ConflictError(format!(
+                "Category {} already exists",
+                category.name
+            )));
+        }
+
+        let now = Utc::now().naive_utc();
+        let category = Category {
+            id: tables.next_id(),
+            name: category.name,
+            description: category.description,
+            created_at: now,
+            updated_at: now,
+            deleted_at: None,
+        };
+        tables.categories.insert(category.id, category.clone());
+        tables.record_event(webhooks::CATEGORY_CREATED, &category);
+        Ok(category)
+    }
+
+    fn update(
+        &self,
+        id: i32,
+        changes: CategoryChangeset,
+        version: Option<NaiveDateTime>,
+    ) -> Result<Option<Category>, AppError> {
+        let mut tables = self.tables();
+        if let Some(name) = &changes.name {
+            if tables.name_taken(name, Some(id)) {
+                return Err(AppError::ConflictError(format!(
+                    "Category {} already exists",
+                    name
+                )));
+            }
+        }
+        if !tables
+            .live_category(id)
+            .is_some_and(|category| is_version(category.updated_at, version))
+        {
+            return Ok(None);
+        }
+        if changes.name.is_some() {
+            tables.touch_linked_news(id, changes.updated_at);
+        }
+
+        let category = tables.categories.get_mut(&id).unwrap();
+
+        if let Some(name) = changes.name {
+            category.name = name;
+        }
+        if let Some(description) = changes.description {
+            category.description = description;
+        }
+        category.updated_at = changes.updated_at;
+        let category = category.clone();
+        tables.record_event(webhooks::CATEGORY_UPDATED, &category);
+        Ok(Some(category))
+    }
+
+    fn delete(&self, id: i32, version: Option<NaiveDateTime>) -> Result<bool, AppError> {
+        let mut tables = self.tables();
+        if !tables
+            .live_category(id)
+            .is_some_and(|category| is_version(category.updated_at, version))
+        {
+            return Ok(false);
+        }
+        let now = Utc::now().naive_utc();
+        tables.touch_linked_news(id, now);
+        let category = tables.categories.get_mut(&id).unwrap();
+        category.deleted_at = Some(now);
+        let category = category.clone();
+        tables.record_event(webhooks::CATEGORY_DELETED, &category);
+        Ok(true)
+    }
+
+    fn trashed(&self) -> Result<Vec<Category>, AppError> {
+        let mut categories: Vec<Category> = self
+            .tables()
+            .categories
+            .values()
+            .filter(|category| category.deleted_at.is_some())
+            .cloned()
+            .collect();
+        categories.sort_by_key(|category| Reverse((category.deleted_at, category.id)));
+        Ok(categories)
+    }
+
+    fn restore(&self, id: i32) -> Result<Option<(Category, Vec<i32>)>, AppError> {
+        let mut tables = self.tables();
+        let now = Utc::now().naive_utc();
+        let Some(name) = tables
+            .categories
+            .get(&id)
+            .filter(|category| category.deleted_at.is_some())
+            .map(|category| category.name.clone())
+        else {
+            return Ok(None);
+        };
+        // its name may have been given to a new category since
+        if tables.name_taken(&name, Some(id)) {
+            return Err(AppError::ConflictError(format!(
+                "Category {} already exists",
+                name
+            )));
+        }
+        let category = tables.categories.get_mut(&id).unwrap();
+        category.deleted_at = None;
+        category.updated_at = now;
+        let category = category.clone();
+        let news_ids = tables.touch_linked_news(id, now);
+        tables.record_event(webhooks::CATEGORY_RESTORED, &category);
+        Ok(Some((category, news_ids)))
+    }
+
+    fn purge(&self, id: i32) -> Result<bool, AppError> {
+        let mut tables = self.tables();
+        if tables
+            .categories
+            .get(&id)
+            .is_none_or(|category| category.deleted_at.is_none())
+        {
+            return Ok(false);
+        }
+        tables.categories.remove(&id);
+        tables
+            .news_categories
+            .retain(|(_, category_id)| *category_id != id);
+        Ok(true)
+    }
+}
+
+impl UserRepository for MemoryRepository {
+    fn find_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
+        Ok(self
+            .tables()
+            .users
+            .values()
+            .find(|user| user.username == username)
+            .cloned())
+    }
+
+    fn find_many(&self, ids: &[i32]) -> Result<Vec<User>, AppError> {
+        let tables = self.tables();
+        Ok(ids
+            .iter()
+            .filter_map(|id| tables.users.get(id).cloned())
+            .collect())
+    }
+
+    fn authors(&self) -> Result<Vec<User>, AppError> {
+        let tables = self.tables();
+        let mut authors: Vec<User> = tables
+            .users
+            .values()
+            .filter(|user| tables.live_news().any(|news| news.author_id == user.id))
+            .cloned()
+            .collect();
+        authors.sort_by(|a, b| a.username.cmp(&b.username));
+        Ok(authors)
+    }
+
+    fn create(&self, user: NewUser) -> Result<User, AppError> {
+        let mut tables = self.tables();
+        if tables
+            .users
+            .values()
+            .any(|existing| existing.username == user.username)
+        {
+            return Err(AppError::ConflictError(format!(
+                "User {} already exists",
+                user.username
+            )));
+        }
+
+        let now = Utc::now().naive_utc();
+        let user = User {
+            id: tables.next_id(),
+            username: user.username,
+            password: user.password,
+            is_admin: user.is_admin,
+            created_at: now,
+            updated_at: now,
+        };
+        tables.users.insert(user.id, user.clone());
+        Ok(user)
+    }
+
+    fn update_password(&self, id: i32, password_hash: String) -> Result<(), AppError> {
+        if let Some(user) = self.tables().users.get_mut(&id) {
+            user.password = password_hash;
+            user.updated_at = Utc::now().naive_utc();
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,137 @@
+use std::sync::Arc;
+
+use actix_web::web;
+use chrono::NaiveDateTime;
+
+use crate::db::PoolError;
+use crate::models::category::{Category, CategoryChangeset, CategorySummary, NewCategory};
+use crate::models::news::{NewNews, News, NewsChangeset, NewsSummary};
+use crate::models::user::{NewUser, User};
+use crate::utils::error_response::AppError;
+
+#[cfg(test)]
+pub mod memory;
+pub mod postgres;
+
+/// Narrows a page of articles, every filter that is set has to match.
+#[derive(Debug, Clone, Default)]
+pub struct NewsFilter {
+    pub author_id: Option<i32>,
+    pub category_id: Option<i32>,
+    // case insensitive part of the title
+    pub title: Option<String>,
+}
+
+/// Articles and the categories they are filed under. Deleted articles go to the trash, every
+/// call but the trash ones leaves them out.
+pub trait NewsRepository: Send + Sync {
+    fn list(&self) -> Result<Vec<NewsSummary>, AppError>;
+    // (number of articles, latest updated_at), every change to the list changes one of them
+    fn list_version(&self) -> Result<(i64, Option<NaiveDateTime>), AppError>;
+    // newest first, with the number of articles matching the filter
+    fn page(
+        &self,
+        filter: &NewsFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<News>, i64), AppError>;
+    fn find(&self, id: i32) -> Result<Option<News>, AppError>;
+    fn categories(&self, news_id: i32) -> Result<Vec<CategorySummary>, AppError>;
+    // the batched lookups behind the GraphQL relations, newest articles first. Lists of articles
+    // are paged per author or category: `limit` of each after skipping `offset`
+    fn by_authors(
+        &self,
+        author_ids: &[i32],
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<News>, AppError>;
+    // (news_id, category) for every link of the given articles
+    fn categories_of(&self, news_ids: &[i32]) -> Result<Vec<(i32, Category)>, AppError>;
+    // (category_id, news) for the links of the given categories
+    fn news_in(
+        &self,
+        category_ids: &[i32],
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, News)>, AppError>;
+    fn create(&self, news: NewNews, category_ids: &[i32]) -> Result<News, AppError>;
+    // the category links are replaced when `category_ids` is given, None when the article is gone
+    // or no longer has the `updated_at` given as `version`
+    fn update(
+        &self,
+        id: i32,
+        changes: NewsChangeset,
+        category_ids: Option<&[i32]>,
+        version: Option<NaiveDateTime>,
+    ) -> Result<Option<News>, AppError>;
+    // moves the article to the trash, false when there was nothing to delete, or it changed
+    // since `version`
+    fn delete(&self, id: i32, version: Option<NaiveDateTime>) -> Result<bool, AppError>;
+    // most recently deleted first
+    fn trashed(&self) -> Result<Vec<News>, AppError>;
+    // takes the article out of the trash with its category links, None when it isn't in there
+    fn restore(&self, id: i32) -> Result<Option<News>, AppError>;
+    // deletes a trashed article for good, false when it isn't in the trash
+    fn purge(&self, id: i32) -> Result<bool, AppError>;
+}
+
+/// Trashed categories keep their name and links until they are purged, their articles just
+/// don't show them.
+pub trait CategoryRepository: Send + Sync {
+    // ordered by name
+    fn list(&self) -> Result<Vec<Category>, AppError>;
+    fn find(&self, id: i32) -> Result<Option<Category>, AppError>;
+    fn create(&self, category: NewCategory) -> Result<Category, AppError>;
+    // `version` works like it does for articles, a new name also touches the linked articles
+    fn update(
+        &self,
+        id: i32,
+        changes: CategoryChangeset,
+        version: Option<NaiveDateTime>,
+    ) -> Result<Option<Category>, AppError>;
+    // moves the category to the trash, its articles are touched
+    fn delete(&self, id: i32, version: Option<NaiveDateTime>) -> Result<bool, AppError>;
+    fn trashed(&self) -> Result<Vec<Category>, AppError>;
+    // a conflict when a live category has its name by now. Its articles are touched again and
+    // their ids returned, what was cached of them while the category was gone isn't tagged with it
+    fn restore(&self, id: i32) -> Result<Option<(Category, Vec<i32>)>, AppError>;
+    fn purge(&self, id: i32) -> Result<bool, AppError>;
+}
+
+pub trait UserRepository: Send + Sync {
+    fn find_by_username(&self, username: &str) -> Result<Option<User>, AppError>;
+    fn find_many(&self, ids: &[i32]) -> Result<Vec<User>, AppError>;
+    // users who wrote at least one article, by username
+    fn authors(&self) -> Result<Vec<User>, AppError>;
+    fn create(&self, user: NewUser) -> Result<User, AppError>;
+    fn update_password(&self, id: i32, password_hash: String) -> Result<(), AppError>;
+}
+
+/// Registers one backend as `web::Data<dyn NewsRepository>`, `web::Data<dyn CategoryRepository>`
+/// and `web::Data<dyn UserRepository>`.
+pub fn configure<R>(repository: Arc<R>) -> impl Fn(&mut web::ServiceConfig) + Clone
+where
+    R: NewsRepository + CategoryRepository + UserRepository + 'static,
+{
+    move |cfg| {
+        let news: Arc<dyn NewsRepository> = repository.clone();
+        let categories: Arc<dyn CategoryRepository> = repository.clone();
+        let users: Arc<dyn UserRepository> = repository.clone();
+        cfg.app_data(web::Data::from(news))
+            .app_data(web::Data::from(categories))
+            .app_data(web::Data::from(users));
+    }
+}
+
+/// Calls the repository on the blocking pool, the Postgres backend queries synchronously.
+pub async fn run<R, T, F>(repository: &web::Data<R>, call: F) -> Result<T, AppError>
+where
+    R: ?Sized + Send + Sync + 'static,
+    T: Send + 'static,
+    F: FnOnce(&R) -> Result<T, AppError> + Send + 'static,
+{
+    let repository = repository.clone().into_inner();
+    web::block(move || call(&repository))
+        .await
+        .map_err(PoolError::from)?
+}
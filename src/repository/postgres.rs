@@ -0,0 +1,511 @@
+use chrono::{NaiveDateTime, Utc};
+use diesel::pg::Pg;
+use diesel::prelude::*;
+use diesel::sql_types::{Array, BigInt, Integer};
+use serde::Serialize;
+
+use crate::db::{DBConnection, DBPool, PoolError};
+use crate::models::category::{Category, CategoryChangeset, CategorySummary, NewCategory};
+use crate::models::news::{NewNews, News, NewsCategory, NewsChangeset, NewsSummary};
+use crate::models::user::{NewUser, User};
+use crate::models::webhook::NewOutboxEvent;
+use crate::repository::{CategoryRepository, NewsFilter, NewsRepository, UserRepository};
+use crate::schema::{categories, news, news_categories, outbox_events, users};
+use crate::utils::error_response::AppError;
+use crate::webhooks;
+
+/// Diesel backed repositories, every call checks out its own pooled connection.
+pub struct PgRepository {
+    pool: DBPool,
+}
+
+impl PgRepository {
+    pub fn new(pool: DBPool) -> Self {
+        PgRepository { pool }
+    }
+
+    fn conn(&self) -> Result<DBConnection, AppError> {
+        self.pool
+            .get()
+            .map_err(|e| AppError::from(PoolError::from(e)))
+    }
+}
+
+// an article with the category it was found through
+#[derive(QueryableByName)]
+struct CategoryNews {
+    #[diesel(sql_type = Integer)]
+    category_id: i32,
+    #[diesel(embed)]
+    news: News,
+}
+
+// link an article to the given categories
+fn link_categories(conn: &mut PgConnection, news_id: i32, category_ids: &[i32]) -> QueryResult<()> {
+    let entries: Vec<NewsCategory> = category_ids
+        .iter()
+        .map(|&category_id| NewsCategory {
+            news_id,
+            category_id,
+        })
+        .collect();
+
+    diesel::insert_into(news_categories::table)
+        .values(&entries)
+        .execute(conn)?;
+    Ok(())
+}
+
+// locks the row until the transaction ends, false when it's gone, in the trash or was changed
+// since `version`
+fn lock_news(
+    conn: &mut PgConnection,
+    id: i32,
+    version: Option<NaiveDateTime>,
+) -> QueryResult<bool> {
+    let updated_at = news::table
+        .find(id)
+        .filter(news::deleted_at.is_null())
+        .select(news::updated_at)
+        .for_update()
+        .first::<NaiveDateTime>(conn)
+        .optional()?;
+    Ok(updated_at.is_some_and(|updated_at| version.is_none_or(|version| version == updated_at)))
+}
+
+fn lock_category(
+    conn: &mut PgConnection,
+    id: i32,
+    version: Option<NaiveDateTime>,
+) -> QueryResult<bool> {
+    let updated_at = categories::table
+        .find(id)
+        .filter(categories::deleted_at.is_null())
+        .select(categories::updated_at)
+        .for_update()
+        .first::<NaiveDateTime>(conn)
+        .optional()?;
+    Ok(updated_at.is_some_and(|updated_at| version.is_none_or(|version| version == updated_at)))
+}
+
+// articles show the names of their categories, so they change with them
+fn touch_linked_news(conn: &mut PgConnection, category_id: i32) -> QueryResult<Vec<i32>> {
+    diesel::update(
+        news::table.filter(
+            news::id.eq_any(
+                news_categories::table
+                    .filter(news_categories::category_id.eq(category_id))
+                    .select(news_categories::news_id),
+            ),
+        ),
+    )
+    .set(news::updated_at.eq(Utc::now().naive_utc()))
+    .returning(news::id)
+    .get_results(conn)
+}
+
+// queue an event for the webhooks, it is committed or rolled back with the change
+fn record_event<T: Serialize>(
+    conn: &mut PgConnection,
+    event_type: &str,
+    row: &T,
+) -> QueryResult<()> {
+    diesel::insert_into(outbox_events::table)
+        .values(NewOutboxEvent {
+            event_type: event_type.to_string(),
+            payload: webhooks::payload(row),
+        })
+        .execute(conn)?;
+    Ok(())
+}
+
+// the articles a filter matches, unordered
+fn filtered_news(filter: &NewsFilter) -> news::BoxedQuery<'static, Pg> {
+    let mut query = news::table.filter(news::deleted_at.is_null()).into_boxed();
+    if let Some(author_id) = filter.author_id {
+        query = query.filter(news::author_id.eq(author_id));
+    }
+    if let Some(category_id) = filter.category_id {
+        query = query.filter(
+            news::id.eq_any(
+                news_categories::table
+                    .inner_join(categories::table)
+                    .filter(news_categories::category_id.eq(category_id))
+                    .filter(categories::deleted_at.is_null())
+                    .select(news_categories::news_id),
+            ),
+        );
+    }
+    if let Some(title) = &filter.title {
+        // the title is matched literally
+        let escaped = title
+            .replace('\\', "\\\\")
+            .replace('%', "\\%")
+            .replace('_', "\\_");
+        query = query.filter(news::title.ilike(format!("%{}%", escaped)));
+    }
+    query
+}
+
+impl NewsRepository for PgRepository {
+    fn list(&self) -> Result<Vec<NewsSummary>, AppError> {
+        let rows = news::table
+            .filter(news::deleted_at.is_null())
+            .select((news::title, news::created_at))
+            .load::<(String, chrono::NaiveDateTime)>(&mut self.conn()?)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(title, created_at)| NewsSummary { title, created_at })
+            .collect())
+    }
+
+    fn list_version(&self) -> Result<(i64, Option<NaiveDateTime>), AppError> {
+        Ok(news::table
+            .filter(news::deleted_at.is_null())
+            .select((
+                diesel::dsl::count_star(),
+                diesel::dsl::max(news::updated_at),
+            ))
+            .first::<(i64, Option<NaiveDateTime>)>(&mut self.conn()?)?)
+    }
+
+    fn page(
+        &self,
+        filter: &NewsFilter,
+        offset: i64,
+        limit: i64,
+    ) -> Result<(Vec<News>, i64), AppError> {
+        let conn = &mut self.conn()?;
+        let total = filtered_news(filter).count().get_result::<i64>(conn)?;
+        let page = filtered_news(filter)
+            .order((news::created_at.desc(), news::id.desc()))
+            .offset(offset)
+            .limit(limit)
+            .load::<News>(conn)?;
+        Ok((page, total))
+    }
+
+    fn find(&self, id: i32) -> Result<Option<News>, AppError> {
+        Ok(news::table
+            .find(id)
+            .filter(news::deleted_at.is_null())
+            .first::<News>(&mut self.conn()?)
+            .optional()?)
+    }
+
+    fn by_authors(
+        &self,
+        author_ids: &[i32],
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<News>, AppError> {
+        // numbered per author, so only the pages asked for are read
+        Ok(diesel::sql_query(
+            "SELECT id, title, content, author_id, created_at, updated_at, deleted_at FROM ( \
+                 SELECT news.*, ROW_NUMBER() OVER ( \
+                     PARTITION BY author_id ORDER BY created_at DESC, id DESC \
+                 ) AS position \
+                 FROM news WHERE author_id = ANY($1) AND deleted_at IS NULL \
+             ) ranked \
+             WHERE position > $2 AND position <= $2 + $3 \
+             ORDER BY created_at DESC, id DESC",
+        )
+        .bind::<Array<Integer>, _>(author_ids)
+        .bind::<BigInt, _>(offset)
+        .bind::<BigInt, _>(limit)
+        .load::<News>(&mut self.conn()?)?)
+    }
+
+    fn categories_of(&self, news_ids: &[i32]) -> Result<Vec<(i32, Category)>, AppError> {
+        Ok(news_categories::table
+            .inner_join(categories::table)
+            .filter(news_categories::news_id.eq_any(news_ids))
+            .filter(categories::deleted_at.is_null())
+            .order(categories::name.asc())
+            .select((news_categories::news_id, categories::all_columns))
+            .load::<(i32, Category)>(&mut self.conn()?)?)
+    }
+
+    fn news_in(
+        &self,
+        category_ids: &[i32],
+        offset: i64,
+        limit: i64,
+    ) -> Result<Vec<(i32, News)>, AppError> {
+        let rows = diesel::sql_query(
+            "SELECT category_id, id, title, content, author_id, created_at, updated_at, deleted_at \
+             FROM ( \
+                 SELECT news_categories.category_id, news.*, ROW_NUMBER() OVER ( \
+                     PARTITION BY news_categories.category_id \
+                     ORDER BY news.created_at DESC, news.id DESC \
+                 ) AS position \
+                 FROM news_categories JOIN news ON news.id = news_categories.news_id \
+                 WHERE news_categories.category_id = ANY($1) AND news.deleted_at IS NULL \
+             ) ranked \
+             WHERE position > $2 AND position <= $2 + $3 \
+             ORDER BY created_at DESC, id DESC",
+        )
+        .bind::<Array<Integer>, _>(category_ids)
+        .bind::<BigInt, _>(offset)
+        .bind::<BigInt, _>(limit)
+        .load::<CategoryNews>(&mut self.conn()?)?;
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.category_id, row.news))
+            .collect())
+    }
+
+    fn categories(&self, news_id: i32) -> Result<Vec<CategorySummary>, AppError> {
+        let rows = news_categories::table
+            .inner_join(categories::table)
+            .filter(news_categories::news_id.eq(news_id))
+            .filter(categories::deleted_at.is_null())
+            .select((categories::id, categories::name))
+            .load::<(i32, String)>(&mut self.conn()?)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(id, name)| CategorySummary { id, name })
+            .collect())
+    }
+
+    fn create(&self, new_news: NewNews, category_ids: &[i32]) -> Result<News, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            let created = diesel::insert_into(news::table)
+                .values(&new_news)
+                .get_result::<News>(conn)?;
+
+            link_categories(conn, created.id, category_ids)?;
+            record_event(conn, webhooks::NEWS_CREATED, &created)?;
+            record_event(conn, webhooks::NEWS_PUBLISHED, &created)?;
+
+            Ok(created)
+        })
+    }
+
+    fn update(
+        &self,
+        id: i32,
+        changes: NewsChangeset,
+        category_ids: Option<&[i32]>,
+        version: Option<NaiveDateTime>,
+    ) -> Result<Option<News>, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            if !lock_news(conn, id, version)? {
+                return Ok(None);
+            }
+            let Some(updated) = diesel::update(news::table.find(id))
+                .set(&changes)
+                .get_result::<News>(conn)
+                .optional()?
+            else {
+                return Ok(None);
+            };
+
+            if let Some(category_ids) = category_ids {
+                diesel::delete(news_categories::table.filter(news_categories::news_id.eq(id)))
+                    .execute(conn)?;
+                link_categories(conn, id, category_ids)?;
+            }
+            record_event(conn, webhooks::NEWS_UPDATED, &updated)?;
+
+            Ok(Some(updated))
+        })
+    }
+
+    fn delete(&self, id: i32, version: Option<NaiveDateTime>) -> Result<bool, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            if !lock_news(conn, id, version)? {
+                return Ok(false);
+            }
+            // the category links are kept for a restore
+            let deleted = diesel::update(news::table.find(id))
+                .set(news::deleted_at.eq(Utc::now().naive_utc()))
+                .get_result::<News>(conn)?;
+            record_event(conn, webhooks::NEWS_DELETED, &deleted)?;
+            Ok(true)
+        })
+    }
+
+    fn trashed(&self) -> Result<Vec<News>, AppError> {
+        Ok(news::table
+            .filter(news::deleted_at.is_not_null())
+            .order((news::deleted_at.desc(), news::id.desc()))
+            .load::<News>(&mut self.conn()?)?)
+    }
+
+    fn restore(&self, id: i32) -> Result<Option<News>, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            // a new updated_at, so copies from before it was deleted aren't taken for current
+            let restored =
+                diesel::update(news::table.find(id).filter(news::deleted_at.is_not_null()))
+                    .set((
+                        news::deleted_at.eq(None::<NaiveDateTime>),
+                        news::updated_at.eq(Utc::now().naive_utc()),
+                    ))
+                    .get_result::<News>(conn)
+                    .optional()?;
+            if let Some(restored) = &restored {
+                record_event(conn, webhooks::NEWS_RESTORED, restored)?;
+            }
+            Ok(restored)
+        })
+    }
+
+    fn purge(&self, id: i32) -> Result<bool, AppError> {
+        // the category links go with it
+        let purged = diesel::delete(news::table.find(id).filter(news::deleted_at.is_not_null()))
+            .execute(&mut self.conn()?)?;
+        Ok(purged > 0)
+    }
+}
+
+impl CategoryRepository for PgRepository {
+    fn list(&self) -> Result<Vec<Category>, AppError> {
+        Ok(categories::table
+            .filter(categories::deleted_at.is_null())
+            .order(categories::name.asc())
+            .load::<Category>(&mut self.conn()?)?)
+    }
+
+    fn find(&self, id: i32) -> Result<Option<Category>, AppError> {
+        Ok(categories::table
+            .find(id)
+            .filter(categories::deleted_at.is_null())
+            .first::<Category>(&mut self.conn()?)
+            .optional()?)
+    }
+
+    fn create(&self, category: NewCategory) -> Result<Category, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            // a duplicate name becomes a conflict
+            let created = diesel::insert_into(categories::table)
+                .values(&category)
+                .get_result::<Category>(conn)?;
+            record_event(conn, webhooks::CATEGORY_CREATED, &created)?;
+            Ok(created)
+        })
+    }
+
+    fn update(
+        &self,
+        id: i32,
+        changes: CategoryChangeset,
+        version: Option<NaiveDateTime>,
+    ) -> Result<Option<Category>, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            if !lock_category(conn, id, version)? {
+                return Ok(None);
+            }
+            if changes.name.is_some() {
+                touch_linked_news(conn, id)?;
+            }
+            let updated = diesel::update(categories::table.find(id))
+                .set(&changes)
+                .get_result::<Category>(conn)
+                .optional()?;
+            if let Some(updated) = &updated {
+                record_event(conn, webhooks::CATEGORY_UPDATED, updated)?;
+            }
+            Ok(updated)
+        })
+    }
+
+    fn delete(&self, id: i32, version: Option<NaiveDateTime>) -> Result<bool, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            if !lock_category(conn, id, version)? {
+                return Ok(false);
+            }
+            touch_linked_news(conn, id)?;
+            let deleted = diesel::update(categories::table.find(id))
+                .set(categories::deleted_at.eq(Utc::now().naive_utc()))
+                .get_result::<Category>(conn)?;
+            record_event(conn, webhooks::CATEGORY_DELETED, &deleted)?;
+            Ok(true)
+        })
+    }
+
+    fn trashed(&self) -> Result<Vec<Category>, AppError> {
+        Ok(categories::table
+            .filter(categories::deleted_at.is_not_null())
+            .order((categories::deleted_at.desc(), categories::id.desc()))
+            .load::<Category>(&mut self.conn()?)?)
+    }
+
+    fn restore(&self, id: i32) -> Result<Option<(Category, Vec<i32>)>, AppError> {
+        self.conn()?.transaction::<_, AppError, _>(|conn| {
+            let restored = diesel::update(
+                categories::table
+                    .find(id)
+                    .filter(categories::deleted_at.is_not_null()),
+            )
+            .set((
+                categories::deleted_at.eq(None::<NaiveDateTime>),
+                categories::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .get_result::<Category>(conn)
+            .optional()?;
+            let Some(restored) = restored else {
+                return Ok(None);
+            };
+            let news_ids = touch_linked_news(conn, id)?;
+            record_event(conn, webhooks::CATEGORY_RESTORED, &restored)?;
+            Ok(Some((restored, news_ids)))
+        })
+    }
+
+    fn purge(&self, id: i32) -> Result<bool, AppError> {
+        let purged = diesel::delete(
+            categories::table
+                .find(id)
+                .filter(categories::deleted_at.is_not_null()),
+        )
+        .execute(&mut self.conn()?)?;
+        Ok(purged > 0)
+    }
+}
+
+impl UserRepository for PgRepository {
+    fn find_by_username(&self, username: &str) -> Result<Option<User>, AppError> {
+        Ok(users::table
+            .filter(users::username.eq(username))
+            .first::<User>(&mut self.conn()?)
+            .optional()?)
+    }
+
+    fn find_many(&self, ids: &[i32]) -> Result<Vec<User>, AppError> {
+        Ok(users::table
+            .filter(users::id.eq_any(ids))
+            .load::<User>(&mut self.conn()?)?)
+    }
+
+    fn authors(&self) -> Result<Vec<User>, AppError> {
+        Ok(users::table
+            .filter(
+                users::id.eq_any(
+                    news::table
+                        .filter(news::deleted_at.is_null())
+                        .select(news::author_id),
+                ),
+            )
+            .order(users::username.asc())
+            .load::<User>(&mut self.conn()?)?)
+    }
+
+    fn create(&self, user: NewUser) -> Result<User, AppError> {
+        Ok(diesel::insert_into(users::table)
+            .values(&user)
+            .get_result::<User>(&mut self.conn()?)?)
+    }
+
+    fn update_password(&self, id: i32, password_hash: String) -> Result<(), AppError> {
+        diesel::update(users::table.find(id))
+            .set((
+                users::password.eq(password_hash),
+                users::updated_at.eq(Utc::now().naive_utc()),
+            ))
+            .execute(&mut self.conn()?)?;
+        Ok(())
+    }
+}
@@ -1,64 +1,286 @@
-use actix_web::web;
+use actix_web::http::Method;
+use actix_web::{web, Route};
 
 use crate::{
-    handlers::admin::update_news,
-    middleware::{admin::AdminMiddleware, auth::AuthMiddleWare},
+    config::{Config, RateLimitSettings},
+    handlers::{
+        admin, api_keys, auth, categories, edit_rooms, graphql, health, metrics, news, oidc,
+        sessions, trash, webhooks,
+    },
+    middleware::{
+        admin::AdminMiddleware,
+        auth::{AuthMiddleWare, OptionalAuthMiddleWare},
+        deprecation::deprecated,
+        rate_limit::{RateLimitKey, RateLimitMiddleware, RateLimitPolicy},
+    },
 };
 
-pub fn configure_routes(cfg: &mut web::ServiceConfig) {
-    // Admin routes
-    cfg.service(
-        web::scope("/admin")
-            .wrap(AuthMiddleWare) // First check if user is authenticated
-            .wrap(AdminMiddleware) // Then check if user is admin
-            .route(
-                "/create-news",
-                web::post().to(crate::handlers::admin::create_news),
-            )
-            .route("/news-update/{id}", web::put().to(update_news))
-            .route(
-                "/list-news",
-                web::get().to(crate::handlers::news::list_news),
-            )
-            .route(
-                "/create-category",
-                web::post().to(crate::handlers::admin::create_category),
-            )
-            .route(
-                "/create-category",
-                web::post().to(crate::handlers::admin::update_category),
-            )
-            .route(
-                "/news-detail/{id}",
-                web::get().to(crate::handlers::news::get_news_detail),
-            )
-            .route(
-                "/delete-news/{id}",
-                web::get().to(crate::handlers::admin::delete_news),
-            )
-            .route(
-                "/delete-category/{id}",
-                web::get().to(crate::handlers::admin::delete_category),
-            ),
+fn rate_limit(scope: &str, settings: RateLimitSettings, key: RateLimitKey) -> RateLimitMiddleware {
+    RateLimitMiddleware::new(RateLimitPolicy::new(
+        scope,
+        settings.capacity,
+        settings.refill_per_second,
+        key,
+    ))
+}
+
+// anyone can read, per API key when one is sent
+fn public_read(route: Route, config: &Config) -> Route {
+    route
+        .wrap(rate_limit(
+            "user",
+            config.rate_limit.user,
+            RateLimitKey::ApiKey,
+        ))
+        .wrap(OptionalAuthMiddleWare)
+}
+
+// any signed-in user, the handler decides what they may change
+fn signed_in_write(route: Route, config: &Config) -> Route {
+    route
+        .wrap(rate_limit(
+            "admin",
+            config.rate_limit.admin,
+            RateLimitKey::User,
+        ))
+        .wrap(AuthMiddleWare)
+}
+
+// middleware registered last runs first: authenticate, limit per user, then check for an admin
+fn admin_write(route: Route, config: &Config) -> Route {
+    route
+        .wrap(AdminMiddleware)
+        .wrap(rate_limit(
+            "admin",
+            config.rate_limit.admin,
+            RateLimitKey::User,
+        ))
+        .wrap(AuthMiddleWare)
+}
+
+// what the signed-in user owns, like their sessions
+fn own_account(route: Route, config: &Config) -> Route {
+    route
+        .wrap(rate_limit("me", config.rate_limit.me, RateLimitKey::User))
+        .wrap(AuthMiddleWare)
+}
+
+// registration and sign-in, per client IP
+fn sign_in(route: Route, config: &Config) -> Route {
+    route.wrap(rate_limit("auth", config.rate_limit.auth, RateLimitKey::Ip))
+}
+
+/// Every route with its method and path, registered as one resource per path in the order
+/// the paths were first added. The OpenAPI document is checked against the same table.
+#[derive(Default)]
+pub struct RouteTable {
+    resources: Vec<(String, Vec<(Method, Route)>)>,
+}
+
+impl RouteTable {
+    fn add(&mut self, method: Method, path: &str, route: impl FnOnce(Route) -> Route) {
+        let route = (method.clone(), route(web::method(method)));
+        match self.resources.iter_mut().find(|(wired, _)| wired == path) {
+            Some((_, routes)) => routes.push(route),
+            None => self.resources.push((path.to_string(), vec![route])),
+        }
+    }
+
+    /// The (method, path) pairs of the table, paths written like in the OpenAPI document.
+    #[cfg(test)]
+    pub fn wired(&self) -> impl Iterator<Item = (&Method, &str)> {
+        self.resources.iter().flat_map(|(path, routes)| {
+            routes
+                .iter()
+                .map(move |(method, _)| (method, path.as_str()))
+        })
+    }
+
+    fn register(self, cfg: &mut web::ServiceConfig) {
+        for (path, routes) in self.resources {
+            let mut resource = web::resource(path);
+            for (_, route) in routes {
+                resource = resource.route(route);
+            }
+            cfg.service(resource);
+        }
+    }
+}
+
+pub fn route_table(config: &Config) -> RouteTable {
+    let mut routes = RouteTable::default();
+
+    // Probes for the orchestrator, outside the rate limits
+    routes.add(Method::GET, "/healthz", |r| r.to(health::healthz));
+    routes.add(Method::GET, "/readyz", |r| r.to(health::readyz));
+    routes.add(Method::GET, "/version", |r| r.to(health::version));
+    if config.metrics.enabled {
+        routes.add(Method::GET, "/metrics", |r| r.to(metrics::metrics));
+    }
+
+    // Versioned resources, authorization depends on the method
+    routes.add(Method::GET, "/api/v1/news", |r| {
+        public_read(r.to(news::list_news), config)
+    });
+    routes.add(Method::POST, "/api/v1/news", |r| {
+        admin_write(r.to(admin::create_news), config)
+    });
+    // before /news/{id}, which would take `stream` for an id
+    routes.add(Method::GET, "/api/v1/news/stream", |r| {
+        public_read(r.to(news::stream_news), config)
+    });
+    routes.add(Method::GET, "/api/v1/news/{id}", |r| {
+        public_read(r.to(news::get_news_detail), config)
+    });
+    // authors can edit their own articles, the handler checks
+    routes.add(Method::PATCH, "/api/v1/news/{id}", |r| {
+        signed_in_write(r.to(admin::update_news), config)
+    });
+    routes.add(Method::DELETE, "/api/v1/news/{id}", |r| {
+        signed_in_write(r.to(admin::delete_news), config)
+    });
+    // authors and admins, the handler checks like for saves
+    routes.add(Method::GET, "/api/v1/news/{id}/room", |r| {
+        signed_in_write(r.to(edit_rooms::join_room), config)
+    });
+    routes.add(Method::GET, "/api/v1/categories", |r| {
+        public_read(r.to(categories::list_categories), config)
+    });
+    routes.add(Method::POST, "/api/v1/categories", |r| {
+        admin_write(r.to(admin::create_category), config)
+    });
+    routes.add(Method::GET, "/api/v1/categories/{id}", |r| {
+        public_read(r.to(categories::get_category), config)
+    });
+    routes.add(Method::PATCH, "/api/v1/categories/{id}", |r| {
+        admin_write(r.to(admin::update_category), config)
+    });
+    routes.add(Method::DELETE, "/api/v1/categories/{id}", |r| {
+        admin_write(r.to(admin::delete_category), config)
+    });
+    routes.add(Method::GET, "/api/v1/webhooks", |r| {
+        admin_write(r.to(webhooks::list_webhooks), config)
+    });
+    routes.add(Method::POST, "/api/v1/webhooks", |r| {
+        admin_write(r.to(webhooks::create_webhook), config)
+    });
+    routes.add(Method::DELETE, "/api/v1/webhooks/{id}", |r| {
+        admin_write(r.to(webhooks::delete_webhook), config)
+    });
+    routes.add(Method::GET, "/api/v1/webhooks/{id}/deliveries", |r| {
+        admin_write(r.to(webhooks::list_deliveries), config)
+    });
+    routes.add(
+        Method::POST,
+        "/api/v1/webhooks/{id}/deliveries/{delivery_id}/retry",
+        |r| admin_write(r.to(webhooks::retry_delivery), config),
     );
+    routes.add(Method::GET, "/api/v1/trash/news", |r| {
+        admin_write(r.to(trash::list_trashed_news), config)
+    });
+    routes.add(Method::DELETE, "/api/v1/trash/news/{id}", |r| {
+        admin_write(r.to(trash::purge_news), config)
+    });
+    routes.add(Method::POST, "/api/v1/trash/news/{id}/restore", |r| {
+        admin_write(r.to(trash::restore_news), config)
+    });
+    routes.add(Method::GET, "/api/v1/trash/categories", |r| {
+        admin_write(r.to(trash::list_trashed_categories), config)
+    });
+    routes.add(Method::DELETE, "/api/v1/trash/categories/{id}", |r| {
+        admin_write(r.to(trash::purge_category), config)
+    });
+    routes.add(Method::POST, "/api/v1/trash/categories/{id}/restore", |r| {
+        admin_write(r.to(trash::restore_category), config)
+    });
+
+    // GraphQL over the same resources, reads are public and mutations check the token. Mutations
+    // also take from the per-user "admin" bucket of the REST writes, in the resolvers
+    routes.add(Method::GET, "/graphql", |r| {
+        public_read(r.to(graphql::execute_query), config)
+    });
+    routes.add(Method::POST, "/graphql", |r| {
+        public_read(r.to(graphql::execute), config)
+    });
+    if config.graphql.playground {
+        routes.add(Method::GET, "/graphiql", |r| r.to(graphql::playground));
+    }
+
+    // Admin routes, the news and category ones are superseded by /api/v1
+    routes.add(Method::POST, "/admin/create-news", |r| {
+        admin_write(r.to(admin::create_news).wrap(deprecated()), config)
+    });
+    routes.add(Method::PUT, "/admin/news-update/{id}", |r| {
+        admin_write(r.to(admin::update_news).wrap(deprecated()), config)
+    });
+    routes.add(Method::GET, "/admin/list-news", |r| {
+        admin_write(r.to(news::list_news).wrap(deprecated()), config)
+    });
+    routes.add(Method::POST, "/admin/create-category", |r| {
+        admin_write(r.to(admin::create_category).wrap(deprecated()), config)
+    });
+    routes.add(Method::GET, "/admin/news-detail/{id}", |r| {
+        admin_write(r.to(news::get_news_detail).wrap(deprecated()), config)
+    });
+    routes.add(Method::GET, "/admin/delete-news/{id}", |r| {
+        admin_write(r.to(admin::delete_news).wrap(deprecated()), config)
+    });
+    routes.add(Method::GET, "/admin/delete-category/{id}", |r| {
+        admin_write(r.to(admin::delete_category).wrap(deprecated()), config)
+    });
+    routes.add(Method::POST, "/admin/create-api-key", |r| {
+        admin_write(r.to(api_keys::create_api_key), config)
+    });
+    routes.add(Method::GET, "/admin/list-api-keys", |r| {
+        admin_write(r.to(api_keys::list_api_keys), config)
+    });
+    routes.add(Method::DELETE, "/admin/revoke-api-key/{id}", |r| {
+        admin_write(r.to(api_keys::revoke_api_key), config)
+    });
+    routes.add(Method::DELETE, "/admin/users/{id}/sessions", |r| {
+        admin_write(r.to(sessions::revoke_user_sessions), config)
+    });
+
+    // Routes for the signed-in user
+    routes.add(Method::GET, "/me/sessions", |r| {
+        own_account(r.to(sessions::list_sessions), config)
+    });
+    routes.add(Method::DELETE, "/me/sessions/{id}", |r| {
+        own_account(r.to(sessions::revoke_session), config)
+    });
 
     // Auth route
-    cfg.service(
-        web::scope("/auth")
-            .route("/register", web::post().to(crate::handlers::auth::register))
-            .route("/login", web::post().to(crate::handlers::auth::login)),
-    );
+    routes.add(Method::POST, "/auth/register", |r| {
+        sign_in(r.to(auth::register), config)
+    });
+    routes.add(Method::POST, "/auth/login", |r| {
+        sign_in(r.to(auth::login), config)
+    });
+    routes.add(Method::GET, "/auth/oidc/login", |r| {
+        sign_in(r.to(oidc::oidc_login), config)
+    });
+    routes.add(Method::GET, "/auth/oidc/callback", |r| {
+        sign_in(r.to(oidc::oidc_callback), config)
+    });
 
-    // User routes
-    cfg.service(
-        web::scope("/user")
-            .route(
-                "/list-news",
-                web::get().to(crate::handlers::news::list_news),
-            )
-            .route(
-                "/create-category",
-                web::post().to(crate::handlers::admin::create_category),
-            ),
-    );
+    // User routes, superseded by /api/v1
+    routes.add(Method::GET, "/user/list-news", |r| {
+        public_read(r.to(news::list_news).wrap(deprecated()), config)
+    });
+    // admins only, like its /api/v1 replacement
+    routes.add(Method::POST, "/user/create-category", |r| {
+        admin_write(r.to(admin::create_category).wrap(deprecated()), config)
+    });
+
+    routes
+}
+
+pub fn configure_routes(cfg: &mut web::ServiceConfig, config: &Config) {
+    crate::utils::error_response::configure(cfg, config.limits.json_payload_bytes);
+
+    // The OpenAPI document and its Swagger UI
+    crate::openapi::configure(cfg);
+    crate::graphql::configure(cfg);
+
+    route_table(config).register(cfg);
 }
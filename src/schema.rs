@@ -1,5 +1,20 @@
 // @generated automatically by Diesel CLI.
 
+diesel::table! {
+    api_keys (id) {
+        id -> Int4,
+        owner_id -> Int4,
+        name -> Varchar,
+        key_prefix -> Varchar,
+        key_hash -> Varchar,
+        scopes -> Array<Text>,
+        expires_at -> Nullable<Timestamp>,
+        last_used_at -> Nullable<Timestamp>,
+        revoked_at -> Nullable<Timestamp>,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     categories (id) {
         id -> Int4,
@@ -7,6 +22,7 @@ diesel::table! {
         description -> Nullable<Text>,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -18,6 +34,7 @@ diesel::table! {
         author_id -> Int4,
         created_at -> Timestamp,
         updated_at -> Timestamp,
+        deleted_at -> Nullable<Timestamp>,
     }
 }
 
@@ -28,6 +45,49 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    outbox_events (id) {
+        id -> Int8,
+        event_type -> Varchar,
+        payload -> Jsonb,
+        created_at -> Timestamp,
+        dispatched_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    rate_limit_buckets (bucket_key) {
+        bucket_key -> Varchar,
+        tokens -> Float8,
+        allowed -> Bool,
+        updated_at -> Timestamp,
+    }
+}
+
+diesel::table! {
+    sessions (id) {
+        id -> Varchar,
+        user_id -> Int4,
+        device_label -> Nullable<Varchar>,
+        user_agent -> Nullable<Varchar>,
+        ip_address -> Nullable<Varchar>,
+        created_at -> Timestamp,
+        last_seen_at -> Timestamp,
+        expires_at -> Timestamp,
+        revoked_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    user_identities (id) {
+        id -> Int4,
+        user_id -> Int4,
+        issuer -> Varchar,
+        subject -> Varchar,
+        created_at -> Timestamp,
+    }
+}
+
 diesel::table! {
     users (id) {
         id -> Int4,
@@ -39,13 +99,51 @@ diesel::table! {
     }
 }
 
+diesel::table! {
+    webhook_deliveries (id) {
+        id -> Int8,
+        webhook_id -> Int4,
+        event_id -> Int8,
+        event_type -> Varchar,
+        status -> Varchar,
+        attempts -> Int4,
+        next_attempt_at -> Timestamp,
+        last_status_code -> Nullable<Int4>,
+        last_error -> Nullable<Text>,
+        created_at -> Timestamp,
+        delivered_at -> Nullable<Timestamp>,
+    }
+}
+
+diesel::table! {
+    webhooks (id) {
+        id -> Int4,
+        url -> Varchar,
+        secret -> Varchar,
+        events -> Array<Text>,
+        created_at -> Timestamp,
+    }
+}
+
+diesel::joinable!(api_keys -> users (owner_id));
 diesel::joinable!(news -> users (author_id));
 diesel::joinable!(news_categories -> categories (category_id));
 diesel::joinable!(news_categories -> news (news_id));
+diesel::joinable!(sessions -> users (user_id));
+diesel::joinable!(user_identities -> users (user_id));
+diesel::joinable!(webhook_deliveries -> outbox_events (event_id));
+diesel::joinable!(webhook_deliveries -> webhooks (webhook_id));
 
 diesel::allow_tables_to_appear_in_same_query!(
+    api_keys,
     categories,
     news,
     news_categories,
+    outbox_events,
+    rate_limit_buckets,
+    sessions,
+    user_identities,
     users,
+    webhook_deliveries,
+    webhooks,
 );
@@ -0,0 +1,171 @@
+use std::collections::BTreeMap;
+use std::future::Future;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::rt;
+use futures::future::join_all;
+use serde::Serialize;
+use tokio::sync::watch;
+
+pub type TaskResult = Result<(), Box<dyn std::error::Error + Send + Sync>>;
+
+// restart delay doubles after each crash up to the maximum
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(tag = "state", rename_all = "snake_case")]
+pub enum TaskStatus {
+    Running,
+    // crashed, waiting for the backoff to restart
+    Restarting { failures: u32, last_error: String },
+    Stopped,
+}
+
+/// Handed to every task, resolves once the server is shutting down.
+#[derive(Clone)]
+pub struct Shutdown(watch::Receiver<bool>);
+
+impl Shutdown {
+    pub fn is_requested(&self) -> bool {
+        *self.0.borrow()
+    }
+
+    pub async fn requested(&mut self) {
+        // a dropped supervisor counts as a shutdown
+        let _ = self.0.wait_for(|stop| *stop).await;
+    }
+}
+
+type Statuses = Arc<Mutex<BTreeMap<&'static str, TaskStatus>>>;
+
+fn set_status(statuses: &Statuses, name: &'static str, status: TaskStatus) {
+    statuses.lock().unwrap().insert(name, status);
+}
+
+/// Runs background tasks, restarting them with backoff when they fail or panic.
+pub struct Supervisor {
+    shutdown: watch::Sender<bool>,
+    statuses: Statuses,
+    handles: Mutex<Vec<rt::task::JoinHandle<()>>>,
+}
+
+impl Default for Supervisor {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Supervisor {
+    pub fn new() -> Self {
+        Supervisor {
+            shutdown: watch::Sender::new(false),
+            statuses: Statuses::default(),
+            handles: Mutex::new(Vec::new()),
+        }
+    }
+
+    /// Spawns `task` on the current arbiter, it is called again for every restart.
+    pub fn spawn<F, Fut>(&self, name: &'static str, task: F)
+    where
+        F: Fn(Shutdown) -> Fut + 'static,
+        Fut: Future<Output = TaskResult> + 'static,
+    {
+        let mut shutdown = Shutdown(self.shutdown.subscribe());
+        let statuses = self.statuses.clone();
+        set_status(&statuses, name, TaskStatus::Running);
+
+        let handle = rt::spawn(async move {
+            let mut failures = 0;
+            loop {
+                let started = Instant::now();
+                // the extra spawn turns a panic into an error instead of killing the loop
+                let result = rt::spawn(task(shutdown.clone())).await;
+                if shutdown.is_requested() {
+                    break;
+                }
+
+                let error = match result {
+                    Ok(Ok(())) => "exited before shutdown".to_string(),
+                    Ok(Err(e)) => e.to_string(),
+                    Err(e) => e.to_string(),
+                };
+                // a task that ran fine for a while starts over with the shortest delay
+                if started.elapsed() > MAX_BACKOFF {
+                    failures = 0;
+                }
+                failures += 1;
+                let backoff = INITIAL_BACKOFF
+                    .saturating_mul(2u32.saturating_pow(failures - 1))
+                    .min(MAX_BACKOFF);
+
+                tracing::error!(
+                    task = name,
+                    failures,
+                    error = %error,
+                    "Background task failed, restarting in {:?}",
+                    backoff
+                );
+                set_status(
+                    &statuses,
+                    name,
+                    TaskStatus::Restarting {
+                        failures,
+                        last_error: error,
+                    },
+                );
+
+                tokio::select! {
+                    _ = rt::time::sleep(backoff) => {}
+                    _ = shutdown.requested() => break,
+                }
+                set_status(&statuses, name, TaskStatus::Running);
+            }
+            set_status(&statuses, name, TaskStatus::Stopped);
+        });
+        self.handles.lock().unwrap().push(handle);
+    }
+
+    pub fn statuses(&self) -> BTreeMap<&'static str, TaskStatus> {
+        self.statuses.lock().unwrap().clone()
+    }
+
+    /// Asks every task to stop and waits up to `timeout`, false when some didn't finish.
+    pub async fn shutdown(&self, timeout: Duration) -> bool {
+        self.shutdown.send_replace(true);
+        let handles = std::mem::take(&mut *self.handles.lock().unwrap());
+
+        if rt::time::timeout(timeout, join_all(handles)).await.is_ok() {
+            return true;
+        }
+
+        let pending: Vec<_> = self
+            .statuses()
+            .into_iter()
+            .filter(|(_, status)| *status != TaskStatus::Stopped)
+            .map(|(name, _)| name)
+            .collect();
+        tracing::warn!(
+            ?pending,
+            "Background tasks did not stop within {:?}",
+            timeout
+        );
+        false
+    }
+}
+
+/// Runs `job` every `period` until shutdown, starting one period from now.
+pub async fn periodic<F, Fut>(mut shutdown: Shutdown, period: Duration, mut job: F) -> TaskResult
+where
+    F: FnMut() -> Fut,
+    Fut: Future<Output = TaskResult>,
+{
+    let mut interval = rt::time::interval_at(rt::time::Instant::now() + period, period);
+    loop {
+        tokio::select! {
+            _ = interval.tick() => job().await?,
+            _ = shutdown.requested() => return Ok(()),
+        }
+    }
+}
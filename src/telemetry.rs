@@ -0,0 +1,122 @@
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::{fmt, EnvFilter, Layer};
+
+use crate::config::{LogFormat, LoggingConfig};
+
+/// Keeps the trace exporter alive, pending spans are flushed when dropped.
+pub struct TelemetryGuard {
+    #[cfg(feature = "otlp")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+impl Drop for TelemetryGuard {
+    fn drop(&mut self) {
+        #[cfg(feature = "otlp")]
+        if let Some(provider) = self.provider.take() {
+            if let Err(e) = provider.shutdown() {
+                eprintln!("Failed to flush traces: {}", e);
+            }
+        }
+    }
+}
+
+/// Installs the global subscriber, `log` records from dependencies are forwarded to it.
+pub fn init(config: &LoggingConfig) -> Result<TelemetryGuard, Box<dyn std::error::Error>> {
+    // RUST_LOG wins over the configured level
+    let filter =
+        EnvFilter::try_from_default_env().or_else(|_| EnvFilter::try_new(&config.level))?;
+
+    let output = match config.format {
+        LogFormat::Json => fmt::layer()
+            .json()
+            .with_current_span(true)
+            .with_span_list(false)
+            .boxed(),
+        LogFormat::Pretty => fmt::layer().boxed(),
+    };
+
+    #[cfg(feature = "otlp")]
+    let (otlp, provider) = match &config.otlp_endpoint {
+        Some(endpoint) => {
+            let (layer, provider) = otlp::layer(endpoint, &config.service_name)?;
+            (Some(layer), Some(provider))
+        }
+        None => (None, None),
+    };
+    #[cfg(not(feature = "otlp"))]
+    let otlp: Option<Box<dyn Layer<_> + Send + Sync>> = None;
+
+    tracing_subscriber::registry()
+        .with(filter)
+        .with(output)
+        .with(otlp)
+        .try_init()?;
+
+    Ok(TelemetryGuard {
+        #[cfg(feature = "otlp")]
+        provider,
+    })
+}
+
+#[cfg(feature = "otlp")]
+pub mod otlp {
+    use actix_web::http::header::HeaderMap;
+    use opentelemetry::propagation::{Extractor, TextMapPropagator};
+    use opentelemetry::trace::TracerProvider;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::propagation::TraceContextPropagator;
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+    use tracing_subscriber::registry::LookupSpan;
+    use tracing_subscriber::Layer;
+
+    pub type BoxedLayer<S> = Box<dyn Layer<S> + Send + Sync>;
+
+    // layer exporting spans in batches to the collector's OTLP/HTTP endpoint
+    pub fn layer<S>(
+        endpoint: &str,
+        service_name: &str,
+    ) -> Result<(BoxedLayer<S>, SdkTracerProvider), Box<dyn std::error::Error>>
+    where
+        S: tracing::Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+    {
+        let exporter = SpanExporter::builder()
+            .with_http()
+            .with_endpoint(endpoint)
+            .build()?;
+        let provider = SdkTracerProvider::builder()
+            .with_batch_exporter(exporter)
+            .with_resource(
+                Resource::builder()
+                    .with_service_name(service_name.to_string())
+                    .build(),
+            )
+            .build();
+        let tracer = provider.tracer(service_name.to_string());
+
+        Ok((
+            tracing_opentelemetry::layer().with_tracer(tracer).boxed(),
+            provider,
+        ))
+    }
+
+    struct HeaderExtractor<'a>(&'a HeaderMap);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|value| value.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|name| name.as_str()).collect()
+        }
+    }
+
+    // continue the caller's trace when it sent a W3C traceparent header
+    pub fn set_remote_parent(span: &tracing::Span, headers: &HeaderMap) {
+        let parent = TraceContextPropagator::new().extract(&HeaderExtractor(headers));
+        let _ = span.set_parent(parent);
+    }
+}
@@ -0,0 +1,134 @@
+#[cfg(test)]
+mod api_key_tests {
+    use crate::db::establish_connection;
+    use crate::models::user::NewUser;
+    use crate::schema::users::dsl::*;
+    use crate::test::test_utils::{configure_app, test_config};
+    use crate::utils::api_key::{api_key_from_headers, generate_api_key, hash_api_key};
+    use crate::utils::session::{issue_session_token, SessionInfo};
+    use actix_web::test::{call_service, init_service, read_body_json, try_call_service};
+    use actix_web::{http::StatusCode, test::TestRequest, App};
+    use diesel::prelude::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_generated_keys_are_hashed() {
+        let (key, prefix, hash) = generate_api_key();
+
+        assert!(key.starts_with("nk_"));
+        assert!(key.starts_with(&prefix));
+        assert_eq!(hash, hash_api_key(&key));
+        assert_ne!(hash, key);
+    }
+
+    #[test]
+    fn test_key_header_forms() {
+        let req = TestRequest::default()
+            .insert_header(("X-Api-Key", "nk_header"))
+            .to_http_request();
+        assert_eq!(api_key_from_headers(req.headers()), Some("nk_header"));
+
+        let req = TestRequest::default()
+            .insert_header(("Authorization", "ApiKey nk_authorization"))
+            .to_http_request();
+        assert_eq!(
+            api_key_from_headers(req.headers()),
+            Some("nk_authorization")
+        );
+
+        let req = TestRequest::default()
+            .insert_header(("Authorization", "Bearer token"))
+            .to_http_request();
+        assert_eq!(api_key_from_headers(req.headers()), None);
+    }
+
+    #[actix_web::test]
+    async fn test_api_key_lifecycle() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let conn = &mut pool.get().unwrap();
+
+        // seed an admin to own the key
+        diesel::delete(users.filter(username.eq("api_key_admin")))
+            .execute(conn)
+            .unwrap();
+        let admin_id = diesel::insert_into(users)
+            .values(&NewUser {
+                username: "api_key_admin".to_string(),
+                password: "unused".to_string(),
+                is_admin: true,
+            })
+            .returning(id)
+            .get_result::<i32>(conn)
+            .unwrap();
+        let token = issue_session_token(
+            conn,
+            &config.jwt,
+            admin_id,
+            "api_key_admin",
+            true,
+            SessionInfo::default(),
+        )
+        .unwrap();
+
+        let app =
+            init_service(App::new().configure(configure_app(pool.clone(), config.clone()))).await;
+
+        // create a read-only admin key
+        let create_req = TestRequest::post()
+            .uri("/admin/create-api-key")
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .set_json(json!({
+                "name": "syndication",
+                "scopes": ["read", "admin"],
+                "expires_in_days": 30
+            }))
+            .to_request();
+        let create_resp = call_service(&app, create_req).await;
+        assert_eq!(create_resp.status(), StatusCode::CREATED);
+
+        let create_body: serde_json::Value = read_body_json(create_resp).await;
+        let key = create_body["key"].as_str().unwrap().to_string();
+        let key_id = create_body["api_key"]["id"].as_i64().unwrap();
+        assert!(create_body["api_key"].get("key_hash").is_none());
+
+        // the key authenticates reads
+        let list_req = TestRequest::get()
+            .uri("/admin/list-api-keys")
+            .insert_header(("Authorization", format!("ApiKey {}", key)))
+            .to_request();
+        assert_eq!(call_service(&app, list_req).await.status(), StatusCode::OK);
+
+        // but writes need the write scope
+        let write_req = TestRequest::post()
+            .uri("/admin/create-api-key")
+            .insert_header(("X-Api-Key", key.as_str()))
+            .set_json(json!({ "name": "escalation", "scopes": ["write"] }))
+            .to_request();
+        let write_err = try_call_service(&app, write_req).await.unwrap_err();
+        assert_eq!(
+            write_err.as_response_error().status_code(),
+            StatusCode::FORBIDDEN
+        );
+
+        // revoked keys stop working
+        let revoke_req = TestRequest::delete()
+            .uri(&format!("/admin/revoke-api-key/{}", key_id))
+            .insert_header(("Authorization", format!("Bearer {}", token)))
+            .to_request();
+        assert_eq!(
+            call_service(&app, revoke_req).await.status(),
+            StatusCode::OK
+        );
+
+        let revoked_req = TestRequest::get()
+            .uri("/admin/list-api-keys")
+            .insert_header(("X-Api-Key", key.as_str()))
+            .to_request();
+        let revoked_err = try_call_service(&app, revoked_req).await.unwrap_err();
+        assert_eq!(
+            revoked_err.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
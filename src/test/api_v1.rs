@@ -0,0 +1,275 @@
+#[cfg(test)]
+mod api_v1_tests {
+    use crate::config::Config;
+    use crate::db::establish_connection;
+    use crate::models::user::NewUser;
+    use crate::schema::news;
+    use crate::schema::users::dsl::*;
+    use crate::test::test_utils::{configure_app, test_config, DBPool};
+    use crate::utils::session::{issue_session_token, SessionInfo};
+    use actix_web::test::{call_service, init_service, read_body_json, try_call_service};
+    use actix_web::{http::StatusCode, test::TestRequest, App};
+    use diesel::prelude::*;
+    use serde_json::{json, Value};
+    use uuid::Uuid;
+
+    // a fresh user with a session, returns (user id, bearer header value)
+    fn seed_user(pool: &DBPool, config: &Config, name: &str, admin: bool) -> (i32, String) {
+        let conn = &mut pool.get().unwrap();
+        // deleted articles of an earlier run are still in the trash
+        diesel::delete(
+            news::table.filter(news::author_id.eq_any(users.filter(username.eq(name)).select(id))),
+        )
+        .execute(conn)
+        .unwrap();
+        diesel::delete(users.filter(username.eq(name)))
+            .execute(conn)
+            .unwrap();
+        let user_id = diesel::insert_into(users)
+            .values(&NewUser {
+                username: name.to_string(),
+                password: "unused".to_string(),
+                is_admin: admin,
+            })
+            .returning(id)
+            .get_result::<i32>(conn)
+            .unwrap();
+        let token = issue_session_token(
+            conn,
+            &config.jwt,
+            user_id,
+            name,
+            admin,
+            SessionInfo::default(),
+        )
+        .unwrap();
+
+        (user_id, format!("Bearer {}", token))
+    }
+
+    #[actix_web::test]
+    async fn test_news_resource() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let (admin_id, admin) = seed_user(&pool, &config, "v1_news_admin", true);
+        let (_, reader) = seed_user(&pool, &config, "v1_news_reader", false);
+        let app =
+            init_service(App::new().configure(configure_app(pool.clone(), config.clone()))).await;
+
+        // reads are public
+        let resp = call_service(&app, TestRequest::get().uri("/api/v1/news").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // creating needs an admin
+        let article = json!({
+            "title": "Versioned",
+            "content": "Served from /api/v1",
+            "author_id": admin_id,
+            "category_ids": []
+        });
+        let anonymous = try_call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/v1/news")
+                .set_json(&article)
+                .to_request(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            anonymous.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+        let not_admin = try_call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/v1/news")
+                .insert_header(("Authorization", reader.as_str()))
+                .set_json(&article)
+                .to_request(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            not_admin.as_response_error().status_code(),
+            StatusCode::FORBIDDEN
+        );
+
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/v1/news")
+                .insert_header(("Authorization", admin.as_str()))
+                .set_json(&article)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let body: Value = read_body_json(resp).await;
+        let uri = format!("/api/v1/news/{}", body["news"]["id"]);
+
+        // only the author or an admin may edit
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("Authorization", reader.as_str()))
+                .set_json(json!({ "news_title": "Hijacked" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let etag = resp.headers().get("etag").unwrap().clone();
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("Authorization", admin.as_str()))
+                .insert_header(("If-Match", etag.clone()))
+                .set_json(json!({ "news_title": "Patched" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // a second edit based on the same version would lose the first
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("Authorization", admin.as_str()))
+                .insert_header(("If-Match", etag))
+                .set_json(json!({ "news_title": "Lost" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["title"], "Patched");
+        assert_eq!(body["content"], "Served from /api/v1");
+
+        let resp = call_service(
+            &app,
+            TestRequest::delete()
+                .uri(&uri)
+                .insert_header(("Authorization", admin.as_str()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
+
+    #[actix_web::test]
+    async fn test_category_resource() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let (_, admin) = seed_user(&pool, &config, "v1_category_admin", true);
+        let (_, reader) = seed_user(&pool, &config, "v1_category_reader", false);
+        let app =
+            init_service(App::new().configure(configure_app(pool.clone(), config.clone()))).await;
+
+        let name = format!("v1-{}", Uuid::new_v4().simple());
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/api/v1/categories")
+                .insert_header(("Authorization", admin.as_str()))
+                .set_json(json!({ "name": name, "description": "Created over v1" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let body: Value = read_body_json(resp).await;
+        let uri = format!("/api/v1/categories/{}", body["category"]["id"]);
+
+        // the update used to be unreachable, fields that aren't sent are kept
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("Authorization", admin.as_str()))
+                .set_json(json!({ "description": "Patched over v1" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["name"], name.as_str());
+        assert_eq!(body["description"], "Patched over v1");
+
+        let resp = call_service(
+            &app,
+            TestRequest::get().uri("/api/v1/categories").to_request(),
+        )
+        .await;
+        let categories: Vec<Value> = read_body_json(resp).await;
+        assert!(categories
+            .iter()
+            .any(|category| category["name"] == name.as_str()));
+
+        let not_admin = try_call_service(
+            &app,
+            TestRequest::delete()
+                .uri(&uri)
+                .insert_header(("Authorization", reader.as_str()))
+                .to_request(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            not_admin.as_response_error().status_code(),
+            StatusCode::FORBIDDEN
+        );
+
+        let resp = call_service(
+            &app,
+            TestRequest::delete()
+                .uri(&uri)
+                .insert_header(("Authorization", admin.as_str()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_legacy_routes_are_deprecated() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let app = init_service(App::new().configure(configure_app(pool, config))).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/user/list-news").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(resp.headers().contains_key("deprecation"));
+        assert!(resp.headers().contains_key("link"));
+
+        let resp = call_service(&app, TestRequest::get().uri("/api/v1/news").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(!resp.headers().contains_key("deprecation"));
+
+        // the public scope doesn't make its category writes public
+        let anonymous = try_call_service(
+            &app,
+            TestRequest::post()
+                .uri("/user/create-category")
+                .set_json(json!({ "name": "Anonymous" }))
+                .to_request(),
+        )
+        .await
+        .unwrap_err();
+        assert_eq!(
+            anonymous.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+    }
+}
@@ -1,16 +1,33 @@
 #[cfg(test)]
 mod auth_tests {
+    use crate::db::establish_connection;
+    use crate::models::user::NewUser;
     use crate::schema::users::dsl::*;
-    use crate::{db::establish_connection, routes::configure_routes};
-    use actix_web::{http::StatusCode, test, web, App};
+    use crate::test::test_utils::{configure_app, test_config};
+    use actix_web::{http::StatusCode, test, App};
     use diesel::prelude::*;
-    use dotenvy::dotenv;
     use serde_json::json;
 
+    // seed a non-admin user with a legacy bcrypt hash so login tests don't depend on test_register
+    fn seed_login_user(conn: &mut PgConnection, name: &str) {
+        diesel::delete(users.filter(username.eq(name)))
+            .execute(conn)
+            .unwrap();
+
+        diesel::insert_into(users)
+            .values(&NewUser {
+                username: name.to_string(),
+                password: bcrypt::hash("login_password", 4).unwrap(),
+                is_admin: false,
+            })
+            .execute(conn)
+            .unwrap();
+    }
+
     #[actix_web::test]
     async fn test_register() {
-        dotenv().ok();
-        let pool = establish_connection();
+        let config = test_config();
+        let pool = establish_connection(&config.database);
         let conn = &mut pool.get().unwrap();
 
         // Clean up the test user if it exists
@@ -18,12 +35,9 @@ mod auth_tests {
             .execute(conn)
             .unwrap();
 
-        let app = test::init_service(
-            App::new()
-                .app_data(web::Data::new(pool.clone()))
-                .configure(configure_routes),
-        )
-        .await;
+        let app =
+            test::init_service(App::new().configure(configure_app(pool.clone(), config.clone())))
+                .await;
 
         // Register a new user
         let register_req = test::TestRequest::post()
@@ -39,26 +53,70 @@ mod auth_tests {
 
         let register_body: serde_json::Value = test::read_body_json(register_resp).await;
         assert_eq!(register_body, "User created successfully");
+
+        // self-registered users never get admin rights
+        let registered_admin = users
+            .filter(username.eq("admin_user"))
+            .select(is_admin)
+            .first::<bool>(conn)
+            .unwrap();
+        assert!(!registered_admin);
+    }
+
+    #[actix_web::test]
+    async fn test_concurrent_signups_with_one_name() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let conn = &mut pool.get().unwrap();
+        diesel::delete(users.filter(username.eq("racing_user")))
+            .execute(conn)
+            .unwrap();
+
+        let app =
+            test::init_service(App::new().configure(configure_app(pool.clone(), config.clone())))
+                .await;
+        let register = || {
+            test::TestRequest::post()
+                .uri("/auth/register")
+                .set_json(json!({
+                    "username": "racing_user",
+                    "password": "racing_password",
+                }))
+                .to_request()
+        };
+
+        // both pass the lookup before either is saved, the database decides
+        let (first, second) = futures::join!(
+            test::call_service(&app, register()),
+            test::call_service(&app, register())
+        );
+        let mut statuses = [first.status(), second.status()];
+        statuses.sort();
+        assert_eq!(statuses, [StatusCode::CREATED, StatusCode::CONFLICT]);
+        let registered = users
+            .filter(username.eq("racing_user"))
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap();
+        assert_eq!(registered, 1);
     }
 
     #[actix_web::test]
     async fn test_login_success() {
-        dotenv().ok();
-        let pool = establish_connection();
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        seed_login_user(&mut pool.get().unwrap(), "login_user");
 
-        let app = test::init_service(
-            App::new()
-                .app_data(web::Data::new(pool.clone()))
-                .configure(configure_routes),
-        )
-        .await;
+        let app =
+            test::init_service(App::new().configure(configure_app(pool.clone(), config.clone())))
+                .await;
 
         // Log in with valid credentials
         let login_req = test::TestRequest::post()
             .uri("/auth/login")
             .set_json(json!({
-                "username": "admin_user",
-                "password": "admin_password"
+                "username": "login_user",
+                "password": "login_password"
             }))
             .to_request();
 
@@ -68,25 +126,52 @@ mod auth_tests {
         let login_body: serde_json::Value = test::read_body_json(login_resp).await;
         assert!(login_body.get("token").is_some());
         assert_eq!(login_body["is_admin"], false);
+
+        // the legacy bcrypt hash was upgraded to argon2id
+        let stored_hash = users
+            .filter(username.eq("login_user"))
+            .select(password)
+            .first::<String>(&mut pool.get().unwrap())
+            .unwrap();
+        assert!(stored_hash.starts_with("$argon2id$"));
+    }
+
+    #[actix_web::test]
+    async fn test_register_rejects_breached_password() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+
+        let app =
+            test::init_service(App::new().configure(configure_app(pool.clone(), config.clone())))
+                .await;
+
+        let register_req = test::TestRequest::post()
+            .uri("/auth/register")
+            .set_json(json!({
+                "username": "breached_user",
+                "password": "password123",
+            }))
+            .to_request();
+
+        let register_resp = test::call_service(&app, register_req).await;
+        assert_eq!(register_resp.status(), StatusCode::BAD_REQUEST);
     }
 
     #[actix_web::test]
     async fn test_login_failure() {
-        dotenv().ok();
-        let pool = establish_connection();
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        seed_login_user(&mut pool.get().unwrap(), "login_failure_user");
 
-        let app = test::init_service(
-            App::new()
-                .app_data(web::Data::new(pool.clone()))
-                .configure(configure_routes),
-        )
-        .await;
+        let app =
+            test::init_service(App::new().configure(configure_app(pool.clone(), config.clone())))
+                .await;
 
         // Log in with invalid credentials
         let login_req = test::TestRequest::post()
             .uri("/auth/login")
             .set_json(json!({
-                "username": "admin_user",
+                "username": "login_failure_user",
                 "password": "wrong_password"
             }))
             .to_request();
@@ -94,11 +179,14 @@ mod auth_tests {
         let login_resp = test::call_service(&app, login_req).await;
         assert_eq!(login_resp.status(), StatusCode::UNAUTHORIZED);
 
-        // Read the response body as a string
-        let body = test::read_body(login_resp).await;
-        let body_str = String::from_utf8(body.to_vec()).unwrap();
+        assert_eq!(
+            login_resp.headers().get("content-type").unwrap(),
+            "application/problem+json"
+        );
 
-        // Assert the plain text error message
-        assert_eq!(body_str, "Invalid credentials");
+        // Assert the problem details
+        let body: serde_json::Value = test::read_body_json(login_resp).await;
+        assert_eq!(body["code"], "unauthorized");
+        assert_eq!(body["detail"], "Invalid credentials");
     }
 }
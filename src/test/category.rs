@@ -1,121 +1,237 @@
 #[cfg(test)]
 mod tests {
-    use super::*;
-    use chrono::Utc;
-    use std::collections::HashMap;
-    use std::sync::{Arc, Mutex};
-
-    #[derive(Debug, Clone)] // ✅ Fix: Add Clone
-    struct Category {
-        id: i32,
-        name: String,
-        description: String,
-        created_at: chrono::NaiveDateTime,
-        updated_at: chrono::NaiveDateTime,
+    use std::sync::Arc;
+
+    use crate::handlers::admin::{create_category, delete_category, update_category};
+    use crate::models::category::NewCategory;
+    use crate::models::news::NewNews;
+    use crate::models::user::NewUser;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::{CategoryRepository, NewsRepository, UserRepository};
+    use crate::test::test_utils::{configure_memory_app, signed_in};
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App};
+    use serde_json::{json, Value};
+
+    fn seed_category(repository: &MemoryRepository) -> i32 {
+        CategoryRepository::create(
+            repository,
+            NewCategory {
+                name: "Tech".to_string(),
+                description: Some("Technology related articles".to_string()),
+            },
+        )
+        .unwrap()
+        .id
     }
 
-    struct MockDb {
-        category_store: Arc<Mutex<HashMap<i32, Category>>>,
+    #[actix_web::test]
+    async fn test_create_category_success() {
+        let repository = Arc::new(MemoryRepository::new());
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .route("/categories", web::post().to(create_category)),
+        )
+        .await;
+
+        let category = json!({
+            "id": 0,
+            "name": "Tech",
+            "description": "Technology related articles",
+            "created_at": "2025-01-01T00:00:00",
+            "updated_at": "2025-01-01T00:00:00"
+        });
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/categories")
+                .set_json(&category)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        let body: Value = read_body_json(resp).await;
+        let id = body["category"]["id"].as_i64().unwrap() as i32;
+        let stored = CategoryRepository::find(repository.as_ref(), id)
+            .unwrap()
+            .expect("Category should exist");
+        assert_eq!(stored.name, "Tech");
+        assert_eq!(
+            stored.description.as_deref(),
+            Some("Technology related articles")
+        );
+
+        // names are unique
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/categories")
+                .set_json(&category)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
     }
 
-    impl MockDb {
-        fn new() -> Self {
-            MockDb {
-                category_store: Arc::new(Mutex::new(HashMap::new())),
-            }
-        }
-
-        fn insert_category(&self, id: i32, name: &str, description: &str) {
-            let mut store = self.category_store.lock().unwrap();
-            store.insert(
-                id,
-                Category {
-                    id,
-                    name: name.to_string(),
-                    description: description.to_string(),
-                    created_at: Utc::now().naive_utc(),
-                    updated_at: Utc::now().naive_utc(),
-                },
-            );
-        }
-
-        fn update_category(
-            &self,
-            id: i32,
-            new_name: &str,
-            new_description: &str,
-        ) -> Result<Category, &'static str> {
-            let mut store = self.category_store.lock().unwrap();
-            if let Some(category) = store.get_mut(&id) {
-                category.name = new_name.to_string();
-                category.description = new_description.to_string();
-                category.updated_at = Utc::now().naive_utc();
-                return Ok(category.clone()); // ✅ Now it works!
-            }
-            Err("Category not found")
-        }
-
-        fn delete_category(&self, id: i32) -> Result<(), &'static str> {
-            let mut store = self.category_store.lock().unwrap();
-            if store.remove(&id).is_some() {
-                return Ok(());
-            }
-            Err("Category not found")
-        }
+    #[actix_web::test]
+    async fn test_update_category_success() {
+        let repository = Arc::new(MemoryRepository::new());
+        let id = seed_category(&repository);
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(1, true))
+                .configure(configure_memory_app(repository))
+                .route("/categories/{id}", web::put().to(update_category)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri(&format!("/categories/{}", id))
+                .set_json(json!({
+                    "name": "Science",
+                    "description": "Science related articles"
+                }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["category"]["name"], "Science");
+        assert_eq!(body["category"]["description"], "Science related articles");
+
+        // a missing description is kept, null clears it
+        let update = |body: Value| {
+            TestRequest::put()
+                .uri(&format!("/categories/{}", id))
+                .set_json(body)
+                .to_request()
+        };
+        let resp = call_service(&app, update(json!({ "name": "Sciences" }))).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["category"]["description"], "Science related articles");
+        let resp = call_service(&app, update(json!({ "description": null }))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["category"]["name"], "Sciences");
+        assert_eq!(body["category"]["description"], Value::Null);
+        let resp = call_service(&app, update(json!({ "description": "ab" }))).await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
     }
 
-    #[test]
-    fn test_create_category_success() {
-        let db = MockDb::new();
-        db.insert_category(1, "Tech", "Technology related articles");
-
-        let store = db.category_store.lock().unwrap();
-        let category = store.get(&1).expect("Category should exist");
-
-        assert_eq!(category.name, "Tech");
-        assert_eq!(category.description, "Technology related articles");
-    }
-
-    #[test]
-    fn test_update_category_success() {
-        let db = MockDb::new();
-        db.insert_category(1, "Tech", "Technology related articles");
-
-        let updated_category = db.update_category(1, "Science", "Science related articles");
-        assert!(updated_category.is_ok());
-
-        let category = updated_category.unwrap();
-        assert_eq!(category.name, "Science");
-        assert_eq!(category.description, "Science related articles");
+    #[actix_web::test]
+    async fn test_update_category_not_found() {
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(1, true))
+                .configure(configure_memory_app(Arc::new(MemoryRepository::new())))
+                .route("/categories/{id}", web::put().to(update_category)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri("/categories/99")
+                .set_json(json!({ "name": "New Name", "description": "New Description" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
-    #[test]
-    fn test_update_category_not_found() {
-        let db = MockDb::new();
-        let result = db.update_category(99, "New Name", "New Description");
-
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Category not found");
+    #[actix_web::test]
+    async fn test_delete_category_success() {
+        let repository = Arc::new(MemoryRepository::new());
+        let id = seed_category(&repository);
+        let author = UserRepository::create(
+            repository.as_ref(),
+            NewUser {
+                username: "author".to_string(),
+                password: "hash".to_string(),
+                is_admin: false,
+            },
+        )
+        .unwrap();
+        let news = NewsRepository::create(
+            repository.as_ref(),
+            NewNews {
+                title: "Tagged".to_string(),
+                content: "Content".to_string(),
+                author_id: author.id,
+            },
+            &[id],
+        )
+        .unwrap();
+
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(1, true))
+                .configure(configure_memory_app(repository.clone()))
+                .route("/categories/{id}", web::delete().to(delete_category)),
+        )
+        .await;
+
+        let uri = format!("/categories/{}", id);
+        let resp = call_service(&app, TestRequest::delete().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        // the article stays, only the link is gone
+        assert!(NewsRepository::categories(repository.as_ref(), news.id)
+            .unwrap()
+            .is_empty());
+
+        // Category should be gone now
+        let resp = call_service(&app, TestRequest::delete().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
-    #[test]
-    fn test_delete_category_success() {
-        let db = MockDb::new();
-        db.insert_category(1, "Tech", "Technology related articles");
-
-        let result = db.delete_category(1);
-        assert!(result.is_ok());
-
-        let check = db.delete_category(1);
-        assert!(check.is_err()); // Category should be gone now
+    #[actix_web::test]
+    async fn test_delete_category_not_found() {
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(1, true))
+                .configure(configure_memory_app(Arc::new(MemoryRepository::new())))
+                .route("/categories/{id}", web::delete().to(delete_category)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::delete().uri("/categories/99").to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
-    #[test]
-    fn test_delete_category_not_found() {
-        let db = MockDb::new();
-        let result = db.delete_category(99);
-
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "Category not found");
+    #[actix_web::test]
+    async fn test_category_changes_need_admin() {
+        let repository = Arc::new(MemoryRepository::new());
+        let id = seed_category(&repository);
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(1, false))
+                .configure(configure_memory_app(repository))
+                .route("/categories/{id}", web::put().to(update_category))
+                .route("/categories/{id}", web::delete().to(delete_category)),
+        )
+        .await;
+
+        let uri = format!("/categories/{}", id);
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri(&uri)
+                .set_json(json!({ "name": "Science", "description": "Science related" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
+
+        let resp = call_service(&app, TestRequest::delete().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
     }
 }
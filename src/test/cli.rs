@@ -0,0 +1,171 @@
+#[cfg(test)]
+mod cli_tests {
+    use crate::cli::content::{export_content, import_content, seed};
+    use crate::cli::maintenance::cleanup;
+    use crate::cli::users::{create_admin, reset_password, set_admin};
+    use crate::cli::{Cli, Command};
+    use crate::db::run_migrations;
+    use crate::schema::{categories, news, outbox_events, sessions, users};
+    use crate::test::test_utils::{cleanup_test_database, get_test_pool};
+    use crate::utils::password::{verify_password, PasswordPolicy};
+    use chrono::{Duration, Utc};
+    use clap::Parser;
+    use diesel::prelude::*;
+
+    fn policy() -> PasswordPolicy {
+        PasswordPolicy::new(8, 128, "password123")
+    }
+
+    // start a session for the user that is still active
+    fn add_session(conn: &mut PgConnection, user_id: i32, id: &str, expires_in: Duration) {
+        diesel::insert_into(sessions::table)
+            .values((
+                sessions::id.eq(id),
+                sessions::user_id.eq(user_id),
+                sessions::expires_at.eq(Utc::now().naive_utc() + expires_in),
+            ))
+            .execute(conn)
+            .unwrap();
+    }
+
+    fn active_sessions(conn: &mut PgConnection, user_id: i32) -> i64 {
+        sessions::table
+            .filter(sessions::user_id.eq(user_id))
+            .filter(sessions::revoked_at.is_null())
+            .count()
+            .get_result(conn)
+            .unwrap()
+    }
+
+    #[test]
+    fn test_admin_commands() {
+        let (pool, database_url) = get_test_pool();
+        let conn = &mut pool.get().unwrap();
+        run_migrations(conn).unwrap();
+
+        // the first admin, without going through the API
+        assert!(create_admin(conn, &policy(), "root", "password123").is_err());
+        let admin_id = create_admin(conn, &policy(), "root", "correct horse battery").unwrap();
+        assert!(create_admin(conn, &policy(), "root", "correct horse battery").is_err());
+
+        let is_admin = |conn: &mut PgConnection| {
+            users::table
+                .find(admin_id)
+                .select(users::is_admin)
+                .first::<bool>(conn)
+                .unwrap()
+        };
+        assert!(is_admin(conn));
+
+        // demoting ends the sessions whose tokens still claim admin
+        add_session(conn, admin_id, "cli-session-1", Duration::hours(1));
+        assert_eq!(set_admin(conn, "root", false).unwrap(), 1);
+        assert!(!is_admin(conn));
+        set_admin(conn, "root", true).unwrap();
+        assert!(is_admin(conn));
+        assert!(set_admin(conn, "nobody", true).is_err());
+
+        add_session(conn, admin_id, "cli-session-2", Duration::hours(1));
+        assert_eq!(
+            reset_password(conn, &policy(), "root", "a brand new passphrase").unwrap(),
+            1
+        );
+        assert_eq!(active_sessions(conn, admin_id), 0);
+        let stored = users::table
+            .find(admin_id)
+            .select(users::password)
+            .first::<String>(conn)
+            .unwrap();
+        assert!(
+            verify_password("a brand new passphrase", &stored)
+                .unwrap()
+                .valid
+        );
+
+        drop(pool);
+        cleanup_test_database(&database_url);
+    }
+
+    #[test]
+    fn test_seed_export_import_and_cleanup() {
+        let (pool, database_url) = get_test_pool();
+        let conn = &mut pool.get().unwrap();
+        run_migrations(conn).unwrap();
+        let admin_id = create_admin(conn, &policy(), "editor", "correct horse battery").unwrap();
+
+        let seeded = seed(conn, "editor").unwrap();
+        assert_eq!(seeded.categories, 3);
+        assert_eq!(seeded.news, 4);
+
+        // exporting and importing again duplicates articles but reuses categories
+        let export = export_content(conn).unwrap();
+        assert_eq!(export.categories.len(), 3);
+        assert_eq!(export.news.len(), 4);
+        assert!(export
+            .news
+            .iter()
+            .any(|article| article.categories == ["Local", "Technology"]));
+
+        let imported = import_content(conn, &export, "editor").unwrap();
+        assert_eq!(imported.categories, 0);
+        assert_eq!(imported.news, 4);
+        let total: i64 = news::table.count().get_result(conn).unwrap();
+        assert_eq!(total, 8);
+        assert!(import_content(conn, &export, "nobody").is_err());
+
+        // a trashed category is restored rather than created again
+        diesel::update(categories::table.filter(categories::name.eq("Local")))
+            .set(categories::deleted_at.eq(Utc::now().naive_utc()))
+            .execute(conn)
+            .unwrap();
+        let imported = import_content(conn, &export, "editor").unwrap();
+        assert_eq!(imported.categories, 1);
+        let live: i64 = categories::table
+            .filter(categories::deleted_at.is_null())
+            .count()
+            .get_result(conn)
+            .unwrap();
+        assert_eq!(live, 3);
+        let total: i64 = categories::table.count().get_result(conn).unwrap();
+        assert_eq!(total, 3);
+
+        // only sessions that ended before the retention window are deleted
+        add_session(conn, admin_id, "cli-expired", -Duration::days(40));
+        add_session(conn, admin_id, "cli-recent", -Duration::days(1));
+        add_session(conn, admin_id, "cli-active", Duration::hours(1));
+        // and webhook events sent out before it
+        for days in [40, 1] {
+            diesel::insert_into(outbox_events::table)
+                .values((
+                    outbox_events::event_type.eq("news.created"),
+                    outbox_events::payload.eq(serde_json::json!({})),
+                    outbox_events::dispatched_at.eq(Utc::now().naive_utc() - Duration::days(days)),
+                ))
+                .execute(conn)
+                .unwrap();
+        }
+        let summary = cleanup(conn, 30).unwrap();
+        assert_eq!(summary.sessions, 1);
+        assert_eq!(summary.outbox_events, 1);
+        assert_eq!(active_sessions(conn, admin_id), 2);
+
+        drop(pool);
+        cleanup_test_database(&database_url);
+    }
+
+    #[test]
+    fn test_cleanup_retention_defaults_to_the_config() {
+        let cli = Cli::try_parse_from(["news_rest_api", "cleanup"]).unwrap();
+        assert!(matches!(
+            cli.command,
+            Some(Command::Cleanup {
+                retention_days: None
+            })
+        ));
+
+        // a negative retention would end active sessions
+        assert!(
+            Cli::try_parse_from(["news_rest_api", "cleanup", "--retention-days", "-1"]).is_err()
+        );
+    }
+}
@@ -0,0 +1,176 @@
+#[cfg(test)]
+mod concurrency_tests {
+    use std::sync::Arc;
+
+    use crate::handlers::admin::{delete_category, delete_news, update_category, update_news};
+    use crate::handlers::categories::get_category;
+    use crate::handlers::news::get_news_detail;
+    use crate::models::news::News;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::NewsRepository;
+    use crate::test::test_utils::{configure_memory_app, seed_article, signed_in};
+    use crate::utils::error_response::AppError;
+    use crate::utils::precondition;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App, ResponseError};
+    use serde_json::{json, Value};
+
+    #[actix_web::test]
+    async fn test_stale_news_edits_are_rejected() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (author_id, news_id, _) = seed_article(repository.as_ref(), "editor", false);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository))
+                .wrap_fn(signed_in(author_id, false))
+                .route("/news/{id}", web::get().to(get_news_detail))
+                .route("/news/{id}", web::patch().to(update_news))
+                .route("/news/{id}", web::delete().to(delete_news)),
+        )
+        .await;
+        let uri = format!("/news/{}", news_id);
+
+        // two editors open the same version
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let read = resp.headers().get("etag").unwrap().clone();
+
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("If-Match", read.clone()))
+                .set_json(json!({ "news_title": "First editor" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let saved = resp.headers().get("etag").unwrap().clone();
+        assert_ne!(saved, read);
+
+        // the second one gets the current version instead of overwriting it
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("If-Match", read.clone()))
+                .set_json(json!({ "news_title": "Second editor" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        assert_eq!(resp.headers().get("etag").unwrap(), &saved);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "precondition_failed");
+        assert_eq!(body["current"]["title"], "First editor");
+
+        let resp = call_service(
+            &app,
+            TestRequest::delete()
+                .uri(&uri)
+                .insert_header(("If-Match", read))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        // the GET ETag matches the one the update returned
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.headers().get("etag").unwrap(), &saved);
+
+        let resp = call_service(
+            &app,
+            TestRequest::delete()
+                .uri(&uri)
+                .insert_header(("If-Match", saved))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_stale_category_edits_are_rejected() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (author_id, _, category_id) = seed_article(repository.as_ref(), "editor", false);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository))
+                .wrap_fn(signed_in(author_id, true))
+                .route("/categories/{id}", web::get().to(get_category))
+                .route("/categories/{id}", web::patch().to(update_category))
+                .route("/categories/{id}", web::delete().to(delete_category)),
+        )
+        .await;
+        let uri = format!("/categories/{}", category_id);
+
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let read = resp.headers().get("etag").unwrap().clone();
+
+        // without If-Match the last write still wins
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .set_json(json!({ "description": "Gadgets and software" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("If-Match", read))
+                .set_json(json!({ "name": "Technology" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["current"]["name"], "Tech");
+        assert_eq!(body["current"]["description"], "Gadgets and software");
+
+        // tags that can't be read never match
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .insert_header(("If-Match", "not a tag"))
+                .set_json(json!({ "name": "Technology" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PRECONDITION_FAILED);
+
+        // any version will do
+        let resp = call_service(
+            &app,
+            TestRequest::delete()
+                .uri(&uri)
+                .insert_header(("If-Match", "*"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+
+    #[test]
+    fn test_versions_are_checked() {
+        let repository = MemoryRepository::new();
+        let (_, news_id, _) = seed_article(&repository, "editor", false);
+        let news = NewsRepository::find(&repository, news_id).unwrap().unwrap();
+
+        let missing = precondition::check(None, true, &news).unwrap_err();
+        assert_eq!(missing.status_code(), StatusCode::PRECONDITION_REQUIRED);
+        assert_eq!(precondition::check(None, false, &news).unwrap(), None);
+
+        // the repository rejects writes based on an older version too
+        let older = news.updated_at - chrono::Duration::seconds(1);
+        assert!(!NewsRepository::delete(&repository, news_id, Some(older)).unwrap());
+        assert!(NewsRepository::delete(&repository, news_id, Some(news.updated_at)).unwrap());
+
+        let gone = precondition::missed(None::<News>, "News not found");
+        assert!(matches!(gone, AppError::NotFoundError(_)));
+    }
+}
@@ -0,0 +1,139 @@
+#[cfg(test)]
+mod config_tests {
+    use crate::config::{Config, ConfigError, RateLimitBackend};
+    use config::{File, FileFormat};
+    use std::collections::HashMap;
+
+    const SECRET: &str = "0123456789abcdef0123456789abcdef";
+
+    fn load(toml: &'static str, vars: &[(&str, &str)]) -> Result<Config, ConfigError> {
+        let vars: HashMap<String, String> = vars
+            .iter()
+            .map(|(key, value)| (key.to_string(), value.to_string()))
+            .collect();
+        Config::from_sources(File::from_str(toml, FileFormat::Toml), vars)
+    }
+
+    #[test]
+    fn test_defaults_with_legacy_variables() {
+        let config = load(
+            "",
+            &[
+                ("DATABASE_URL", "postgres://localhost/news_api"),
+                ("JWT_TOKEN", SECRET),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(config.server.host, "127.0.0.1");
+        assert_eq!(config.server.port, 8080);
+        assert_eq!(config.database.url, "postgres://localhost/news_api");
+        assert_eq!(config.database.max_connections, 10);
+        assert_eq!(config.jwt.secret, SECRET);
+        assert_eq!(config.jwt.token_lifetime_hours, 24);
+        assert_eq!(config.rate_limit.backend, RateLimitBackend::Memory);
+        assert!(config.oidc.is_none());
+    }
+
+    #[test]
+    fn test_environment_overrides_file() {
+        let config = load(
+            r#"
+            [server]
+            host = "127.0.0.1"
+            port = 9000
+
+            [database]
+            url = "postgres://file/news_api"
+            max_connections = 5
+
+            [jwt]
+            secret = "0123456789abcdef0123456789abcdef"
+
+            [rate_limit]
+            backend = "postgres"
+            "#,
+            &[
+                ("NEWS_API__SERVER__HOST", "0.0.0.0"),
+                ("NEWS_API__DATABASE__MAX_CONNECTIONS", "20"),
+                (
+                    "NEWS_API__CORS__ALLOWED_ORIGINS",
+                    "https://news.example.com,https://admin.example.com",
+                ),
+            ],
+        )
+        .unwrap();
+
+        assert_eq!(config.server.host, "0.0.0.0");
+        assert_eq!(config.server.port, 9000);
+        assert_eq!(config.database.url, "postgres://file/news_api");
+        assert_eq!(config.database.max_connections, 20);
+        assert_eq!(config.rate_limit.backend, RateLimitBackend::Postgres);
+        assert_eq!(
+            config.cors.allowed_origins,
+            vec!["https://news.example.com", "https://admin.example.com"]
+        );
+    }
+
+    #[test]
+    fn test_example_config_is_valid() {
+        let config = load(include_str!("../../config.example.toml"), &[]).unwrap();
+
+        assert_eq!(config.rate_limit.admin.capacity, 120);
+        assert_eq!(config.password.min_length, 8);
+    }
+
+    #[test]
+    fn test_invalid_config_lists_every_problem() {
+        let result = load(
+            r#"
+            [server]
+            workers = 0
+
+            [database]
+            url = "postgres://localhost/news_api"
+            min_idle = 50
+
+            [jwt]
+            secret = "short"
+            "#,
+            &[],
+        );
+
+        let Err(ConfigError::Invalid(problems)) = result else {
+            panic!("expected a validation error");
+        };
+        assert_eq!(problems.len(), 3);
+        assert!(problems.iter().any(|p| p.starts_with("server.workers")));
+        assert!(problems.iter().any(|p| p.starts_with("database.min_idle")));
+        assert!(problems.iter().any(|p| p.starts_with("jwt.secret")));
+    }
+
+    #[test]
+    fn test_webhook_retries_need_a_backoff() {
+        let vars = [
+            ("DATABASE_URL", "postgres://localhost/news_api"),
+            ("JWT_SECRET", SECRET),
+        ];
+        for webhooks in [
+            "[webhooks]\nbackoff_secs = 0",
+            "[webhooks]\nbackoff_secs = 60\nmax_backoff_secs = 30",
+            "[webhooks]\nmax_attempts = 0",
+        ] {
+            let Err(ConfigError::Invalid(problems)) = load(webhooks, &vars) else {
+                panic!("expected a validation error for {:?}", webhooks);
+            };
+            assert_eq!(problems.len(), 1);
+            assert!(problems[0].starts_with("webhooks."));
+        }
+    }
+
+    #[test]
+    fn test_missing_required_setting_fails() {
+        let error = load("", &[("DATABASE_URL", "postgres://localhost/news_api")])
+            .err()
+            .unwrap();
+
+        assert!(error.to_string().contains("jwt"));
+    }
+}
@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod db_tests {
+    use std::time::{Duration, Instant};
+
+    use crate::db::{self, establish_connection, PoolError};
+    use crate::test::test_utils::test_config;
+    use crate::utils::error_response::AppError;
+    use actix_web::rt::time::sleep;
+    use diesel::r2d2::ConnectionManager;
+    use diesel::sql_types::Double;
+    use diesel::{PgConnection, RunQueryDsl};
+
+    #[actix_web::test]
+    async fn test_slow_query_does_not_block_the_worker() {
+        let pool = establish_connection(&test_config().database);
+        let started = Instant::now();
+
+        let query = db::run(&pool, |conn| {
+            diesel::sql_query("SELECT pg_sleep($1)")
+                .bind::<Double, _>(0.5)
+                .execute(conn)
+                .map_err(AppError::from)
+        });
+        // the timer fires while the query is still sleeping on the blocking pool
+        let timer = async {
+            sleep(Duration::from_millis(50)).await;
+            started.elapsed()
+        };
+
+        let (result, timer_elapsed) = futures::join!(query, timer);
+        assert!(result.is_ok());
+        assert!(timer_elapsed < Duration::from_millis(400));
+        assert!(started.elapsed() >= Duration::from_millis(500));
+    }
+
+    #[actix_web::test]
+    async fn test_unreachable_database_is_a_pool_error() {
+        let pool = r2d2::Pool::builder()
+            .connection_timeout(Duration::from_millis(200))
+            .build_unchecked(ConnectionManager::<PgConnection>::new(
+                "postgres://nobody@127.0.0.1:1/none",
+            ));
+
+        let result = db::run(&pool, |_| Ok::<_, PoolError>(())).await;
+        assert!(matches!(result, Err(PoolError::Connection(_))));
+
+        let result = db::run(&pool, |_| Ok::<_, AppError>(())).await;
+        assert!(matches!(result, Err(AppError::DatabaseError(_))));
+    }
+}
@@ -0,0 +1,203 @@
+#[cfg(test)]
+mod edit_rooms_tests {
+    use std::time::Duration;
+
+    use crate::db::run_migrations;
+    use crate::models::news::NewNews;
+    use crate::models::user::NewUser;
+    use crate::repository::postgres::PgRepository;
+    use crate::repository::{NewsRepository, UserRepository};
+    use crate::test::test_utils::{
+        cleanup_test_database, configure_app, get_test_pool, test_config,
+    };
+    use crate::utils::session::{issue_session_token, SessionInfo};
+    use actix_web::{App, HttpServer};
+    use futures::{SinkExt, StreamExt};
+    use serde_json::{json, Value};
+    use tokio::net::TcpStream;
+    use tokio_tungstenite::tungstenite::client::IntoClientRequest;
+    use tokio_tungstenite::tungstenite::{Error as WsError, Message};
+    use tokio_tungstenite::{connect_async, MaybeTlsStream, WebSocketStream};
+
+    type Socket = WebSocketStream<MaybeTlsStream<TcpStream>>;
+
+    // the next JSON message from the room
+    async fn next_message(socket: &mut Socket) -> Value {
+        loop {
+            let message = tokio::time::timeout(Duration::from_secs(5), socket.next())
+                .await
+                .expect("No message within 5 seconds")
+                .expect("The socket closed")
+                .unwrap();
+            if let Message::Text(text) = message {
+                return serde_json::from_str(&text).unwrap();
+            }
+        }
+    }
+
+    async fn send(socket: &mut Socket, message: Value) {
+        socket
+            .send(Message::text(message.to_string()))
+            .await
+            .unwrap();
+    }
+
+    fn usernames(presence: &Value) -> Vec<&str> {
+        assert_eq!(presence["type"], "presence");
+        presence["members"]
+            .as_array()
+            .unwrap()
+            .iter()
+            .map(|member| member["username"].as_str().unwrap())
+            .collect()
+    }
+
+    #[actix_web::test]
+    async fn test_presence_locks_and_takeover() {
+        let (pool, database_url) = get_test_pool();
+        run_migrations(&mut pool.get().unwrap()).unwrap();
+        let mut config = test_config();
+        config.edit_locks.enforce = true;
+
+        let repository = PgRepository::new(pool.clone());
+        let mut tokens = Vec::new();
+        let mut user_ids = Vec::new();
+        for (username, is_admin) in [("author", false), ("admin", true), ("reader", false)] {
+            let user = UserRepository::create(
+                &repository,
+                NewUser {
+                    username: username.to_string(),
+                    password: "hash".to_string(),
+                    is_admin,
+                },
+            )
+            .unwrap();
+            let token = issue_session_token(
+                &mut pool.get().unwrap(),
+                &config.jwt,
+                user.id,
+                username,
+                is_admin,
+                SessionInfo::default(),
+            )
+            .unwrap();
+            user_ids.push(user.id);
+            tokens.push(token);
+        }
+        let news = NewsRepository::create(
+            &repository,
+            NewNews {
+                title: "Draft".to_string(),
+                content: "Work in progress".to_string(),
+                author_id: user_ids[0],
+            },
+            &[],
+        )
+        .unwrap();
+
+        // one worker, the rooms live in its app
+        let app_pool = pool.clone();
+        let app_config = config.clone();
+        let server = HttpServer::new(move || {
+            App::new().configure(configure_app(app_pool.clone(), app_config.clone()))
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+        let addr = server.addrs()[0];
+        let server = server.run();
+        let handle = server.handle();
+        actix_web::rt::spawn(server);
+        let room = format!("ws://{}/api/v1/news/{}/room", addr, news.id);
+
+        // browsers send the token in the query, other clients in the header
+        let (mut author, _) = connect_async(format!("{}?access_token={}", room, tokens[0]))
+            .await
+            .unwrap();
+        assert_eq!(
+            next_message(&mut author).await,
+            json!({ "type": "lock", "lock": null })
+        );
+        assert_eq!(usernames(&next_message(&mut author).await), ["author"]);
+
+        let mut request = room.as_str().into_client_request().unwrap();
+        request.headers_mut().insert(
+            "Authorization",
+            format!("Bearer {}", tokens[1]).parse().unwrap(),
+        );
+        let (mut admin, _) = connect_async(request).await.unwrap();
+        next_message(&mut admin).await;
+        assert_eq!(
+            usernames(&next_message(&mut admin).await),
+            ["author", "admin"]
+        );
+        assert_eq!(
+            usernames(&next_message(&mut author).await),
+            ["author", "admin"]
+        );
+
+        // only those who may edit the article get in
+        for url in [format!("{}?access_token={}", room, tokens[2]), room.clone()] {
+            match connect_async(url).await {
+                Err(WsError::Http(response)) => {
+                    assert!([401, 403].contains(&response.status().as_u16()))
+                }
+                other => panic!(
+                    "Expected the handshake to be rejected, got {:?}",
+                    other.map(|_| ())
+                ),
+            }
+        }
+
+        send(&mut author, json!({ "type": "lock" })).await;
+        for socket in [&mut author, &mut admin] {
+            let lock = next_message(socket).await;
+            assert_eq!(lock["lock"]["holder"]["username"], "author");
+        }
+        send(&mut admin, json!({ "type": "lock" })).await;
+        let denied = next_message(&mut admin).await;
+        assert_eq!(denied["type"], "error");
+        assert_eq!(denied["code"], "locked");
+
+        // only the holder can save, the room hears about it
+        let client = reqwest::Client::new();
+        let save = |token: &str| {
+            client
+                .patch(format!("http://{}/api/v1/news/{}", addr, news.id))
+                .bearer_auth(token)
+                .json(&json!({ "news_title": "Finished" }))
+                .send()
+        };
+        assert_eq!(save(&tokens[1]).await.unwrap().status(), 423);
+        assert_eq!(save(&tokens[0]).await.unwrap().status(), 200);
+        for socket in [&mut author, &mut admin] {
+            assert_eq!(
+                next_message(socket).await,
+                json!({ "type": "changed", "news_id": news.id, "change": "updated" })
+            );
+        }
+
+        // an admin can take the lock over, authors can't
+        send(&mut admin, json!({ "type": "lock", "force": true })).await;
+        for socket in [&mut author, &mut admin] {
+            let lock = next_message(socket).await;
+            assert_eq!(lock["lock"]["holder"]["username"], "admin");
+        }
+        send(&mut author, json!({ "type": "lock", "force": true })).await;
+        assert_eq!(next_message(&mut author).await["code"], "forbidden");
+        assert_eq!(save(&tokens[0]).await.unwrap().status(), 423);
+
+        // leaving gives up the lock
+        admin.close(None).await.unwrap();
+        assert_eq!(
+            next_message(&mut author).await,
+            json!({ "type": "lock", "lock": null })
+        );
+        assert_eq!(usernames(&next_message(&mut author).await), ["author"]);
+
+        author.close(None).await.unwrap();
+        handle.stop(true).await;
+        drop(pool);
+        cleanup_test_database(&database_url);
+    }
+}
@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod error_response_tests {
+    use std::sync::Arc;
+
+    use crate::handlers::admin::create_category;
+    use crate::repository::memory::MemoryRepository;
+    use crate::test::test_utils::configure_memory_app;
+    use crate::utils::error_response::{self, AppError};
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App, ResponseError};
+    use diesel::result::{DatabaseErrorKind, Error as DieselError};
+    use serde_json::{json, Value};
+
+    fn category(name: &str) -> Value {
+        json!({
+            "id": 0,
+            "name": name,
+            "description": "Technology related articles",
+            "created_at": "2025-01-01T00:00:00",
+            "updated_at": "2025-01-01T00:00:00"
+        })
+    }
+
+    #[actix_web::test]
+    async fn test_rejected_requests_are_problem_details() {
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(Arc::new(MemoryRepository::new())))
+                .configure(|cfg| error_response::configure(cfg, 256))
+                .route("/categories", web::post().to(create_category)),
+        )
+        .await;
+
+        // field validation
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/categories")
+                .set_json(category(""))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        assert_eq!(
+            resp.headers().get("content-type").unwrap(),
+            "application/problem+json"
+        );
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["type"], "about:blank");
+        assert_eq!(body["status"], 422);
+        assert_eq!(body["code"], "validation_failed");
+        assert_eq!(body["errors"]["name"][0]["message"], "Name is required");
+
+        // malformed body
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/categories")
+                .insert_header(("content-type", "application/json"))
+                .set_payload("{")
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "bad_request");
+
+        // over the payload limit
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/categories")
+                .set_json(category(&"x".repeat(512)))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::PAYLOAD_TOO_LARGE);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "payload_too_large");
+
+        // no such route
+        let resp = call_service(&app, TestRequest::get().uri("/nowhere").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "not_found");
+    }
+
+    #[test]
+    fn test_internal_details_are_not_exposed() {
+        let error = AppError::DatabaseError("relation \"users\" does not exist".into());
+        assert_eq!(error.status_code(), StatusCode::INTERNAL_SERVER_ERROR);
+
+        let problem = error.problem();
+        assert_eq!(problem.code, "internal_error");
+        assert_eq!(problem.detail, "Internal server error");
+    }
+
+    #[test]
+    fn test_constraint_violations_map_to_client_errors() {
+        let unique = DieselError::DatabaseError(
+            DatabaseErrorKind::UniqueViolation,
+            Box::new("duplicate key value violates unique constraint".to_string()),
+        );
+        assert_eq!(AppError::from(unique).status_code(), StatusCode::CONFLICT);
+
+        let foreign_key = DieselError::DatabaseError(
+            DatabaseErrorKind::ForeignKeyViolation,
+            Box::new("violates foreign key constraint".to_string()),
+        );
+        assert_eq!(
+            AppError::from(foreign_key).status_code(),
+            StatusCode::UNPROCESSABLE_ENTITY
+        );
+
+        assert_eq!(
+            AppError::from(DieselError::NotFound).status_code(),
+            StatusCode::NOT_FOUND
+        );
+        assert_eq!(
+            AppError::from(DieselError::RollbackTransaction).code(),
+            "internal_error"
+        );
+    }
+}
@@ -0,0 +1,477 @@
+#[cfg(test)]
+mod graphql_tests {
+    use std::sync::Arc;
+
+    use crate::db::{establish_connection, run_migrations};
+    use crate::graphql;
+    use crate::handlers::admin::update_news;
+    use crate::handlers::graphql::execute;
+    use crate::middleware::rate_limit::{
+        InMemoryStore, RateLimitKey, RateLimitMiddleware, RateLimitPolicy, RateLimitStore,
+    };
+    use crate::models::category::NewCategory;
+    use crate::models::news::NewNews;
+    use crate::models::user::NewUser;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::postgres::PgRepository;
+    use crate::repository::{CategoryRepository, NewsRepository, UserRepository};
+    use crate::test::test_utils::{
+        cleanup_test_database, configure_app, configure_memory_app, get_test_pool, signed_in,
+        test_config,
+    };
+    use actix_web::test::{call_service, init_service, read_body, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App};
+    use serde_json::{json, Value};
+
+    struct Seeded {
+        author: i32,
+        other: i32,
+        tech: i32,
+        science: i32,
+        // oldest first
+        news: Vec<i32>,
+    }
+
+    // two writers, "Tech" and "Science", three articles by the first one
+    fn seed<R: NewsRepository + CategoryRepository + UserRepository>(repository: &R) -> Seeded {
+        let user = |name: &str| {
+            UserRepository::create(
+                repository,
+                NewUser {
+                    username: name.to_string(),
+                    password: "hash".to_string(),
+                    is_admin: false,
+                },
+            )
+            .unwrap()
+            .id
+        };
+        let category = |name: &str| {
+            CategoryRepository::create(
+                repository,
+                NewCategory {
+                    name: name.to_string(),
+                    description: None,
+                },
+            )
+            .unwrap()
+            .id
+        };
+        let (author, other) = (user("author"), user("other"));
+        let (tech, science) = (category("Tech"), category("Science"));
+
+        let articles = [
+            ("Rust 2024", vec![tech]),
+            ("Quantum computing", vec![tech, science]),
+            ("Black holes", vec![science]),
+        ];
+        let news = articles
+            .into_iter()
+            .map(|(title, category_ids)| {
+                NewsRepository::create(
+                    repository,
+                    NewNews {
+                        title: title.to_string(),
+                        content: format!("All about {}", title),
+                        author_id: author,
+                    },
+                    &category_ids,
+                )
+                .unwrap()
+                .id
+            })
+            .collect();
+
+        Seeded {
+            author,
+            other,
+            tech,
+            science,
+            news,
+        }
+    }
+
+    // posts a GraphQL request, as (user id, is admin) when given
+    async fn post(
+        repository: &Arc<MemoryRepository>,
+        caller: Option<(i32, bool)>,
+        query: &str,
+        variables: Value,
+    ) -> Value {
+        let request = TestRequest::post()
+            .uri("/graphql")
+            .set_json(json!({ "query": query, "variables": variables }))
+            .to_request();
+        let app = App::new()
+            .configure(configure_memory_app(repository.clone()))
+            .configure(graphql::configure)
+            .route("/graphql", web::post().to(execute));
+        let resp = match caller {
+            Some((user_id, is_admin)) => {
+                let app = init_service(app.wrap_fn(signed_in(user_id, is_admin))).await;
+                call_service(&app, request).await
+            }
+            None => call_service(&init_service(app).await, request).await,
+        };
+        assert_eq!(resp.status(), StatusCode::OK);
+        read_body_json(resp).await
+    }
+
+    fn error_code(response: &Value) -> &str {
+        response["errors"][0]["extensions"]["code"]
+            .as_str()
+            .unwrap_or_else(|| panic!("expected an error, got {}", response))
+    }
+
+    #[actix_web::test]
+    async fn test_article_page_in_one_request() {
+        let repository = Arc::new(MemoryRepository::new());
+        let seeded = seed(repository.as_ref());
+
+        let query = r#"
+            query ($id: Int!) {
+                article(id: $id) {
+                    title
+                    author { username news { title } }
+                    categories { name news { title } }
+                }
+            }
+        "#;
+        let body = post(&repository, None, query, json!({ "id": seeded.news[1] })).await;
+        assert!(body.get("errors").is_none(), "{}", body);
+
+        let article = &body["data"]["article"];
+        assert_eq!(article["title"], "Quantum computing");
+        assert_eq!(article["author"]["username"], "author");
+        assert_eq!(
+            article["author"]["news"],
+            json!([
+                { "title": "Black holes" },
+                { "title": "Quantum computing" },
+                { "title": "Rust 2024" }
+            ])
+        );
+        // categories by name, their articles newest first
+        assert_eq!(article["categories"][0]["name"], "Science");
+        assert_eq!(
+            article["categories"][0]["news"],
+            json!([{ "title": "Black holes" }, { "title": "Quantum computing" }])
+        );
+        assert_eq!(article["categories"][1]["name"], "Tech");
+
+        let body = post(&repository, None, "{ article(id: 0) { title } }", json!({})).await;
+        assert_eq!(body["data"]["article"], Value::Null);
+
+        // only users who wrote something are authors
+        let query = "query ($id: Int!) { author(id: $id) { username } authors { username } }";
+        let body = post(&repository, None, query, json!({ "id": seeded.other })).await;
+        assert_eq!(body["data"]["author"], Value::Null);
+        assert_eq!(body["data"]["authors"], json!([{ "username": "author" }]));
+    }
+
+    #[actix_web::test]
+    async fn test_news_pagination_and_filters() {
+        let repository = Arc::new(MemoryRepository::new());
+        let seeded = seed(repository.as_ref());
+        let query = r#"
+            query ($filter: NewsFilterInput, $first: Int = 20, $offset: Int = 0) {
+                news(filter: $filter, first: $first, offset: $offset) {
+                    totalCount
+                    items { title }
+                }
+            }
+        "#;
+
+        let body = post(&repository, None, query, json!({ "first": 2 })).await;
+        assert_eq!(body["data"]["news"]["totalCount"], 3);
+        assert_eq!(
+            body["data"]["news"]["items"],
+            json!([{ "title": "Black holes" }, { "title": "Quantum computing" }])
+        );
+
+        let body = post(&repository, None, query, json!({ "first": 2, "offset": 2 })).await;
+        assert_eq!(
+            body["data"]["news"]["items"],
+            json!([{ "title": "Rust 2024" }])
+        );
+
+        let filter = json!({ "filter": { "categoryId": seeded.tech } });
+        let body = post(&repository, None, query, filter).await;
+        assert_eq!(body["data"]["news"]["totalCount"], 2);
+
+        let filter = json!({ "filter": { "categoryId": seeded.science, "title": "HOLE" } });
+        let body = post(&repository, None, query, filter).await;
+        assert_eq!(
+            body["data"]["news"]["items"],
+            json!([{ "title": "Black holes" }])
+        );
+
+        let filter = json!({ "filter": { "authorId": seeded.other } });
+        let body = post(&repository, None, query, filter).await;
+        assert_eq!(body["data"]["news"]["totalCount"], 0);
+
+        // pages are capped
+        let body = post(&repository, None, query, json!({ "first": 500 })).await;
+        assert!(body["errors"].is_array());
+
+        // so are the lists inside them, and nesting them costs what their pages could hold
+        let nested = r#"
+            query ($id: Int!) {
+                author(id: $id) { news(first: 1, offset: 1) { title } }
+            }
+        "#;
+        let body = post(&repository, None, nested, json!({ "id": seeded.author })).await;
+        assert_eq!(
+            body["data"]["author"]["news"],
+            json!([{ "title": "Quantum computing" }])
+        );
+        let body = post(
+            &repository,
+            None,
+            "{ categories { name news(first: 1) { title } } }",
+            json!({}),
+        )
+        .await;
+        assert_eq!(
+            body["data"]["categories"],
+            json!([
+                { "name": "Science", "news": [{ "title": "Black holes" }] },
+                { "name": "Tech", "news": [{ "title": "Quantum computing" }] }
+            ])
+        );
+        let nested = "{ categories { news(first: 100) { author { news(first: 100) { id } } } } }";
+        let body = post(&repository, None, nested, json!({})).await;
+        assert!(body["errors"][0]["message"]
+            .as_str()
+            .unwrap()
+            .contains("complex"));
+    }
+
+    #[test]
+    fn test_nested_pages_are_read_in_the_query() {
+        let (pool, database_url) = get_test_pool();
+        run_migrations(&mut pool.get().unwrap()).unwrap();
+        let repository = PgRepository::new(pool.clone());
+        let seeded = seed(&repository);
+        let (tech, science) = (seeded.tech, seeded.science);
+
+        // one row per category or author, not their whole lists
+        let links = repository.news_in(&[tech, science], 0, 1).unwrap();
+        let mut pages: Vec<(i32, i32)> = links.iter().map(|(id, news)| (*id, news.id)).collect();
+        pages.sort();
+        let mut expected = vec![(tech, seeded.news[1]), (science, seeded.news[2])];
+        expected.sort();
+        assert_eq!(pages, expected);
+        let news = repository
+            .by_authors(&[seeded.author, seeded.other], 1, 1)
+            .unwrap();
+        assert_eq!(news.len(), 1);
+        assert_eq!(news[0].id, seeded.news[1]);
+        assert!(repository.news_in(&[tech], 2, 5).unwrap().is_empty());
+
+        drop(repository);
+        drop(pool);
+        cleanup_test_database(&database_url);
+    }
+
+    #[actix_web::test]
+    async fn test_mutations_follow_the_rest_authorization() {
+        let repository = Arc::new(MemoryRepository::new());
+        let seeded = seed(repository.as_ref());
+        let update = r#"
+            mutation ($id: Int!) {
+                updateNews(id: $id, input: { title: "Edited" }) { title categories { name } }
+            }
+        "#;
+        let variables = json!({ "id": seeded.news[0] });
+
+        let body = post(&repository, None, update, variables.clone()).await;
+        assert_eq!(error_code(&body), "unauthorized");
+
+        let other = Some((seeded.other, false));
+        let body = post(&repository, other, update, variables.clone()).await;
+        assert_eq!(error_code(&body), "forbidden");
+
+        let author = Some((seeded.author, false));
+        let body = post(&repository, author, update, variables.clone()).await;
+        assert_eq!(
+            body["data"]["updateNews"],
+            json!({ "title": "Edited", "categories": [{ "name": "Tech" }] })
+        );
+
+        // edits based on an older version are rejected like with If-Match
+        let stale = r#"
+            mutation ($id: Int!) {
+                updateNews(id: $id, input: { title: "Late" }, ifVersion: "1") { title }
+            }
+        "#;
+        let body = post(&repository, author, stale, variables.clone()).await;
+        assert_eq!(error_code(&body), "precondition_failed");
+        assert_eq!(
+            body["errors"][0]["extensions"]["current"]["title"],
+            "Edited"
+        );
+
+        // creating needs an admin, like POST /api/v1/news
+        let create = r#"
+            mutation ($input: CreateNewsInput!) {
+                createNews(input: $input) { id author { username } categories { name } }
+            }
+        "#;
+        let input = json!({ "input": {
+            "title": "Fresh",
+            "content": "Just in",
+            "authorId": seeded.author,
+            "categoryIds": [seeded.science]
+        }});
+        let body = post(&repository, author, create, input.clone()).await;
+        assert_eq!(error_code(&body), "forbidden");
+
+        let admin = Some((seeded.other, true));
+        let body = post(&repository, admin, create, input).await;
+        let created = &body["data"]["createNews"];
+        assert_eq!(created["author"]["username"], "author");
+        assert_eq!(created["categories"], json!([{ "name": "Science" }]));
+
+        let delete = "mutation ($id: Int!) { deleteNews(id: $id) }";
+        let variables = json!({ "id": created["id"] });
+        let body = post(&repository, other, delete, variables.clone()).await;
+        assert_eq!(error_code(&body), "forbidden");
+        let body = post(&repository, author, delete, variables.clone()).await;
+        assert_eq!(body["data"]["deleteNews"], true);
+        let body = post(&repository, author, delete, variables).await;
+        assert_eq!(error_code(&body), "not_found");
+    }
+
+    #[actix_web::test]
+    async fn test_mutations_share_the_rest_write_limit() {
+        let repository = Arc::new(MemoryRepository::new());
+        let seeded = seed(repository.as_ref());
+        let mut config = test_config();
+        config.rate_limit.admin.capacity = 2;
+        config.rate_limit.admin.refill_per_second = 0.001;
+        let store: Arc<dyn RateLimitStore> = Arc::new(InMemoryStore::new());
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .app_data(web::Data::new(config.clone()))
+                .app_data(web::Data::from(store))
+                .configure(graphql::configure)
+                .wrap_fn(signed_in(seeded.author, false))
+                .route("/graphql", web::post().to(execute))
+                .route(
+                    "/news/{id}",
+                    web::patch().to(update_news).wrap(RateLimitMiddleware::new(
+                        RateLimitPolicy::new(
+                            "admin",
+                            config.rate_limit.admin.capacity,
+                            config.rate_limit.admin.refill_per_second,
+                            RateLimitKey::User,
+                        ),
+                    )),
+                ),
+        )
+        .await;
+        let mutation = r#"
+            mutation ($id: Int!, $title: String!) {
+                updateNews(id: $id, input: { title: $title }) { title }
+            }
+        "#;
+        let update = |title: &str| {
+            TestRequest::post()
+                .uri("/graphql")
+                .set_json(json!({
+                    "query": mutation,
+                    "variables": { "id": seeded.news[0], "title": title },
+                }))
+                .to_request()
+        };
+
+        // one write over REST and one over GraphQL use up the same bucket
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&format!("/news/{}", seeded.news[0]))
+                .set_json(json!({ "news_title": "Over REST" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = read_body_json(call_service(&app, update("Over GraphQL")).await).await;
+        assert_eq!(body["data"]["updateNews"]["title"], "Over GraphQL");
+        let body: Value = read_body_json(call_service(&app, update("One too many")).await).await;
+        assert_eq!(error_code(&body), "rate_limited");
+
+        // queries aren't charged to it
+        let query = TestRequest::post()
+            .uri("/graphql")
+            .set_json(json!({ "query": "{ categories { name } }" }))
+            .to_request();
+        let body: Value = read_body_json(call_service(&app, query).await).await;
+        assert!(body.get("errors").is_none(), "{}", body);
+    }
+
+    #[actix_web::test]
+    async fn test_endpoint_and_playground() {
+        let mut config = test_config();
+        let pool = establish_connection(&config.database);
+        let app =
+            init_service(App::new().configure(configure_app(pool.clone(), config.clone()))).await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/graphql")
+                .set_json(json!({ "query": r#"{
+                    news(first: 5, filter: { title: "100%" }) {
+                        totalCount
+                        items { title author { username news { id } } categories { name news { id } } }
+                    }
+                    categories { id name }
+                    authors { username }
+                }"# }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = read_body_json(resp).await;
+        assert!(body.get("errors").is_none(), "{}", body);
+        assert!(body["data"]["news"]["items"].is_array());
+        assert!(body["data"]["categories"].is_array());
+
+        // queries also work over GET, mutations don't
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/graphql?query=%7B%20categories%20%7B%20id%20%7D%20%7D")
+                .to_request(),
+        )
+        .await;
+        let body: Value = read_body_json(resp).await;
+        assert!(body["data"]["categories"].is_array(), "{}", body);
+
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/graphql?query=mutation%20%7B%20deleteNews(id%3A%200)%20%7D")
+                .to_request(),
+        )
+        .await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(error_code(&body), "bad_request");
+
+        let resp = call_service(&app, TestRequest::get().uri("/graphql").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::BAD_REQUEST);
+
+        // the playground is off unless configured
+        let resp = call_service(&app, TestRequest::get().uri("/graphiql").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        config.graphql.playground = true;
+        let app = init_service(App::new().configure(configure_app(pool, config))).await;
+        let resp = call_service(&app, TestRequest::get().uri("/graphiql").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let page = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+        assert!(page.contains("graphiql"));
+    }
+}
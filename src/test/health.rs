@@ -0,0 +1,50 @@
+#[cfg(test)]
+mod health_tests {
+    use crate::db::establish_connection;
+    use crate::test::test_utils::{
+        cleanup_test_database, configure_app, get_test_pool, test_config,
+    };
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, App};
+
+    #[actix_web::test]
+    async fn test_probes_on_migrated_database() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let app = init_service(App::new().configure(configure_app(pool, config))).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let resp = call_service(&app, TestRequest::get().uri("/readyz").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert!(body["checks"]["tasks"].is_object());
+
+        let resp = call_service(&app, TestRequest::get().uri("/version").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["version"], env!("CARGO_PKG_VERSION"));
+        assert!(body["git_sha"].is_string());
+        assert!(body["build_time"].is_string());
+        assert!(body["schema_version"].is_string());
+    }
+
+    #[actix_web::test]
+    async fn test_not_ready_with_pending_migrations() {
+        let (pool, database_url) = get_test_pool();
+        let app = init_service(App::new().configure(configure_app(pool, test_config()))).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/readyz").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::SERVICE_UNAVAILABLE);
+        let body: serde_json::Value = read_body_json(resp).await;
+        assert_eq!(body["checks"]["migrations"], "pending");
+
+        // still alive, only not ready
+        let resp = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        drop(app);
+        cleanup_test_database(&database_url);
+    }
+}
@@ -0,0 +1,162 @@
+#[cfg(test)]
+mod http_cache_tests {
+    use std::sync::Arc;
+
+    use crate::handlers::news::{get_news_detail, list_news};
+    use crate::models::category::CategoryChangeset;
+    use crate::models::news::NewNews;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::{CategoryRepository, NewsRepository};
+    use crate::test::test_utils::{configure_memory_app, seed_article};
+    use actix_web::test::{call_service, init_service, read_body, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App};
+    use chrono::Utc;
+    use serde_json::Value;
+
+    #[actix_web::test]
+    async fn test_unchanged_articles_are_not_resent() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (_, news_id, category_id) = seed_article(repository.as_ref(), "author", false);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .route("/news/{id}", web::get().to(get_news_detail)),
+        )
+        .await;
+        let uri = format!("/news/{}", news_id);
+
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let headers = resp.headers().clone();
+        let etag = headers.get("etag").unwrap().to_str().unwrap().to_string();
+        let last_modified = headers.get("last-modified").unwrap().clone();
+        assert_eq!(headers.get("cache-control").unwrap(), "public, no-cache");
+        assert_eq!(
+            headers.get("surrogate-key").unwrap().to_str().unwrap(),
+            format!("news-{} category-{}", news_id, category_id)
+        );
+
+        // revalidating with either validator costs no body
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&uri)
+                .insert_header(("If-None-Match", etag.as_str()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        assert_eq!(resp.headers().get("etag").unwrap(), etag.as_str());
+        // the CDN keeps the keys it stored with the body
+        assert!(resp.headers().get("surrogate-key").is_none());
+        assert!(read_body(resp).await.is_empty());
+
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&uri)
+                .insert_header(("If-None-Match", format!("W/{}", etag)))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&uri)
+                .insert_header(("If-Modified-Since", last_modified.clone()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        // a renamed category changes the article it shows up in
+        CategoryRepository::update(
+            repository.as_ref(),
+            category_id,
+            CategoryChangeset {
+                name: Some("Technology".to_string()),
+                description: None,
+                updated_at: Utc::now().naive_utc(),
+            },
+            None,
+        )
+        .unwrap();
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&uri)
+                .insert_header(("If-None-Match", etag.as_str()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_ne!(resp.headers().get("etag").unwrap(), etag.as_str());
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["categories"][0]["name"], "Technology");
+    }
+
+    #[actix_web::test]
+    async fn test_news_list_is_revalidated() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (author_id, news_id, _) = seed_article(repository.as_ref(), "author", false);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .route("/news", web::get().to(list_news)),
+        )
+        .await;
+        let revalidate = |etag: &str| {
+            TestRequest::get()
+                .uri("/news")
+                .insert_header(("If-None-Match", etag.to_string()))
+                .to_request()
+        };
+
+        let resp = call_service(&app, TestRequest::get().uri("/news").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(resp.headers().get("surrogate-key").unwrap(), "news-list");
+        assert!(resp.headers().get("last-modified").is_none());
+        let etag = resp
+            .headers()
+            .get("etag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+        assert!(etag.starts_with("W/"));
+
+        let resp = call_service(&app, revalidate(&etag)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+        let resp = call_service(&app, revalidate("*")).await;
+        assert_eq!(resp.status(), StatusCode::NOT_MODIFIED);
+
+        // new and removed articles both change the list
+        NewsRepository::create(
+            repository.as_ref(),
+            NewNews {
+                title: "Follow-up".to_string(),
+                content: "More details".to_string(),
+                author_id,
+            },
+            &[],
+        )
+        .unwrap();
+        let resp = call_service(&app, revalidate(&etag)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let etag = resp
+            .headers()
+            .get("etag")
+            .unwrap()
+            .to_str()
+            .unwrap()
+            .to_string();
+
+        NewsRepository::delete(repository.as_ref(), news_id, None).unwrap();
+        let resp = call_service(&app, revalidate(&etag)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+    }
+}
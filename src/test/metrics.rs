@@ -0,0 +1,63 @@
+#[cfg(test)]
+mod metrics_tests {
+    use crate::db::establish_connection;
+    use crate::middleware::metrics::MetricsMiddleware;
+    use crate::test::test_utils::{configure_app, test_config};
+    use actix_web::test::{call_service, init_service, read_body, try_call_service, TestRequest};
+    use actix_web::{http::StatusCode, App};
+
+    #[actix_web::test]
+    async fn test_metrics_by_route_pattern() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let app = init_service(
+            App::new()
+                .wrap(MetricsMiddleware)
+                .configure(configure_app(pool, config)),
+        )
+        .await;
+
+        // rejected by the auth middleware, before reaching a handler
+        let err = try_call_service(&app, TestRequest::get().uri("/me/sessions").to_request())
+            .await
+            .unwrap_err();
+        assert_eq!(
+            err.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+        call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+
+        assert!(
+            body.contains(r#"http_requests_total{method="GET",route="/me/sessions",status="401"}"#)
+        );
+        assert!(body.contains(r#"http_requests_total{method="GET",route="/healthz",status="200"}"#));
+        assert!(body.contains("http_request_duration_seconds_bucket"));
+        assert!(body.contains(r#"auth_failures_total{reason="missing_credentials"}"#));
+        assert!(body.contains("db_pool_max_connections"));
+    }
+
+    #[actix_web::test]
+    async fn test_metrics_token() {
+        let mut config = test_config();
+        config.metrics.token = Some("scrape-token".to_string());
+        let pool = establish_connection(&config.database);
+        let app = init_service(App::new().configure(configure_app(pool, config))).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/metrics").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/metrics")
+                .insert_header(("Authorization", "Bearer scrape-token"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,31 @@
+#[cfg(test)]
+mod migrations_tests {
+    use crate::db::{migration_status, revert_migration, run_migrations};
+    use crate::test::test_utils::{cleanup_test_database, get_test_pool};
+
+    #[test]
+    fn test_migrate_up_down_and_status() {
+        let (pool, database_url) = get_test_pool();
+        let mut conn = pool.get().unwrap();
+
+        // a fresh database has everything pending
+        let status = migration_status(&mut conn).unwrap();
+        assert!(!status.is_empty());
+        assert!(status.iter().all(|migration| !migration.applied));
+
+        let applied = run_migrations(&mut conn).unwrap();
+        assert_eq!(applied.len(), status.len());
+        assert!(run_migrations(&mut conn).unwrap().is_empty());
+
+        // reverting leaves only the newest migration pending
+        let reverted = revert_migration(&mut conn).unwrap();
+        let status = migration_status(&mut conn).unwrap();
+        let pending: Vec<_> = status.iter().filter(|m| !m.applied).collect();
+        assert_eq!(pending.len(), 1);
+        assert!(pending[0].name.replace('-', "").starts_with(&reverted));
+
+        drop(conn);
+        drop(pool);
+        cleanup_test_database(&database_url);
+    }
+}
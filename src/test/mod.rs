@@ -1,4 +1,28 @@
+pub mod api_key;
+pub mod api_v1;
 pub mod auth;
 pub mod category;
+pub mod cli;
+pub mod concurrency;
+pub mod config;
+pub mod db;
+pub mod edit_rooms;
+pub mod error_response;
+pub mod graphql;
+pub mod health;
+pub mod http_cache;
+pub mod metrics;
+pub mod migrations;
 pub mod news;
+pub mod news_stream;
+pub mod oidc;
+pub mod openapi;
+pub mod password;
+pub mod rate_limit;
+pub mod request_tracing;
+pub mod response_cache;
+pub mod session;
+pub mod supervisor;
 pub mod test_utils;
+pub mod trash;
+pub mod webhooks;
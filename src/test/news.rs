@@ -1,104 +1,205 @@
 #[cfg(test)]
 mod tests {
-    use crate::models::news::News;
-
-    use super::*;
-    use chrono::Utc;
-    use std::collections::HashMap;
-    use std::sync::{Arc, Mutex};
-
-    // Mock database
-    struct MockDb {
-        news_store: Arc<Mutex<HashMap<i32, News>>>,
+    use std::sync::Arc;
+
+    use crate::handlers::admin::{create_news, delete_news, update_news};
+    use crate::handlers::news::{get_news_detail, list_news};
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::NewsRepository;
+    use crate::test::test_utils::{configure_memory_app, seed_article, signed_in};
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App};
+    use serde_json::{json, Value};
+
+    #[actix_web::test]
+    async fn test_list_and_detail() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (_, news_id, _) = seed_article(repository.as_ref(), "author", false);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository))
+                .route("/news", web::get().to(list_news))
+                .route("/news/{id}", web::get().to(get_news_detail)),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/news").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body[0]["title"], "Breaking");
+
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/news/{}", news_id))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["content"], "Something happened");
+        assert_eq!(body["categories"][0]["name"], "Tech");
+
+        let resp = call_service(&app, TestRequest::get().uri("/news/99").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
-    impl MockDb {
-        fn new() -> Self {
-            MockDb {
-                news_store: Arc::new(Mutex::new(HashMap::new())),
-            }
-        }
-
-        fn insert_news(&self, id: i32, title: &str, content: &str, author_id: i32) {
-            let mut store = self.news_store.lock().unwrap();
-            store.insert(
-                id,
-                News {
-                    id,
-                    title: title.to_string(),
-                    content: content.to_string(),
-                    author_id,
-                    created_at: Utc::now().naive_utc(),
-                    updated_at: Utc::now().naive_utc(),
-                },
-            );
-        }
-
-        fn update_news(
-            &self,
-            id: i32,
-            new_title: &str,
-            new_content: &str,
-        ) -> Result<News, &'static str> {
-            let mut store = self.news_store.lock().unwrap();
-            if let Some(news) = store.get_mut(&id) {
-                news.title = new_title.to_string();
-                news.content = new_content.to_string();
-                news.updated_at = Utc::now().naive_utc();
-                return Ok(news.clone());
-            }
-            Err("News not found")
-        }
-
-        fn delete_news(&self, id: i32) -> Result<(), &'static str> {
-            let mut store = self.news_store.lock().unwrap();
-            if store.remove(&id).is_some() {
-                return Ok(());
-            }
-            Err("News not found")
-        }
+    #[actix_web::test]
+    async fn test_create_news() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (author_id, _, _) = seed_article(repository.as_ref(), "author", false);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .route("/news", web::post().to(create_news)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/news")
+                .set_json(json!({
+                    "title": "Second",
+                    "content": "More content",
+                    "author_id": author_id,
+                    "category_ids": []
+                }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+
+        // an unknown category is rejected like the foreign key would
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/news")
+                .set_json(json!({
+                    "title": "Third",
+                    "content": "Even more",
+                    "author_id": author_id,
+                    "category_ids": [99]
+                }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["code"], "invalid_reference");
+        assert_eq!(NewsRepository::list(repository.as_ref()).unwrap().len(), 2);
     }
 
-    #[test]
-    fn test_update_news_success() {
-        let db = MockDb::new();
-        db.insert_news(1, "Original Title", "Original Content", 1);
-
-        let updated_news = db.update_news(1, "Updated Title", "Updated Content");
-        assert!(updated_news.is_ok());
-
-        let news = updated_news.unwrap();
-        assert_eq!(news.title, "Updated Title");
-        assert_eq!(news.content, "Updated Content");
+    #[actix_web::test]
+    async fn test_update_news_success() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (author_id, news_id, _) = seed_article(repository.as_ref(), "author", false);
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(author_id, false))
+                .configure(configure_memory_app(repository.clone()))
+                .route("/news/{id}", web::put().to(update_news)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri(&format!("/news/{}", news_id))
+                .set_json(json!({ "news_title": "Updated Title", "category_ids": [] }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["news"]["title"], "Updated Title");
+        // fields that weren't sent are kept
+        assert_eq!(body["news"]["content"], "Something happened");
+        assert!(NewsRepository::categories(repository.as_ref(), news_id)
+            .unwrap()
+            .is_empty());
     }
 
-    #[test]
-    fn test_update_news_not_found() {
-        let db = MockDb::new();
-        let result = db.update_news(99, "New Title", "New Content");
-
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "News not found");
+    #[actix_web::test]
+    async fn test_update_news_not_found() {
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(1, true))
+                .configure(configure_memory_app(Arc::new(MemoryRepository::new())))
+                .route("/news/{id}", web::put().to(update_news)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri("/news/99")
+                .set_json(json!({ "news_title": "New Title" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 
-    #[test]
-    fn test_delete_news_success() {
-        let db = MockDb::new();
-        db.insert_news(1, "Test News", "Some Content", 1);
-
-        let result = db.delete_news(1);
-        assert!(result.is_ok());
-
-        let check = db.delete_news(1);
-        assert!(check.is_err()); // News should be gone now
+    #[actix_web::test]
+    async fn test_update_news_by_another_user() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (author_id, news_id, _) = seed_article(repository.as_ref(), "author", false);
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(author_id + 100, false))
+                .configure(configure_memory_app(repository))
+                .route("/news/{id}", web::put().to(update_news)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::put()
+                .uri(&format!("/news/{}", news_id))
+                .set_json(json!({ "news_title": "Hijacked" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::FORBIDDEN);
     }
 
-    #[test]
-    fn test_delete_news_not_found() {
-        let db = MockDb::new();
-        let result = db.delete_news(99);
+    #[actix_web::test]
+    async fn test_delete_news_success() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (author_id, news_id, _) = seed_article(repository.as_ref(), "author", false);
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(author_id, false))
+                .configure(configure_memory_app(repository.clone()))
+                .route("/news/{id}", web::delete().to(delete_news)),
+        )
+        .await;
+
+        let uri = format!("/news/{}", news_id);
+        let resp = call_service(&app, TestRequest::delete().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert!(NewsRepository::find(repository.as_ref(), news_id)
+            .unwrap()
+            .is_none());
+
+        // News should be gone now
+        let resp = call_service(&app, TestRequest::delete().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+    }
 
-        assert!(result.is_err());
-        assert_eq!(result.unwrap_err(), "News not found");
+    #[actix_web::test]
+    async fn test_delete_news_not_found() {
+        let app = init_service(
+            App::new()
+                .wrap_fn(signed_in(1, true))
+                .configure(configure_memory_app(Arc::new(MemoryRepository::new())))
+                .route("/news/{id}", web::delete().to(delete_news)),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::delete().uri("/news/99").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
     }
 }
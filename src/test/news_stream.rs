@@ -0,0 +1,205 @@
+#[cfg(test)]
+mod news_stream_tests {
+    use std::pin::Pin;
+    use std::sync::Arc;
+    use std::time::Duration;
+
+    use crate::events::EventBus;
+    use crate::handlers::admin::{create_news, delete_news, update_news};
+    use crate::handlers::news::stream_news;
+    use crate::models::category::NewCategory;
+    use crate::models::user::NewUser;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::{CategoryRepository, NewsRepository, UserRepository};
+    use crate::supervisor::Supervisor;
+    use crate::test::test_utils::{configure_memory_app, signed_in, test_config};
+    use crate::utils::news_stream::NewsStream;
+    use actix_web::body::MessageBody;
+    use actix_web::test::{call_service, init_service, TestRequest};
+    use actix_web::{http::header, http::StatusCode, web, App};
+    use serde_json::{json, Value};
+
+    // (id, event, data) of the next event on the stream
+    async fn next_event<B: MessageBody + Unpin>(body: &mut B) -> (String, String, Value) {
+        let frame = tokio::time::timeout(
+            Duration::from_secs(5),
+            std::future::poll_fn(|cx| Pin::new(&mut *body).poll_next(cx)),
+        )
+        .await
+        .expect("No event within 5 seconds");
+        let Some(Ok(frame)) = frame else {
+            panic!("The stream ended");
+        };
+
+        let frame = String::from_utf8(frame.to_vec()).unwrap();
+        let field = |name: &str| {
+            frame
+                .lines()
+                .find_map(|line| line.strip_prefix(&format!("{}: ", name)))
+                .unwrap_or_default()
+                .to_string()
+        };
+        let data = serde_json::from_str(&field("data")).unwrap();
+        (field("id"), field("event"), data)
+    }
+
+    #[actix_web::test]
+    async fn test_stream_filters_and_resumes() {
+        let repository = Arc::new(MemoryRepository::new());
+        let admin = UserRepository::create(
+            repository.as_ref(),
+            NewUser {
+                username: "admin".to_string(),
+                password: "hash".to_string(),
+                is_admin: true,
+            },
+        )
+        .unwrap();
+        let mut category_ids = Vec::new();
+        for name in ["Tech", "Sport"] {
+            let category = CategoryRepository::create(
+                repository.as_ref(),
+                NewCategory {
+                    name: name.to_string(),
+                    description: None,
+                },
+            )
+            .unwrap();
+            category_ids.push(category.id);
+        }
+        let (tech, sport) = (category_ids[0], category_ids[1]);
+
+        // what main does, with the task turning changes into events
+        let config = test_config();
+        let events = web::Data::new(EventBus::new());
+        let news_stream = web::Data::new(NewsStream::new(&config.news_stream));
+        events.subscribe(news_stream.clone().into_inner());
+        let news_repository: Arc<dyn NewsRepository> = repository.clone();
+        let news_repository = web::Data::from(news_repository);
+        let supervisor = Supervisor::new();
+        let task_stream = news_stream.clone();
+        supervisor.spawn("news_stream", move |shutdown| {
+            let news_stream = task_stream.clone();
+            let repository = news_repository.clone();
+            async move { news_stream.run(repository, shutdown).await }
+        });
+
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .app_data(events)
+                .app_data(news_stream)
+                .wrap_fn(signed_in(admin.id, true))
+                .route("/news/stream", web::get().to(stream_news))
+                .route("/news", web::post().to(create_news))
+                .route("/news/{id}", web::patch().to(update_news))
+                .route("/news/{id}", web::delete().to(delete_news)),
+        )
+        .await;
+
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/news/stream?category_id={}", tech))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        assert_eq!(
+            resp.headers().get(header::CONTENT_TYPE).unwrap(),
+            "text/event-stream"
+        );
+        let mut tech_stream = resp.into_body();
+
+        let mut news_ids = Vec::new();
+        for category_id in [sport, tech] {
+            let resp = call_service(
+                &app,
+                TestRequest::post()
+                    .uri("/news")
+                    .set_json(json!({
+                        "title": "Breaking",
+                        "content": "Something happened",
+                        "author_id": admin.id,
+                        "category_ids": [category_id],
+                    }))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let body: Value = actix_web::test::read_body_json(resp).await;
+            news_ids.push(body["news"]["id"].as_i64().unwrap());
+        }
+        // the sports article never shows up
+        let article = news_ids[1];
+        let (published_id, event, data) = next_event(&mut tech_stream).await;
+        assert_eq!(event, "published");
+        assert_eq!(data["news_id"], article);
+        assert_eq!(data["category_ids"], json!([tech]));
+
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&format!("/news/{}", article))
+                .set_json(json!({ "news_title": "Updated" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let (_, event, data) = next_event(&mut tech_stream).await;
+        assert_eq!(event, "updated");
+        assert_eq!(data["news"]["title"], "Updated");
+
+        // a deleted article keeps the categories of its last event
+        let resp = call_service(
+            &app,
+            TestRequest::delete()
+                .uri(&format!("/news/{}", article))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let (_, event, data) = next_event(&mut tech_stream).await;
+        assert_eq!(event, "deleted");
+        assert!(data.get("news").is_none());
+
+        // a client coming back gets what it missed
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/news/stream")
+                .insert_header(("Last-Event-ID", published_id))
+                .to_request(),
+        )
+        .await;
+        let mut resumed = resp.into_body();
+        assert_eq!(next_event(&mut resumed).await.1, "updated");
+        assert_eq!(next_event(&mut resumed).await.1, "deleted");
+
+        // and one that missed more than is kept has to start over
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/news/stream")
+                .insert_header(("Last-Event-ID", "1"))
+                .to_request(),
+        )
+        .await;
+        let mut stale = resp.into_body();
+        assert_eq!(next_event(&mut stale).await.1, "reset");
+
+        // ids the stream never sent start over too
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/news/stream")
+                .insert_header(("Last-Event-ID", u64::MAX.to_string()))
+                .to_request(),
+        )
+        .await;
+        let mut unknown = resp.into_body();
+        assert_eq!(next_event(&mut unknown).await.1, "reset");
+
+        supervisor.shutdown(Duration::from_secs(1)).await;
+    }
+}
@@ -0,0 +1,283 @@
+#[cfg(test)]
+mod oidc_tests {
+    use crate::db::establish_connection;
+    use crate::handlers::oidc::provision_user;
+    use crate::models::user::NewUser;
+    use crate::schema::{user_identities, users};
+    use crate::test::test_utils::{configure_app, test_config};
+    use crate::utils::error_response::AppError;
+    use crate::utils::oidc::{code_challenge, OidcClient, OidcConfig, OidcIdentity};
+    use actix_web::cookie::Cookie;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App, HttpResponse, HttpServer};
+    use diesel::prelude::*;
+    use jsonwebtoken::{encode, EncodingKey, Header};
+    use serde_json::json;
+    use std::collections::HashMap;
+    use std::sync::{Arc, Mutex};
+
+    const CLIENT_ID: &str = "news-api";
+    const CLIENT_SECRET: &str = "mock-idp-secret";
+
+    // what the mock provider expects on the token request
+    #[derive(Default)]
+    struct MockIdpState {
+        challenge: String,
+        nonce: String,
+        subject: String,
+        groups: Vec<String>,
+    }
+
+    // start a minimal standards-compliant provider on a random port
+    fn start_mock_idp(state: Arc<Mutex<MockIdpState>>) -> String {
+        let server = HttpServer::new(move || {
+            let state = state.clone();
+            App::new()
+                .app_data(web::Data::new(state))
+                .route(
+                    "/.well-known/openid-configuration",
+                    web::get().to(|req: actix_web::HttpRequest| async move {
+                        let issuer = format!("http://{}", req.connection_info().host());
+                        HttpResponse::Ok().json(json!({
+                            "issuer": issuer,
+                            "authorization_endpoint": format!("{}/authorize", issuer),
+                            "token_endpoint": format!("{}/token", issuer),
+                            "jwks_uri": format!("{}/jwks", issuer),
+                        }))
+                    }),
+                )
+                .route(
+                    "/token",
+                    web::post().to(
+                        |req: actix_web::HttpRequest,
+                         state: web::Data<Arc<Mutex<MockIdpState>>>,
+                         form: web::Form<HashMap<String, String>>| async move {
+                            let state = state.lock().unwrap();
+                            let verifier_ok = form.get("code_verifier").is_some_and(|verifier| {
+                                code_challenge(verifier) == state.challenge
+                            });
+                            if form.get("code").map(String::as_str) != Some("mock-code")
+                                || !verifier_ok
+                            {
+                                return HttpResponse::BadRequest()
+                                    .json(json!({ "error": "invalid_grant" }));
+                            }
+
+                            let issuer = format!("http://{}", req.connection_info().host());
+                            let id_token = encode(
+                                &Header::default(),
+                                &json!({
+                                    "iss": issuer,
+                                    "aud": CLIENT_ID,
+                                    "sub": state.subject,
+                                    "preferred_username": format!("sso_{}", &state.subject[..8]),
+                                    "groups": state.groups,
+                                    "nonce": state.nonce,
+                                    "iat": chrono::Utc::now().timestamp(),
+                                    "exp": chrono::Utc::now().timestamp() + 300,
+                                }),
+                                &EncodingKey::from_secret(CLIENT_SECRET.as_bytes()),
+                            )
+                            .unwrap();
+
+                            HttpResponse::Ok().json(json!({
+                                "access_token": "mock-access-token",
+                                "token_type": "Bearer",
+                                "id_token": id_token,
+                            }))
+                        },
+                    ),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        format!("http://{}", addr)
+    }
+
+    fn oidc_config(issuer_url: String) -> OidcConfig {
+        OidcConfig {
+            issuer_url,
+            client_id: CLIENT_ID.to_string(),
+            client_secret: Some(CLIENT_SECRET.to_string()),
+            redirect_url: "http://localhost:8080/auth/oidc/callback".to_string(),
+            scopes: "openid profile".to_string(),
+            groups_claim: "groups".to_string(),
+            admin_groups: vec!["news-admins".to_string()],
+            allowed_groups: vec!["staff".to_string()],
+        }
+    }
+
+    #[test]
+    fn test_code_challenge_matches_rfc7636() {
+        assert_eq!(
+            code_challenge("dBjftJeZ4CVP-mB92K27uhbUJU1p1r_wW1gFWFOEjXk"),
+            "E9Melhoa2OwvFrEMTJguCHaoeK1t8URWbuGJSstw-cM"
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_sso_login_provisions_user_and_maps_groups() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let idp_state = Arc::new(Mutex::new(MockIdpState {
+            subject: uuid::Uuid::new_v4().simple().to_string(),
+            groups: vec!["staff".to_string(), "news-admins".to_string()],
+            ..Default::default()
+        }));
+        let issuer = start_mock_idp(idp_state.clone());
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(OidcClient::new(
+                    oidc_config(issuer),
+                    config.jwt.secret.clone(),
+                )))
+                .configure(configure_app(pool.clone(), config.clone())),
+        )
+        .await;
+
+        // log in twice, the second time without the admin group
+        for expect_admin in [true, false] {
+            let login_resp = call_service(
+                &app,
+                TestRequest::get().uri("/auth/oidc/login").to_request(),
+            )
+            .await;
+            assert_eq!(login_resp.status(), StatusCode::FOUND);
+
+            let location = reqwest::Url::parse(
+                login_resp
+                    .headers()
+                    .get("location")
+                    .unwrap()
+                    .to_str()
+                    .unwrap(),
+            )
+            .unwrap();
+            let params: HashMap<_, _> = location.query_pairs().into_owned().collect();
+            assert_eq!(params["code_challenge_method"], "S256");
+            assert_eq!(params["client_id"], CLIENT_ID);
+
+            // the user authenticates at the provider
+            {
+                let mut idp = idp_state.lock().unwrap();
+                idp.challenge = params["code_challenge"].clone();
+                idp.nonce = params["nonce"].clone();
+                if !expect_admin {
+                    idp.groups = vec!["staff".to_string()];
+                }
+            }
+
+            let login_cookie = login_resp
+                .response()
+                .cookies()
+                .find(|cookie| cookie.name() == "oidc_login")
+                .unwrap()
+                .into_owned();
+            let callback_resp = call_service(
+                &app,
+                TestRequest::get()
+                    .uri(&format!(
+                        "/auth/oidc/callback?code=mock-code&state={}",
+                        params["state"]
+                    ))
+                    .cookie(Cookie::new("oidc_login", login_cookie.value().to_string()))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(callback_resp.status(), StatusCode::OK);
+
+            let body: serde_json::Value = read_body_json(callback_resp).await;
+            assert!(body.get("token").is_some());
+            assert_eq!(body["is_admin"], expect_admin);
+        }
+
+        // exactly one user was provisioned for the identity
+        let subject = idp_state.lock().unwrap().subject.clone();
+        let linked = user_identities::table
+            .inner_join(users::table)
+            .filter(user_identities::subject.eq(&subject))
+            .select(users::is_admin)
+            .load::<bool>(&mut pool.get().unwrap())
+            .unwrap();
+        assert_eq!(linked, vec![false]);
+    }
+
+    #[test]
+    fn test_sso_user_never_takes_a_local_account() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let conn = &mut pool.get().unwrap();
+        let username = format!("local_{}", &uuid::Uuid::new_v4().simple().to_string()[..8]);
+        diesel::insert_into(users::table)
+            .values(&NewUser {
+                username: username.clone(),
+                password: "hash".to_string(),
+                is_admin: false,
+            })
+            .execute(conn)
+            .unwrap();
+
+        let identity = OidcIdentity {
+            issuer: "http://idp.example".to_string(),
+            subject: uuid::Uuid::new_v4().simple().to_string(),
+            username: username.clone(),
+            groups: vec![],
+        };
+        let provisioned = provision_user(conn, &identity, false);
+        assert!(matches!(provisioned, Err(AppError::ConflictError(_))));
+        let linked = user_identities::table
+            .filter(user_identities::subject.eq(&identity.subject))
+            .count()
+            .get_result::<i64>(conn)
+            .unwrap();
+        assert_eq!(linked, 0);
+
+        diesel::delete(users::table.filter(users::username.eq(&username)))
+            .execute(conn)
+            .unwrap();
+    }
+
+    #[actix_web::test]
+    async fn test_sso_callback_rejects_forged_state() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let issuer = start_mock_idp(Arc::new(Mutex::new(MockIdpState::default())));
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(OidcClient::new(
+                    oidc_config(issuer),
+                    config.jwt.secret.clone(),
+                )))
+                .configure(configure_app(pool.clone(), config.clone())),
+        )
+        .await;
+
+        let login_resp = call_service(
+            &app,
+            TestRequest::get().uri("/auth/oidc/login").to_request(),
+        )
+        .await;
+        let login_cookie = login_resp
+            .response()
+            .cookies()
+            .find(|cookie| cookie.name() == "oidc_login")
+            .unwrap()
+            .into_owned();
+
+        let callback_resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/auth/oidc/callback?code=mock-code&state=forged")
+                .cookie(Cookie::new("oidc_login", login_cookie.value().to_string()))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(callback_resp.status(), StatusCode::UNAUTHORIZED);
+    }
+}
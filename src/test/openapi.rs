@@ -0,0 +1,77 @@
+#[cfg(test)]
+mod openapi_tests {
+    use std::collections::BTreeSet;
+
+    use crate::db::establish_connection;
+    use crate::openapi::ApiDoc;
+    use crate::routes::route_table;
+    use crate::test::test_utils::{configure_app, test_config};
+    use actix_web::test::{call_service, init_service, read_body, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, App};
+    use utoipa::OpenApi;
+
+    // (method, path) for every route the app registers, the optional ones included
+    fn wired_routes() -> BTreeSet<(String, String)> {
+        let mut config = test_config();
+        config.metrics.enabled = true;
+        config.graphql.playground = true;
+        route_table(&config)
+            .wired()
+            .map(|(method, path)| (method.as_str().to_lowercase(), path.to_string()))
+            .collect()
+    }
+
+    fn documented_routes() -> BTreeSet<(String, String)> {
+        let mut routes = BTreeSet::new();
+        for (path, item) in ApiDoc::openapi().paths.paths {
+            let operations = [
+                ("get", &item.get),
+                ("post", &item.post),
+                ("put", &item.put),
+                ("patch", &item.patch),
+                ("delete", &item.delete),
+            ];
+            for (method, operation) in operations {
+                if operation.is_some() {
+                    routes.insert((method.to_string(), path.clone()));
+                }
+            }
+        }
+        routes
+    }
+
+    #[test]
+    fn test_spec_matches_routes() {
+        let wired = wired_routes();
+        let documented = documented_routes();
+        assert!(wired.len() > 10, "failed to read the routes: {:?}", wired);
+
+        let undocumented: Vec<_> = wired.difference(&documented).collect();
+        let stale: Vec<_> = documented.difference(&wired).collect();
+        assert!(
+            undocumented.is_empty() && stale.is_empty(),
+            "routes missing from the spec: {:?}, spec paths without a route: {:?}",
+            undocumented,
+            stale
+        );
+    }
+
+    #[actix_web::test]
+    async fn test_spec_and_docs_are_served() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let app = init_service(App::new().configure(configure_app(pool, config))).await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/openapi.json").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let spec: serde_json::Value = read_body_json(resp).await;
+        assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+        assert!(spec["components"]["schemas"]["NewsWithCategories"].is_object());
+        assert!(spec["components"]["securitySchemes"]["bearer"].is_object());
+
+        let resp = call_service(&app, TestRequest::get().uri("/docs/").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let page = String::from_utf8(read_body(resp).await.to_vec()).unwrap();
+        assert!(page.contains("swagger-ui"));
+    }
+}
@@ -0,0 +1,55 @@
+#[cfg(test)]
+mod password_tests {
+    use crate::utils::password::{hash_password, verify_password, PasswordPolicy};
+    use argon2::password_hash::{rand_core::OsRng, PasswordHasher, SaltString};
+    use argon2::{Algorithm, Argon2, Params, Version};
+
+    #[test]
+    fn test_argon2id_round_trip() {
+        let hash = hash_password("correct horse battery").unwrap();
+        assert!(hash.starts_with("$argon2id$"));
+
+        let verification = verify_password("correct horse battery", &hash).unwrap();
+        assert!(verification.valid);
+        assert!(!verification.needs_rehash);
+
+        assert!(!verify_password("wrong horse", &hash).unwrap().valid);
+    }
+
+    #[test]
+    fn test_legacy_bcrypt_needs_rehash() {
+        let hash = bcrypt::hash("legacy password", 4).unwrap();
+
+        let verification = verify_password("legacy password", &hash).unwrap();
+        assert!(verification.valid);
+        assert!(verification.needs_rehash);
+    }
+
+    #[test]
+    fn test_outdated_argon2_params_need_rehash() {
+        let weak = Argon2::new(
+            Algorithm::Argon2id,
+            Version::V0x13,
+            Params::new(8 * 1024, 1, 1, None).unwrap(),
+        );
+        let hash = weak
+            .hash_password(b"old params", &SaltString::generate(&mut OsRng))
+            .unwrap()
+            .to_string();
+
+        let verification = verify_password("old params", &hash).unwrap();
+        assert!(verification.valid);
+        assert!(verification.needs_rehash);
+    }
+
+    #[test]
+    fn test_password_policy() {
+        let policy = PasswordPolicy::new(8, 64, "# comment\nhunter22\n");
+
+        assert!(policy.check("alice", "short").is_err());
+        assert!(policy.check("alice", &"x".repeat(65)).is_err());
+        assert!(policy.check("alice_smith", "ALICE_SMITH").is_err());
+        assert!(policy.check("alice", "Hunter22").is_err());
+        assert!(policy.check("alice", "a long unique passphrase").is_ok());
+    }
+}
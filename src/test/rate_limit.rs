@@ -0,0 +1,173 @@
+#[cfg(test)]
+mod rate_limit_tests {
+    use crate::db::establish_connection;
+    use crate::middleware::rate_limit::{
+        InMemoryStore, PostgresStore, RateLimitKey, RateLimitMiddleware, RateLimitPolicy,
+        RateLimitStore,
+    };
+    use crate::test::test_utils::{configure_app, test_config};
+    use actix_web::test::{call_service, init_service, try_call_service, TestRequest};
+    use actix_web::{http::StatusCode, web, App, HttpResponse};
+
+    #[test]
+    fn test_bucket_drains_and_denies() {
+        let store = InMemoryStore::new();
+        let policy = RateLimitPolicy::new("test", 2, 0.5, RateLimitKey::Ip);
+
+        let first = store.acquire("client", &policy).unwrap();
+        assert!(first.allowed);
+        assert_eq!(first.remaining, 1);
+
+        let second = store.acquire("client", &policy).unwrap();
+        assert!(second.allowed);
+        assert_eq!(second.remaining, 0);
+
+        let third = store.acquire("client", &policy).unwrap();
+        assert!(!third.allowed);
+        assert_eq!(third.retry_after, 2);
+
+        // other clients have their own bucket
+        assert!(store.acquire("other", &policy).unwrap().allowed);
+    }
+
+    #[test]
+    fn test_sweep_keeps_partly_drained_buckets_of_every_scope() {
+        let store = InMemoryStore::sweeping_after(1);
+        let user = RateLimitPolicy::new("user", 60, 0.001, RateLimitKey::User);
+        let auth = RateLimitPolicy::new("auth", 10, 0.001, RateLimitKey::Ip);
+
+        // drained below the capacity of `user`, but above the one of `auth`
+        for _ in 0..20 {
+            store.acquire("user:1", &user).unwrap();
+        }
+        store.acquire("auth:ip:1", &auth).unwrap();
+
+        // a sweep during an `auth` request still measures the `user` bucket by its own capacity
+        assert_eq!(store.acquire("auth:ip:1", &auth).unwrap().remaining, 8);
+        assert_eq!(store.acquire("user:1", &user).unwrap().remaining, 39);
+    }
+
+    #[test]
+    fn test_postgres_bucket_is_shared() {
+        let store = PostgresStore::new(establish_connection(&test_config().database));
+        let policy = RateLimitPolicy::new("test", 1, 0.001, RateLimitKey::Ip);
+        let key = format!("test:{}", uuid::Uuid::new_v4());
+
+        assert!(store.acquire(&key, &policy).unwrap().allowed);
+
+        let denied = store.acquire(&key, &policy).unwrap();
+        assert!(!denied.allowed);
+        assert_eq!(denied.remaining, 0);
+    }
+
+    #[actix_web::test]
+    async fn test_middleware_returns_429_with_headers() {
+        let app = init_service(
+            App::new().service(
+                web::scope("/limited")
+                    .wrap(RateLimitMiddleware::new(RateLimitPolicy::new(
+                        "limited",
+                        1,
+                        0.1,
+                        RateLimitKey::Ip,
+                    )))
+                    .route("", web::get().to(HttpResponse::Ok)),
+            ),
+        )
+        .await;
+
+        let peer = "10.0.0.1:1234".parse().unwrap();
+        let ok_resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/limited")
+                .peer_addr(peer)
+                .to_request(),
+        )
+        .await;
+        assert_eq!(ok_resp.status(), StatusCode::OK);
+        assert_eq!(ok_resp.headers().get("ratelimit-limit").unwrap(), "1");
+        assert_eq!(ok_resp.headers().get("ratelimit-remaining").unwrap(), "0");
+
+        // the rejection is an error which actix renders into the 429 response
+        let limited_resp = try_call_service(
+            &app,
+            TestRequest::get()
+                .uri("/limited")
+                .peer_addr(peer)
+                .to_request(),
+        )
+        .await
+        .unwrap_err()
+        .error_response();
+        assert_eq!(limited_resp.status(), StatusCode::TOO_MANY_REQUESTS);
+        assert_eq!(limited_resp.headers().get("retry-after").unwrap(), "10");
+        assert_eq!(
+            limited_resp.headers().get("ratelimit-remaining").unwrap(),
+            "0"
+        );
+
+        // a different client is not affected
+        let other_resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/limited")
+                .peer_addr("10.0.0.2:1234".parse().unwrap())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(other_resp.status(), StatusCode::OK);
+    }
+
+    #[actix_web::test]
+    async fn test_failed_credentials_are_limited_by_ip() {
+        let config = test_config();
+        let capacity = config.rate_limit.auth.capacity;
+        let pool = establish_connection(&config.database);
+        let app = init_service(App::new().configure(configure_app(pool, config))).await;
+        let bogus_key = |peer: &str| {
+            TestRequest::get()
+                .uri("/api/v1/news")
+                .insert_header(("X-Api-Key", "nk_bogus"))
+                .peer_addr(peer.parse().unwrap())
+                .to_request()
+        };
+
+        for _ in 0..capacity {
+            let rejected = try_call_service(&app, bogus_key("10.0.1.1:1234"))
+                .await
+                .unwrap_err();
+            assert_eq!(
+                rejected.as_response_error().status_code(),
+                StatusCode::UNAUTHORIZED
+            );
+        }
+
+        // no more lookups for that client until its bucket refills
+        let limited = try_call_service(&app, bogus_key("10.0.1.1:1234"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            limited.as_response_error().status_code(),
+            StatusCode::TOO_MANY_REQUESTS
+        );
+        let other = try_call_service(&app, bogus_key("10.0.1.2:1234"))
+            .await
+            .unwrap_err();
+        assert_eq!(
+            other.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // anonymous reads don't count against it
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/api/v1/news")
+                .peer_addr("10.0.1.1:1234".parse().unwrap())
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+    }
+}
@@ -0,0 +1,71 @@
+#[cfg(test)]
+mod request_tracing_tests {
+    use crate::db::establish_connection;
+    use crate::middleware::request_tracing::{TracingMiddleware, REQUEST_ID_HEADER};
+    use crate::test::test_utils::{configure_app, test_config};
+    use actix_web::test::{call_service, init_service, try_call_service, TestRequest};
+    use actix_web::{http::StatusCode, App};
+
+    #[actix_web::test]
+    async fn test_request_id_generated_and_propagated() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let app = init_service(
+            App::new()
+                .wrap(TracingMiddleware)
+                .configure(configure_app(pool, config)),
+        )
+        .await;
+
+        let resp = call_service(&app, TestRequest::get().uri("/healthz").to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let generated = resp.headers().get(REQUEST_ID_HEADER).unwrap();
+        assert_eq!(generated.len(), 36);
+
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/healthz")
+                .insert_header(("X-Request-Id", "abc-123"))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-123");
+
+        // ids with spaces or control characters are replaced
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri("/healthz")
+                .insert_header(("X-Request-Id", "not valid"))
+                .to_request(),
+        )
+        .await;
+        assert_ne!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "not valid");
+    }
+
+    #[actix_web::test]
+    async fn test_request_id_on_middleware_errors() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let app = init_service(
+            App::new()
+                .wrap(TracingMiddleware)
+                .configure(configure_app(pool, config)),
+        )
+        .await;
+
+        let err = try_call_service(
+            &app,
+            TestRequest::get()
+                .uri("/me/sessions")
+                .insert_header(("X-Request-Id", "abc-456"))
+                .to_request(),
+        )
+        .await
+        .unwrap_err();
+        let resp = err.error_response();
+        assert_eq!(resp.status(), StatusCode::UNAUTHORIZED);
+        assert_eq!(resp.headers().get(REQUEST_ID_HEADER).unwrap(), "abc-456");
+    }
+}
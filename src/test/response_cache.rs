@@ -0,0 +1,174 @@
+#[cfg(test)]
+mod response_cache_tests {
+    use std::sync::Arc;
+
+    use crate::config::ResponseCacheConfig;
+    use crate::events::ContentEvent;
+    use crate::handlers::admin::{create_category, delete_category, update_category, update_news};
+    use crate::handlers::categories::list_categories;
+    use crate::handlers::news::get_news_detail;
+    use crate::handlers::trash::restore_category;
+    use crate::models::news::NewsChangeset;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::NewsRepository;
+    use crate::test::test_utils::{
+        configure_events, configure_memory_app, seed_article, signed_in, test_config,
+    };
+    use crate::utils::http_cache::Validators;
+    use crate::utils::metrics::RESPONSE_CACHE_LOOKUPS_TOTAL;
+    use crate::utils::precondition;
+    use crate::utils::response_cache::{CacheKey, CachedResponse, ResponseCache};
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    fn enabled(ttl_secs: u64) -> ResponseCacheConfig {
+        ResponseCacheConfig {
+            enabled: true,
+            capacity: 10,
+            ttl_secs,
+        }
+    }
+
+    fn article(title: &str) -> CachedResponse {
+        let validators = Validators {
+            etag: precondition::etag(Utc::now().naive_utc()),
+            last_modified: None,
+        };
+        CachedResponse::json(validators, None, &json!({ "title": title }))
+    }
+
+    #[actix_web::test]
+    async fn test_writes_purge_what_they_change() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (admin_id, news_id, category_id) = seed_article(repository.as_ref(), "admin", true);
+        let mut config = test_config();
+        config.response_cache = enabled(60);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .configure(configure_events(&config))
+                .wrap_fn(signed_in(admin_id, true))
+                .route("/news/{id}", web::get().to(get_news_detail))
+                .route("/news/{id}", web::patch().to(update_news))
+                .route("/categories", web::get().to(list_categories))
+                .route("/categories", web::post().to(create_category))
+                .route("/categories/{id}", web::patch().to(update_category))
+                .route("/categories/{id}", web::delete().to(delete_category))
+                .route(
+                    "/trash/categories/{id}/restore",
+                    web::post().to(restore_category),
+                ),
+        )
+        .await;
+        let uri = format!("/news/{}", news_id);
+        let hits = RESPONSE_CACHE_LOOKUPS_TOTAL.with_label_values(&["news", "hit"]);
+        let hits_before = hits.get();
+
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+
+        // changes that bypass the handlers aren't seen until the entry expires
+        NewsRepository::update(
+            repository.as_ref(),
+            news_id,
+            NewsChangeset {
+                title: Some("Unannounced".to_string()),
+                content: None,
+                updated_at: Utc::now().naive_utc(),
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["title"], "Breaking");
+        assert!(hits.get() > hits_before);
+
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&uri)
+                .set_json(json!({ "news_title": "Updated" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["title"], "Updated");
+
+        // renaming a category reaches the articles filed under it
+        let resp = call_service(
+            &app,
+            TestRequest::patch()
+                .uri(&format!("/categories/{}", category_id))
+                .set_json(json!({ "name": "Technology" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["categories"][0]["name"], "Technology");
+
+        let resp = call_service(&app, TestRequest::get().uri("/categories").to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/categories")
+                .set_json(json!({ "name": "Science" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let resp = call_service(&app, TestRequest::get().uri("/categories").to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body.as_array().unwrap().len(), 2);
+
+        // an article cached while its category was in the trash isn't tagged with it
+        let category = format!("/categories/{}", category_id);
+        let resp = call_service(&app, TestRequest::delete().uri(&category).to_request()).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["categories"].as_array().unwrap().len(), 0);
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri(&format!("/trash/categories/{}/restore", category_id))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, TestRequest::get().uri(&uri).to_request()).await;
+        let body: Value = read_body_json(resp).await;
+        assert_eq!(body["categories"][0]["id"], category_id);
+    }
+
+    #[test]
+    fn test_stale_reads_are_not_kept() {
+        let cache = ResponseCache::new(&enabled(60));
+
+        // an article read before it was changed
+        let miss = cache.get(CacheKey::News(1)).unwrap_err();
+        cache.purge(&ContentEvent::NewsUpdated { id: 1 }.keys());
+        cache.insert(miss, article("Old"), vec!["news-1".to_string()]);
+        let miss = cache.get(CacheKey::News(1)).unwrap_err();
+
+        cache.insert(miss, article("New"), vec!["news-1".to_string()]);
+        assert!(cache.get(CacheKey::News(1)).is_ok());
+        // other articles are left alone
+        cache.purge(&ContentEvent::NewsDeleted { id: 2 }.keys());
+        assert!(cache.get(CacheKey::News(1)).is_ok());
+
+        let expiring = ResponseCache::new(&enabled(0));
+        let miss = expiring.get(CacheKey::NewsList).unwrap_err();
+        expiring.insert(miss, article("Gone"), vec!["news-list".to_string()]);
+        assert!(expiring.get(CacheKey::NewsList).is_err());
+    }
+}
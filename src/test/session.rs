@@ -0,0 +1,124 @@
+#[cfg(test)]
+mod session_tests {
+    use crate::db::establish_connection;
+    use crate::models::user::NewUser;
+    use crate::schema::users::dsl::*;
+    use crate::test::test_utils::{configure_app, test_config};
+    use crate::utils::password::hash_password;
+    use crate::utils::session::{issue_session_token, SessionInfo};
+    use actix_web::test::{call_service, init_service, read_body_json, try_call_service};
+    use actix_web::{http::StatusCode, test::TestRequest, App};
+    use diesel::prelude::*;
+    use serde_json::json;
+
+    #[actix_web::test]
+    async fn test_list_and_revoke_sessions() {
+        let config = test_config();
+        let pool = establish_connection(&config.database);
+        let conn = &mut pool.get().unwrap();
+
+        diesel::delete(users.filter(username.eq("session_user")))
+            .execute(conn)
+            .unwrap();
+        let user_id = diesel::insert_into(users)
+            .values(&NewUser {
+                username: "session_user".to_string(),
+                password: hash_password("session_password").unwrap(),
+                is_admin: false,
+            })
+            .returning(id)
+            .get_result::<i32>(conn)
+            .unwrap();
+
+        let app =
+            init_service(App::new().configure(configure_app(pool.clone(), config.clone()))).await;
+
+        // log in from two devices
+        let mut tokens = Vec::new();
+        for device in ["Office tablet", "Phone"] {
+            let login_req = TestRequest::post()
+                .uri("/auth/login")
+                .insert_header(("User-Agent", "session-test"))
+                .set_json(json!({
+                    "username": "session_user",
+                    "password": "session_password",
+                    "device_label": device
+                }))
+                .to_request();
+            let login_body: serde_json::Value =
+                read_body_json(call_service(&app, login_req).await).await;
+            tokens.push(login_body["token"].as_str().unwrap().to_string());
+        }
+
+        let list_req = TestRequest::get()
+            .uri("/me/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", tokens[1])))
+            .to_request();
+        let list_resp = call_service(&app, list_req).await;
+        assert_eq!(list_resp.status(), StatusCode::OK);
+
+        let sessions: Vec<serde_json::Value> = read_body_json(list_resp).await;
+        assert_eq!(sessions.len(), 2);
+        let tablet = sessions
+            .iter()
+            .find(|session| session["device_label"] == "Office tablet")
+            .unwrap();
+        assert_eq!(tablet["current"], false);
+        assert_eq!(tablet["user_agent"], "session-test");
+
+        // sign out the tablet from the phone
+        let revoke_req = TestRequest::delete()
+            .uri(&format!("/me/sessions/{}", tablet["id"].as_str().unwrap()))
+            .insert_header(("Authorization", format!("Bearer {}", tokens[1])))
+            .to_request();
+        assert_eq!(
+            call_service(&app, revoke_req).await.status(),
+            StatusCode::OK
+        );
+
+        let revoked_req = TestRequest::get()
+            .uri("/me/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", tokens[0])))
+            .to_request();
+        let revoked_err = try_call_service(&app, revoked_req).await.unwrap_err();
+        assert_eq!(
+            revoked_err.as_response_error().status_code(),
+            StatusCode::UNAUTHORIZED
+        );
+
+        // an admin signs the user out everywhere
+        diesel::delete(users.filter(username.eq("session_admin")))
+            .execute(conn)
+            .unwrap();
+        let admin_id = diesel::insert_into(users)
+            .values(&NewUser {
+                username: "session_admin".to_string(),
+                password: "unused".to_string(),
+                is_admin: true,
+            })
+            .returning(id)
+            .get_result::<i32>(conn)
+            .unwrap();
+        let admin_token = issue_session_token(
+            conn,
+            &config.jwt,
+            admin_id,
+            "session_admin",
+            true,
+            SessionInfo::default(),
+        )
+        .unwrap();
+
+        let kill_req = TestRequest::delete()
+            .uri(&format!("/admin/users/{}/sessions", user_id))
+            .insert_header(("Authorization", format!("Bearer {}", admin_token)))
+            .to_request();
+        assert_eq!(call_service(&app, kill_req).await.status(), StatusCode::OK);
+
+        let killed_req = TestRequest::get()
+            .uri("/me/sessions")
+            .insert_header(("Authorization", format!("Bearer {}", tokens[1])))
+            .to_request();
+        assert!(try_call_service(&app, killed_req).await.is_err());
+    }
+}
@@ -0,0 +1,84 @@
+#[cfg(test)]
+mod supervisor_tests {
+    use std::cell::Cell;
+    use std::rc::Rc;
+    use std::time::Duration;
+
+    use crate::supervisor::{periodic, Supervisor, TaskResult, TaskStatus};
+    use actix_web::rt::time::sleep;
+
+    #[actix_web::test]
+    async fn test_failed_task_is_restarted() {
+        let supervisor = Supervisor::new();
+        let runs = Rc::new(Cell::new(0));
+
+        let counter = runs.clone();
+        supervisor.spawn("flaky", move |mut shutdown| {
+            let counter = counter.clone();
+            async move {
+                counter.set(counter.get() + 1);
+                if counter.get() == 1 {
+                    return Err("boom".into());
+                }
+                if counter.get() == 2 {
+                    panic!("kaboom");
+                }
+                shutdown.requested().await;
+                TaskResult::Ok(())
+            }
+        });
+
+        sleep(Duration::from_millis(100)).await;
+        assert_eq!(
+            supervisor.statuses()["flaky"],
+            TaskStatus::Restarting {
+                failures: 1,
+                last_error: "boom".to_string()
+            }
+        );
+
+        // 1s after the error, then 2s after the panic
+        sleep(Duration::from_millis(3200)).await;
+        assert_eq!(runs.get(), 3);
+        assert_eq!(supervisor.statuses()["flaky"], TaskStatus::Running);
+
+        assert!(supervisor.shutdown(Duration::from_secs(1)).await);
+        assert_eq!(supervisor.statuses()["flaky"], TaskStatus::Stopped);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_stops_periodic_tasks() {
+        let supervisor = Supervisor::new();
+        let runs = Rc::new(Cell::new(0));
+
+        let counter = runs.clone();
+        supervisor.spawn("ticker", move |shutdown| {
+            let counter = counter.clone();
+            periodic(shutdown, Duration::from_millis(20), move || {
+                counter.set(counter.get() + 1);
+                async { Ok(()) }
+            })
+        });
+
+        sleep(Duration::from_millis(110)).await;
+        assert!(supervisor.shutdown(Duration::from_secs(1)).await);
+        assert_eq!(supervisor.statuses()["ticker"], TaskStatus::Stopped);
+
+        let after_shutdown = runs.get();
+        assert!(after_shutdown >= 3);
+        sleep(Duration::from_millis(60)).await;
+        assert_eq!(runs.get(), after_shutdown);
+    }
+
+    #[actix_web::test]
+    async fn test_shutdown_times_out_on_stuck_task() {
+        let supervisor = Supervisor::new();
+        supervisor.spawn("stuck", |_| async {
+            sleep(Duration::from_secs(60)).await;
+            TaskResult::Ok(())
+        });
+
+        sleep(Duration::from_millis(10)).await;
+        assert!(!supervisor.shutdown(Duration::from_millis(50)).await);
+    }
+}
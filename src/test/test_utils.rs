@@ -1,12 +1,140 @@
-use actix_web::{web, App};
+use actix_web::dev::{Service, ServiceRequest, ServiceResponse};
+use actix_web::{web, HttpMessage};
 use diesel::r2d2::ConnectionManager;
 use diesel::{Connection, PgConnection, RunQueryDsl};
 use dotenvy::dotenv;
 use std::env;
+use std::sync::Arc;
 use uuid::Uuid;
 
+use crate::config::Config;
+use crate::events::EventBus;
+use crate::models::category::NewCategory;
+use crate::models::news::NewNews;
+use crate::models::user::NewUser;
+use crate::repository::{self, memory::MemoryRepository, postgres::PgRepository};
+use crate::repository::{CategoryRepository, NewsRepository, UserRepository};
+use crate::routes::configure_routes;
+use crate::supervisor::Supervisor;
+use crate::utils::edit_rooms::EditRooms;
+use crate::utils::jwt::Claims;
+use crate::utils::news_stream::NewsStream;
+use crate::utils::password::PasswordPolicy;
+use crate::utils::response_cache::ResponseCache;
+
 pub type DBPool = r2d2::Pool<ConnectionManager<PgConnection>>;
 
+/// Loads the app config the same way `main` does
+pub fn test_config() -> Config {
+    dotenv().ok();
+    Config::load().unwrap_or_else(|e| panic!("{}", e))
+}
+
+/// Registers the event bus with a response cache, a news stream and the edit rooms subscribed to
+/// it, like `main` does. Nothing turns the changes into stream events unless the test runs the stream.
+pub fn configure_events(config: &Config) -> impl FnOnce(&mut web::ServiceConfig) {
+    let events = web::Data::new(EventBus::new());
+    let response_cache = web::Data::new(ResponseCache::new(&config.response_cache));
+    events.subscribe(response_cache.clone().into_inner());
+    let news_stream = web::Data::new(NewsStream::new(&config.news_stream));
+    events.subscribe(news_stream.clone().into_inner());
+    let edit_rooms = web::Data::new(EditRooms::new(&config.edit_locks));
+    events.subscribe(edit_rooms.clone().into_inner());
+    move |cfg| {
+        cfg.app_data(events)
+            .app_data(response_cache)
+            .app_data(news_stream)
+            .app_data(edit_rooms);
+    }
+}
+
+/// Registers the shared app data and the routes for a test app
+pub fn configure_app(pool: DBPool, config: Config) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        let password_policy =
+            PasswordPolicy::from_config(&config.password).expect("Invalid password policy");
+        repository::configure(Arc::new(PgRepository::new(pool.clone())))(cfg);
+        configure_events(&config)(cfg);
+        cfg.app_data(web::Data::new(pool))
+            .app_data(web::Data::new(password_policy))
+            .app_data(web::Data::new(Supervisor::new()));
+        configure_routes(cfg, &config);
+        cfg.app_data(web::Data::new(config));
+    }
+}
+
+/// Registers an in-memory repository, for handler tests that don't need Postgres
+pub fn configure_memory_app(
+    repository: Arc<MemoryRepository>,
+) -> impl FnOnce(&mut web::ServiceConfig) {
+    move |cfg| {
+        let config = test_config();
+        let password_policy =
+            PasswordPolicy::from_config(&config.password).expect("Invalid password policy");
+        repository::configure(repository)(cfg);
+        configure_events(&config)(cfg);
+        cfg.app_data(web::Data::new(password_policy))
+            .app_data(web::Data::new(config));
+    }
+}
+
+/// A user with an article filed under a category, returns (user id, news id, category id)
+pub fn seed_article<R: NewsRepository + CategoryRepository + UserRepository>(
+    repository: &R,
+    username: &str,
+    is_admin: bool,
+) -> (i32, i32, i32) {
+    let user = UserRepository::create(
+        repository,
+        NewUser {
+            username: username.to_string(),
+            password: "hash".to_string(),
+            is_admin,
+        },
+    )
+    .unwrap();
+    let tech = CategoryRepository::create(
+        repository,
+        NewCategory {
+            name: "Tech".to_string(),
+            description: None,
+        },
+    )
+    .unwrap();
+    let news = NewsRepository::create(
+        repository,
+        NewNews {
+            title: "Breaking".to_string(),
+            content: "Something happened".to_string(),
+            author_id: user.id,
+        },
+        &[tech.id],
+    )
+    .unwrap();
+
+    (user.id, news.id, tech.id)
+}
+
+/// Stands in for the auth middleware, every request is made by this user
+pub fn signed_in<S, B>(
+    user_id: i32,
+    is_admin: bool,
+) -> impl Fn(ServiceRequest, &S) -> S::Future + Clone + 'static
+where
+    S: Service<ServiceRequest, Response = ServiceResponse<B>, Error = actix_web::Error>,
+{
+    move |req, service| {
+        req.extensions_mut().insert(Claims {
+            sub: user_id,
+            username: format!("user{}", user_id),
+            is_admin,
+            exp: usize::MAX,
+            sid: None,
+        });
+        service.call(req)
+    }
+}
+
 /// Creates a new test database and returns its connection URL
 pub fn create_test_database() -> String {
     dotenv().ok();
@@ -41,14 +169,15 @@ pub fn get_test_pool() -> (DBPool, String) {
 
 /// Cleans up the test database
 pub fn cleanup_test_database(database_url: &str) {
-    let postgres_url = database_url.replace(database_url.split('/').last().unwrap(), "postgres");
+    let postgres_url =
+        database_url.replace(database_url.split('/').next_back().unwrap(), "postgres");
 
     let mut conn =
         PgConnection::establish(&postgres_url).expect("Failed to connect to postgres database");
 
     let db_name = database_url
         .split('/')
-        .last()
+        .next_back()
         .expect("Invalid database URL");
 
     // Safety check
@@ -0,0 +1,212 @@
+#[cfg(test)]
+mod trash_tests {
+    use std::sync::Arc;
+
+    use crate::cli::maintenance::purge_trash;
+    use crate::db::run_migrations;
+    use crate::handlers::admin::{create_category, delete_category, delete_news};
+    use crate::handlers::news::{get_news_detail, list_news};
+    use crate::handlers::trash::{
+        list_trashed_categories, list_trashed_news, purge_news, restore_category, restore_news,
+    };
+    use crate::models::category::NewCategory;
+    use crate::models::news::NewNews;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::postgres::PgRepository;
+    use crate::repository::{CategoryRepository, NewsFilter, NewsRepository};
+    use crate::schema::news;
+    use crate::test::test_utils::{
+        cleanup_test_database, configure_memory_app, get_test_pool, seed_article, signed_in,
+    };
+    use crate::utils::error_response::AppError;
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::{http::StatusCode, web, App};
+    use chrono::{Duration, Utc};
+    use diesel::prelude::*;
+    use serde_json::{json, Value};
+
+    #[actix_web::test]
+    async fn test_delete_restore_and_purge() {
+        let repository = Arc::new(MemoryRepository::new());
+        let (admin_id, news_id, tech_id) = seed_article(repository.as_ref(), "admin", true);
+        let app = init_service(
+            App::new()
+                .configure(configure_memory_app(repository.clone()))
+                .wrap_fn(signed_in(admin_id, true))
+                .route("/news", web::get().to(list_news))
+                .route("/news/{id}", web::get().to(get_news_detail))
+                .route("/news/{id}", web::delete().to(delete_news))
+                .route("/categories", web::post().to(create_category))
+                .route("/categories/{id}", web::delete().to(delete_category))
+                .route("/trash/news", web::get().to(list_trashed_news))
+                .route("/trash/news/{id}", web::delete().to(purge_news))
+                .route("/trash/news/{id}/restore", web::post().to(restore_news))
+                .route("/trash/categories", web::get().to(list_trashed_categories))
+                .route(
+                    "/trash/categories/{id}/restore",
+                    web::post().to(restore_category),
+                ),
+        )
+        .await;
+        let request = |method: &str, uri: String| match method {
+            "POST" => TestRequest::post().uri(&uri).to_request(),
+            "DELETE" => TestRequest::delete().uri(&uri).to_request(),
+            _ => TestRequest::get().uri(&uri).to_request(),
+        };
+        let article = format!("/news/{}", news_id);
+
+        // a deleted article is gone from every read, but not lost
+        let resp = call_service(&app, request("DELETE", article.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, request("GET", article.clone())).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = call_service(&app, request("GET", "/news".into())).await;
+        let listed: Value = read_body_json(resp).await;
+        assert_eq!(listed.as_array().unwrap().len(), 0);
+        let resp = call_service(&app, request("DELETE", article.clone())).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+
+        let resp = call_service(&app, request("GET", "/trash/news".into())).await;
+        let trashed: Value = read_body_json(resp).await;
+        assert_eq!(trashed[0]["id"], news_id);
+        assert!(trashed[0]["deleted_at"].is_string());
+
+        // restoring brings its categories back
+        let uri = format!("/trash/news/{}/restore", news_id);
+        let resp = call_service(&app, request("POST", uri.clone())).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let restored: Value = read_body_json(resp).await;
+        assert!(restored.get("deleted_at").is_none());
+        let resp = call_service(&app, request("POST", uri)).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        let resp = call_service(&app, request("GET", article.clone())).await;
+        let detail: Value = read_body_json(resp).await;
+        assert_eq!(detail["categories"][0]["id"], tech_id);
+
+        // a trashed category disappears from its articles until it is restored
+        let resp = call_service(&app, request("DELETE", format!("/categories/{}", tech_id))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, request("GET", article.clone())).await;
+        let detail: Value = read_body_json(resp).await;
+        assert_eq!(detail["categories"].as_array().unwrap().len(), 0);
+        let resp = call_service(&app, request("GET", "/trash/categories".into())).await;
+        let trashed: Value = read_body_json(resp).await;
+        assert_eq!(trashed[0]["name"], "Tech");
+        let uri = format!("/trash/categories/{}/restore", tech_id);
+        let resp = call_service(&app, request("POST", uri)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, request("GET", article.clone())).await;
+        let detail: Value = read_body_json(resp).await;
+        assert_eq!(detail["categories"][0]["id"], tech_id);
+
+        // its name is free while it is in the trash
+        let resp = call_service(&app, request("DELETE", format!("/categories/{}", tech_id))).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(
+            &app,
+            TestRequest::post()
+                .uri("/categories")
+                .set_json(json!({ "name": "Tech" }))
+                .to_request(),
+        )
+        .await;
+        assert_eq!(resp.status(), StatusCode::CREATED);
+        let uri = format!("/trash/categories/{}/restore", tech_id);
+        let resp = call_service(&app, request("POST", uri)).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+
+        // only what is in the trash can be purged
+        let purge = format!("/trash/news/{}", news_id);
+        let resp = call_service(&app, request("DELETE", purge.clone())).await;
+        assert_eq!(resp.status(), StatusCode::NOT_FOUND);
+        call_service(&app, request("DELETE", article)).await;
+        let resp = call_service(&app, request("DELETE", purge)).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let resp = call_service(&app, request("GET", "/trash/news".into())).await;
+        let trashed: Value = read_body_json(resp).await;
+        assert_eq!(trashed.as_array().unwrap().len(), 0);
+
+        let types: Vec<String> = repository
+            .outbox()
+            .into_iter()
+            .map(|(event_type, _)| event_type)
+            .collect();
+        assert!(types.contains(&"news.restored".to_string()));
+        assert!(types.contains(&"category.restored".to_string()));
+    }
+
+    #[test]
+    fn test_trash_is_purged_after_retention() {
+        let (pool, database_url) = get_test_pool();
+        run_migrations(&mut pool.get().unwrap()).unwrap();
+        let repository = PgRepository::new(pool.clone());
+        let (admin_id, news_id, tech_id) = seed_article(&repository, "admin", true);
+        let older = NewsRepository::create(
+            &repository,
+            NewNews {
+                title: "Old news".to_string(),
+                content: "Long ago".to_string(),
+                author_id: admin_id,
+            },
+            &[tech_id],
+        )
+        .unwrap();
+
+        for id in [news_id, older.id] {
+            assert!(NewsRepository::delete(&repository, id, None).unwrap());
+        }
+        assert!(CategoryRepository::delete(&repository, tech_id, None).unwrap());
+        assert!(NewsRepository::find(&repository, news_id)
+            .unwrap()
+            .is_none());
+        assert!(CategoryRepository::list(&repository).unwrap().is_empty());
+        assert_eq!(NewsRepository::trashed(&repository).unwrap().len(), 2);
+        let filter = NewsFilter {
+            category_id: Some(tech_id),
+            ..NewsFilter::default()
+        };
+        assert_eq!(repository.page(&filter, 0, 10).unwrap().1, 0);
+
+        // the article is back, its category only once that is restored too
+        assert!(NewsRepository::restore(&repository, news_id)
+            .unwrap()
+            .is_some());
+        assert!(repository.categories(news_id).unwrap().is_empty());
+        assert!(CategoryRepository::restore(&repository, tech_id)
+            .unwrap()
+            .is_some());
+        assert_eq!(repository.categories(news_id).unwrap()[0].id, tech_id);
+        assert_eq!(repository.page(&filter, 0, 10).unwrap().1, 1);
+        assert!(!NewsRepository::purge(&repository, news_id).unwrap());
+
+        // only what was deleted before the retention window goes
+        let conn = &mut pool.get().unwrap();
+        diesel::update(news::table.find(older.id))
+            .set(news::deleted_at.eq(Utc::now().naive_utc() - Duration::days(40)))
+            .execute(conn)
+            .unwrap();
+        assert!(CategoryRepository::delete(&repository, tech_id, None).unwrap());
+        // a new category can take the name of a trashed one, which then stays in the trash
+        CategoryRepository::create(
+            &repository,
+            NewCategory {
+                name: "Tech".to_string(),
+                description: None,
+            },
+        )
+        .unwrap();
+        let taken = CategoryRepository::restore(&repository, tech_id).unwrap_err();
+        assert!(matches!(taken, AppError::ConflictError(_)));
+        let summary = purge_trash(conn, 30).unwrap();
+        assert_eq!((summary.news, summary.categories), (1, 0));
+        assert!(NewsRepository::trashed(&repository).unwrap().is_empty());
+
+        assert!(CategoryRepository::purge(&repository, tech_id).unwrap());
+        assert!(CategoryRepository::trashed(&repository).unwrap().is_empty());
+        assert!(repository.categories(news_id).unwrap().is_empty());
+
+        drop(repository);
+        drop(pool);
+        cleanup_test_database(&database_url);
+    }
+}
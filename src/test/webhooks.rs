@@ -0,0 +1,297 @@
+#[cfg(test)]
+mod webhooks_tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::sync::{Arc, Mutex};
+
+    use crate::config::WebhookConfig;
+    use crate::db::run_migrations;
+    use crate::handlers::webhooks::{
+        create_webhook, list_deliveries, list_webhooks, retry_delivery,
+    };
+    use crate::models::category::NewCategory;
+    use crate::models::news::{NewNews, NewsChangeset};
+    use crate::models::user::NewUser;
+    use crate::repository::memory::MemoryRepository;
+    use crate::repository::postgres::PgRepository;
+    use crate::repository::{CategoryRepository, NewsRepository, UserRepository};
+    use crate::test::test_utils::{cleanup_test_database, get_test_pool};
+    use crate::webhooks::dispatcher::Dispatcher;
+    use crate::webhooks::{is_subscribed, is_valid_pattern, sign};
+    use actix_web::test::{call_service, init_service, read_body_json, TestRequest};
+    use actix_web::web::Bytes;
+    use actix_web::{http::StatusCode, web, App, HttpRequest, HttpResponse, HttpServer};
+    use chrono::Utc;
+    use serde_json::{json, Value};
+
+    // (signature header, timestamp header, body) of every request the receiver got
+    type Received = Arc<Mutex<Vec<(String, String, Bytes)>>>;
+
+    // start a receiver on a random port, `/flaky` fails its first request and `/down` all of them
+    fn start_receiver(received: Received) -> String {
+        let failures = Arc::new(AtomicUsize::new(0));
+        let server = HttpServer::new(move || {
+            let received = received.clone();
+            let failures = failures.clone();
+            App::new()
+                .route(
+                    "/flaky",
+                    web::post().to(move |req: HttpRequest, body: Bytes| {
+                        let received = received.clone();
+                        let failures = failures.clone();
+                        async move {
+                            let header = |name: &str| {
+                                req.headers()
+                                    .get(name)
+                                    .and_then(|value| value.to_str().ok())
+                                    .unwrap_or_default()
+                                    .to_string()
+                            };
+                            received.lock().unwrap().push((
+                                header("X-Webhook-Signature"),
+                                header("X-Webhook-Timestamp"),
+                                body,
+                            ));
+                            if failures.fetch_add(1, Ordering::SeqCst) == 0 {
+                                HttpResponse::InternalServerError().finish()
+                            } else {
+                                HttpResponse::Ok().finish()
+                            }
+                        }
+                    }),
+                )
+                .route(
+                    "/down",
+                    web::post().to(|| async { HttpResponse::ServiceUnavailable().finish() }),
+                )
+        })
+        .workers(1)
+        .bind(("127.0.0.1", 0))
+        .unwrap();
+
+        let addr = server.addrs()[0];
+        actix_web::rt::spawn(server.run());
+        format!("http://{}", addr)
+    }
+
+    #[test]
+    fn test_event_patterns() {
+        assert!(is_valid_pattern("*"));
+        assert!(is_valid_pattern("news.published"));
+        assert!(is_valid_pattern("category.*"));
+        assert!(!is_valid_pattern("news.archived"));
+        assert!(!is_valid_pattern("user.*"));
+
+        let patterns = vec!["category.*".to_string(), "news.deleted".to_string()];
+        assert!(is_subscribed(&patterns, "category.updated"));
+        assert!(is_subscribed(&patterns, "news.deleted"));
+        assert!(!is_subscribed(&patterns, "news.created"));
+
+        // the timestamp is part of what is signed
+        assert_ne!(sign("secret", 1, b"{}"), sign("secret", 2, b"{}"));
+        assert!(sign("secret", 1, b"{}").starts_with("sha256="));
+    }
+
+    #[test]
+    fn test_writes_record_events() {
+        let repository = MemoryRepository::new();
+        let author = UserRepository::create(
+            &repository,
+            NewUser {
+                username: "editor".to_string(),
+                password: "hash".to_string(),
+                is_admin: true,
+            },
+        )
+        .unwrap();
+        let tech = CategoryRepository::create(
+            &repository,
+            NewCategory {
+                name: "Tech".to_string(),
+                description: None,
+            },
+        )
+        .unwrap();
+        let news = NewsRepository::create(
+            &repository,
+            NewNews {
+                title: "Breaking".to_string(),
+                content: "Something happened".to_string(),
+                author_id: author.id,
+            },
+            &[tech.id],
+        )
+        .unwrap();
+        NewsRepository::update(
+            &repository,
+            news.id,
+            NewsChangeset {
+                title: Some("Updated".to_string()),
+                content: None,
+                updated_at: Utc::now().naive_utc(),
+            },
+            None,
+            None,
+        )
+        .unwrap();
+        NewsRepository::delete(&repository, news.id, None).unwrap();
+        CategoryRepository::delete(&repository, tech.id, None).unwrap();
+        // nothing changed, nothing to announce
+        assert!(!NewsRepository::delete(&repository, news.id, None).unwrap());
+
+        let outbox = repository.outbox();
+        let types: Vec<&str> = outbox.iter().map(|(kind, _)| kind.as_str()).collect();
+        assert_eq!(
+            types,
+            [
+                "category.created",
+                "news.created",
+                "news.published",
+                "news.updated",
+                "news.deleted",
+                "category.deleted",
+            ]
+        );
+        assert_eq!(outbox[3].1["title"], "Updated");
+        assert_eq!(outbox[5].1["name"], "Tech");
+    }
+
+    #[actix_web::test]
+    async fn test_deliveries_are_signed_retried_and_dead_lettered() {
+        let (pool, database_url) = get_test_pool();
+        run_migrations(&mut pool.get().unwrap()).unwrap();
+        let received: Received = Arc::default();
+        let receiver = start_receiver(received.clone());
+
+        let app = init_service(
+            App::new()
+                .app_data(web::Data::new(pool.clone()))
+                .route("/webhooks", web::get().to(list_webhooks))
+                .route("/webhooks", web::post().to(create_webhook))
+                .route("/webhooks/{id}/deliveries", web::get().to(list_deliveries))
+                .route(
+                    "/webhooks/{id}/deliveries/{delivery_id}/retry",
+                    web::post().to(retry_delivery),
+                ),
+        )
+        .await;
+
+        // only http endpoints and known events are accepted
+        for invalid in [
+            json!({ "url": "ftp://example.com/hook", "events": ["*"] }),
+            json!({ "url": "not a url", "events": ["*"] }),
+            json!({ "url": "https://example.com/hook", "events": [] }),
+            json!({ "url": "https://example.com/hook", "events": ["news.archived"] }),
+        ] {
+            let resp = call_service(
+                &app,
+                TestRequest::post()
+                    .uri("/webhooks")
+                    .set_json(invalid)
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::UNPROCESSABLE_ENTITY);
+        }
+
+        let mut hooks = Vec::new();
+        for (path, events) in [("/flaky", json!(["category.*"])), ("/down", json!(["*"]))] {
+            let resp = call_service(
+                &app,
+                TestRequest::post()
+                    .uri("/webhooks")
+                    .set_json(json!({ "url": format!("{}{}", receiver, path), "events": events }))
+                    .to_request(),
+            )
+            .await;
+            assert_eq!(resp.status(), StatusCode::CREATED);
+            let body: Value = read_body_json(resp).await;
+            assert!(body["secret"].as_str().unwrap().starts_with("whsec_"));
+            hooks.push(body);
+        }
+        let resp = call_service(&app, TestRequest::get().uri("/webhooks").to_request()).await;
+        let listed: Value = read_body_json(resp).await;
+        assert_eq!(listed.as_array().unwrap().len(), 2);
+        assert!(listed[0].get("secret").is_none());
+
+        let category = CategoryRepository::create(
+            &PgRepository::new(pool.clone()),
+            NewCategory {
+                name: "Tech".to_string(),
+                description: None,
+            },
+        )
+        .unwrap();
+
+        let dispatcher = Dispatcher::new(
+            pool.clone(),
+            WebhookConfig {
+                backoff_secs: 0,
+                max_attempts: 2,
+                ..WebhookConfig::default()
+            },
+        );
+        // the first attempt fails on both, the second reaches /flaky and gives up on /down
+        assert_eq!(dispatcher.run_once().await.unwrap(), 2);
+        assert_eq!(dispatcher.run_once().await.unwrap(), 2);
+        assert_eq!(dispatcher.run_once().await.unwrap(), 0);
+
+        let received = received.lock().unwrap().clone();
+        assert_eq!(received.len(), 2);
+        let (signature, timestamp, body) = &received[1];
+        let secret = hooks[0]["secret"].as_str().unwrap();
+        assert_eq!(signature, &sign(secret, timestamp.parse().unwrap(), body));
+        let event: Value = serde_json::from_slice(body).unwrap();
+        assert_eq!(event["type"], "category.created");
+        assert_eq!(event["data"]["id"], category.id);
+
+        let flaky = &hooks[0]["webhook"]["id"];
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/webhooks/{}/deliveries", flaky))
+                .to_request(),
+        )
+        .await;
+        let deliveries: Value = read_body_json(resp).await;
+        assert_eq!(deliveries[0]["status"], "delivered");
+        assert_eq!(deliveries[0]["attempts"], 2);
+        assert_eq!(deliveries[0]["last_status_code"], 200);
+
+        let down = &hooks[1]["webhook"]["id"];
+        let resp = call_service(
+            &app,
+            TestRequest::get()
+                .uri(&format!("/webhooks/{}/deliveries?status=dead", down))
+                .to_request(),
+        )
+        .await;
+        let dead: Value = read_body_json(resp).await;
+        assert_eq!(dead.as_array().unwrap().len(), 1);
+        assert_eq!(dead[0]["last_status_code"], 503);
+
+        // dead deliveries can be sent again, delivered ones can't
+        let retry = |webhook: &Value, delivery: &Value| {
+            TestRequest::post()
+                .uri(&format!(
+                    "/webhooks/{}/deliveries/{}/retry",
+                    webhook, delivery["id"]
+                ))
+                .to_request()
+        };
+        let resp = call_service(&app, retry(down, &dead[0])).await;
+        assert_eq!(resp.status(), StatusCode::OK);
+        let retried: Value = read_body_json(resp).await;
+        assert_eq!(retried["status"], "pending");
+        assert_eq!(retried["attempts"], 0);
+        let resp = call_service(&app, retry(down, &dead[0])).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        let resp = call_service(&app, retry(flaky, &deliveries[0])).await;
+        assert_eq!(resp.status(), StatusCode::CONFLICT);
+        assert_eq!(dispatcher.run_once().await.unwrap(), 1);
+
+        drop(app);
+        drop(dispatcher);
+        drop(pool);
+        cleanup_test_database(&database_url);
+    }
+}
@@ -0,0 +1,106 @@
+use actix_web::http::header::{HeaderMap, AUTHORIZATION};
+use chrono::Utc;
+use diesel::prelude::*;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::models::api_key::{ApiKey, ApiKeyContext, SCOPE_ADMIN};
+use crate::models::user::User;
+use crate::schema::{api_keys, users};
+use crate::utils::error_response::AppError;
+use crate::utils::jwt::Claims;
+
+pub const API_KEY_HEADER: &str = "X-Api-Key";
+
+// marker so leaked keys are easy to recognise in logs and secret scanners
+const KEY_MARKER: &str = "nk_";
+
+// generate a new API key, returns (plaintext key, display prefix, hash)
+pub fn generate_api_key() -> (String, String, String) {
+    let key = format!(
+        "{}{}{}",
+        KEY_MARKER,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    );
+    let prefix = key[..KEY_MARKER.len() + 8].to_string();
+    let hash = hash_api_key(&key);
+
+    (key, prefix, hash)
+}
+
+// keys are long random strings, so a plain SHA-256 is enough to store them
+pub fn hash_api_key(key: &str) -> String {
+    format!("{:x}", Sha256::digest(key.as_bytes()))
+}
+
+// read the key from `X-Api-Key` or `Authorization: ApiKey ...`
+pub fn api_key_from_headers(headers: &HeaderMap) -> Option<&str> {
+    if let Some(value) = headers.get(API_KEY_HEADER) {
+        return value.to_str().ok();
+    }
+
+    headers
+        .get(AUTHORIZATION)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.strip_prefix("ApiKey "))
+}
+
+// funtion to verify an API key and build the claims of its owner
+pub fn authenticate_api_key(
+    conn: &mut PgConnection,
+    key: &str,
+) -> Result<(Claims, ApiKeyContext), AppError> {
+    let now = Utc::now().naive_utc();
+
+    let (api_key, owner) = api_keys::table
+        .inner_join(users::table)
+        .filter(api_keys::key_hash.eq(hash_api_key(key)))
+        .select((ApiKey::as_select(), users::all_columns))
+        .first::<(ApiKey, User)>(conn)
+        .optional()?
+        .ok_or_else(|| AppError::UnauthorizedError("Invalid API key".into()))?;
+
+    if api_key.revoked_at.is_some() {
+        return Err(AppError::UnauthorizedError(
+            "API key has been revoked".into(),
+        ));
+    }
+
+    if api_key
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= now)
+    {
+        return Err(AppError::UnauthorizedError("API key has expired".into()));
+    }
+
+    // record usage, at most once a minute to keep writes down
+    diesel::update(
+        api_keys::table.filter(api_keys::id.eq(api_key.id)).filter(
+            api_keys::last_used_at
+                .is_null()
+                .or(api_keys::last_used_at.lt(now - chrono::Duration::minutes(1))),
+        ),
+    )
+    .set(api_keys::last_used_at.eq(now))
+    .execute(conn)?;
+
+    let context = ApiKeyContext {
+        id: api_key.id,
+        scopes: api_key.scopes,
+    };
+
+    // the key never grants more than its owner has
+    let claims = Claims {
+        sub: owner.id,
+        username: owner.username,
+        is_admin: owner.is_admin && context.has_scope(SCOPE_ADMIN),
+        exp: api_key
+            .expires_at
+            .map(|expires_at| expires_at.and_utc().timestamp() as usize)
+            .unwrap_or(usize::MAX),
+        sid: None,
+    };
+
+    Ok((claims, context))
+}
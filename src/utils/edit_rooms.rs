@@ -0,0 +1,255 @@
+use std::collections::HashMap;
+use std::sync::Mutex;
+
+use chrono::{NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+use tokio::sync::mpsc;
+
+use crate::config::EditLockConfig;
+use crate::events::{ContentEvent, Subscriber};
+use crate::utils::error_response::AppError;
+use crate::utils::jwt::Claims;
+
+/// Someone in a room.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct Member {
+    pub user_id: i32,
+    pub username: String,
+}
+
+/// The advisory edit lock of an article, free again after `expires_at` unless renewed.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct EditLock {
+    pub holder: Member,
+    pub expires_at: NaiveDateTime,
+}
+
+/// What clients send into a room.
+#[derive(Debug, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum ClientMessage {
+    // takes or renews the lock, admins may take it from someone else with `force`
+    Lock {
+        #[serde(default)]
+        force: bool,
+    },
+    Unlock,
+}
+
+/// What a room sends to its members.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum RoomMessage {
+    // everyone in the room, once per user
+    Presence { members: Vec<Member> },
+    Lock { lock: Option<EditLock> },
+    // the article was saved or deleted, by anyone
+    Changed { news_id: i32, change: &'static str },
+    Error { code: &'static str, message: String },
+}
+
+impl RoomMessage {
+    pub fn error(e: &AppError) -> Self {
+        RoomMessage::Error {
+            code: e.code(),
+            message: e.problem().detail,
+        }
+    }
+}
+
+struct Connection {
+    id: u64,
+    member: Member,
+    is_admin: bool,
+    sender: mpsc::UnboundedSender<RoomMessage>,
+}
+
+#[derive(Default)]
+struct Room {
+    connections: Vec<Connection>,
+    lock: Option<EditLock>,
+}
+
+impl Room {
+    fn presence(&self) -> RoomMessage {
+        let mut members: Vec<Member> = Vec::new();
+        for connection in &self.connections {
+            if !members.contains(&connection.member) {
+                members.push(connection.member.clone());
+            }
+        }
+        RoomMessage::Presence { members }
+    }
+
+    // the lock unless it expired
+    fn lock(&mut self) -> Option<&EditLock> {
+        if self
+            .lock
+            .as_ref()
+            .is_some_and(|lock| lock.expires_at <= Utc::now().naive_utc())
+        {
+            self.lock = None;
+        }
+        self.lock.as_ref()
+    }
+
+    fn broadcast(&self, message: RoomMessage) {
+        for connection in &self.connections {
+            // a closed connection is removed when its socket task ends
+            let _ = connection.sender.send(message.clone());
+        }
+    }
+}
+
+/// One room per article being edited, shared by all workers as `web::Data<EditRooms>`. Rooms
+/// and locks live in this process, so editors of the same article have to reach the same one.
+pub struct EditRooms {
+    rooms: Mutex<HashMap<i32, Room>>,
+    next_id: Mutex<u64>,
+    ttl: chrono::Duration,
+}
+
+impl EditRooms {
+    pub fn new(config: &EditLockConfig) -> Self {
+        EditRooms {
+            rooms: Mutex::new(HashMap::new()),
+            next_id: Mutex::new(0),
+            ttl: chrono::Duration::seconds(config.ttl_secs as i64),
+        }
+    }
+
+    /// Adds a connection to the room of the article, returns its id and what it will be sent.
+    /// Everyone gets the new presence, the newcomer the current lock too.
+    pub fn join(
+        &self,
+        news_id: i32,
+        claims: &Claims,
+    ) -> (u64, mpsc::UnboundedReceiver<RoomMessage>) {
+        let id = {
+            let mut next_id = self.next_id.lock().unwrap();
+            *next_id += 1;
+            *next_id
+        };
+        let (sender, receiver) = mpsc::unbounded_channel();
+
+        let mut rooms = self.rooms.lock().unwrap();
+        let room = rooms.entry(news_id).or_default();
+        let _ = sender.send(RoomMessage::Lock {
+            lock: room.lock().cloned(),
+        });
+        room.connections.push(Connection {
+            id,
+            member: Member {
+                user_id: claims.sub,
+                username: claims.username.clone(),
+            },
+            is_admin: claims.is_admin,
+            sender,
+        });
+        room.broadcast(room.presence());
+        (id, receiver)
+    }
+
+    /// Removes a connection, a user who left on every connection gives up the lock.
+    pub fn leave(&self, news_id: i32, connection_id: u64) {
+        let mut rooms = self.rooms.lock().unwrap();
+        let Some(room) = rooms.get_mut(&news_id) else {
+            return;
+        };
+        let Some(index) = room.connections.iter().position(|c| c.id == connection_id) else {
+            return;
+        };
+        let left = room.connections.remove(index).member;
+        if room.connections.is_empty() {
+            rooms.remove(&news_id);
+            return;
+        }
+
+        let still_here = room.connections.iter().any(|c| c.member == left);
+        if !still_here && room.lock().is_some_and(|lock| lock.holder == left) {
+            room.lock = None;
+            room.broadcast(RoomMessage::Lock { lock: None });
+        }
+        room.broadcast(room.presence());
+    }
+
+    /// Handles a message from a connection, the room hears about every lock change.
+    pub fn handle(
+        &self,
+        news_id: i32,
+        connection_id: u64,
+        message: ClientMessage,
+    ) -> Result<(), AppError> {
+        let mut rooms = self.rooms.lock().unwrap();
+        let room = rooms
+            .get_mut(&news_id)
+            .ok_or_else(|| AppError::NotFoundError("Not in the room".into()))?;
+        let (member, is_admin) = room
+            .connections
+            .iter()
+            .find(|c| c.id == connection_id)
+            .map(|c| (c.member.clone(), c.is_admin))
+            .ok_or_else(|| AppError::NotFoundError("Not in the room".into()))?;
+
+        let lock = match message {
+            ClientMessage::Lock { force } => {
+                if let Some(lock) = room.lock().filter(|lock| lock.holder != member) {
+                    if !force {
+                        return Err(AppError::LockedError(format!(
+                            "{} is editing this article",
+                            lock.holder.username
+                        )));
+                    }
+                    if !is_admin {
+                        return Err(AppError::ForbiddenError(
+                            "Only admins can take over a lock".into(),
+                        ));
+                    }
+                }
+                Some(EditLock {
+                    holder: member,
+                    expires_at: Utc::now().naive_utc() + self.ttl,
+                })
+            }
+            ClientMessage::Unlock => {
+                if room.lock().is_some_and(|lock| lock.holder != member) {
+                    return Err(AppError::LockedError(
+                        "Only the holder can release the lock".into(),
+                    ));
+                }
+                None
+            }
+        };
+        room.lock = lock.clone();
+        room.broadcast(RoomMessage::Lock { lock });
+        Ok(())
+    }
+
+    /// Fails unless the user holds the lock of the article, for saves when locks are enforced.
+    pub fn check_holder(&self, news_id: i32, claims: &Claims) -> Result<(), AppError> {
+        let mut rooms = self.rooms.lock().unwrap();
+        match rooms.get_mut(&news_id).and_then(|room| room.lock()) {
+            Some(lock) if lock.holder.user_id == claims.sub => Ok(()),
+            Some(lock) => Err(AppError::LockedError(format!(
+                "{} is editing this article",
+                lock.holder.username
+            ))),
+            None => Err(AppError::LockedError(
+                "Take the edit lock of the article before saving".into(),
+            )),
+        }
+    }
+}
+
+impl Subscriber for EditRooms {
+    fn notify(&self, event: &ContentEvent) {
+        let (news_id, change) = match *event {
+            ContentEvent::NewsUpdated { id } => (id, "updated"),
+            ContentEvent::NewsDeleted { id } => (id, "deleted"),
+            _ => return,
+        };
+        if let Some(room) = self.rooms.lock().unwrap().get(&news_id) {
+            room.broadcast(RoomMessage::Changed { news_id, change });
+        }
+    }
+}
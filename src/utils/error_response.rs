@@ -1,9 +1,24 @@
-use actix_web::{error::ResponseError, http::StatusCode, HttpResponse};
+use actix_web::{
+    error::{JsonPayloadError, ResponseError},
+    http::{
+        header::{ETAG, RETRY_AFTER},
+        StatusCode,
+    },
+    web, HttpRequest, HttpResponse,
+};
 use derive_more::Display;
-use diesel::result::Error as DieselError;
-use serde_json::json;
+use diesel::result::{DatabaseErrorKind, Error as DieselError};
+use serde::Serialize;
+use serde_json::{json, Value};
+use utoipa::ToSchema;
+use validator::ValidationErrors;
+
+use crate::db::PoolError;
+
+pub const PROBLEM_CONTENT_TYPE: &str = "application/problem+json";
 
 // enum for error object
+#[allow(clippy::enum_variant_names)]
 #[derive(Debug, Display)]
 pub enum AppError {
     #[display("Database error: {}", _0)]
@@ -14,6 +29,46 @@ pub enum AppError {
     UnauthorizedError(String),
     #[display("Forbidden: {}", _0)]
     ForbiddenError(String),
+    #[display("External service error: {}", _0)]
+    ExternalServiceError(String),
+    #[display("Bad request: {}", _0)]
+    BadRequestError(String),
+    #[display("Validation failed: {}", _0)]
+    ValidationError(ValidationErrors),
+    #[display("Conflict: {}", _0)]
+    ConflictError(String),
+    // a referenced row (author, category, ...) doesn't exist
+    #[display("Invalid reference: {}", _0)]
+    InvalidReferenceError(String),
+    #[display("Rate limited, retry after {}s", _0)]
+    RateLimitedError(u64),
+    #[display("Payload too large: {}", _0)]
+    PayloadTooLargeError(String),
+    // If-Match named an older version, with the current ETag and representation
+    #[display("Precondition failed, current version is {}", _0)]
+    PreconditionFailedError(String, Value),
+    #[display("Precondition required: {}", _0)]
+    PreconditionRequiredError(String),
+    // saving needs the edit lock of the article, which someone else may hold
+    #[display("Locked: {}", _0)]
+    LockedError(String),
+}
+
+/// RFC 7807 problem details, `code` is the stable value clients should match on
+#[derive(Debug, Serialize, ToSchema)]
+pub struct Problem {
+    #[serde(rename = "type")]
+    pub problem_type: &'static str,
+    pub title: &'static str,
+    pub status: u16,
+    pub detail: String,
+    pub code: &'static str,
+    // per field validation failures
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub errors: Option<Value>,
+    // the server's version after a failed precondition, to merge with
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub current: Option<Value>,
 }
 
 // Implement std::error::Error for AppError
@@ -22,28 +77,134 @@ impl std::error::Error for AppError {}
 // Implement From<DieselError> for AppError
 impl From<DieselError> for AppError {
     fn from(error: DieselError) -> Self {
-        AppError::DatabaseError(format!("Diesel error: {}", error))
+        match error {
+            DieselError::NotFound => AppError::NotFoundError("Resource not found".into()),
+            DieselError::DatabaseError(DatabaseErrorKind::UniqueViolation, info) => {
+                log::warn!("Unique violation: {}", info.message());
+                AppError::ConflictError("Resource already exists".into())
+            }
+            DieselError::DatabaseError(DatabaseErrorKind::ForeignKeyViolation, info) => {
+                log::warn!("Foreign key violation: {}", info.message());
+                AppError::InvalidReferenceError("Referenced resource does not exist".into())
+            }
+            error => AppError::DatabaseError(format!("Diesel error: {}", error)),
+        }
+    }
+}
+
+impl From<PoolError> for AppError {
+    fn from(error: PoolError) -> Self {
+        AppError::DatabaseError(format!("Failed to get DB connection: {}", error))
+    }
+}
+
+impl From<ValidationErrors> for AppError {
+    fn from(errors: ValidationErrors) -> Self {
+        AppError::ValidationError(errors)
+    }
+}
+
+// field -> [{code, message}], flatter than what ValidationErrors serializes to
+fn field_errors(errors: &ValidationErrors) -> Value {
+    let fields = errors
+        .field_errors()
+        .into_iter()
+        .map(|(field, errors)| {
+            let errors: Vec<Value> = errors
+                .iter()
+                .map(|error| {
+                    json!({
+                        "code": error.code,
+                        "message": error.message.as_ref().unwrap_or(&error.code),
+                    })
+                })
+                .collect();
+            (field.to_string(), Value::Array(errors))
+        })
+        .collect();
+    Value::Object(fields)
+}
+
+impl AppError {
+    // stable machine readable code for each variant
+    pub fn code(&self) -> &'static str {
+        match self {
+            AppError::DatabaseError(_) => "internal_error",
+            AppError::NotFoundError(_) => "not_found",
+            AppError::UnauthorizedError(_) => "unauthorized",
+            AppError::ForbiddenError(_) => "forbidden",
+            AppError::ExternalServiceError(_) => "upstream_error",
+            AppError::BadRequestError(_) => "bad_request",
+            AppError::ValidationError(_) => "validation_failed",
+            AppError::ConflictError(_) => "conflict",
+            AppError::InvalidReferenceError(_) => "invalid_reference",
+            AppError::RateLimitedError(_) => "rate_limited",
+            AppError::PayloadTooLargeError(_) => "payload_too_large",
+            AppError::PreconditionFailedError(..) => "precondition_failed",
+            AppError::PreconditionRequiredError(_) => "precondition_required",
+            AppError::LockedError(_) => "locked",
+        }
+    }
+
+    pub fn problem(&self) -> Problem {
+        let status = self.status_code();
+        let detail = match self {
+            // internal details only go to the log
+            AppError::DatabaseError(msg) => {
+                log::error!("Internal error: {}", msg);
+                "Internal server error".to_string()
+            }
+            AppError::ValidationError(_) => "Request validation failed".to_string(),
+            AppError::RateLimitedError(_) => "Too many requests".to_string(),
+            AppError::PreconditionFailedError(..) => {
+                "The resource was changed since it was read".to_string()
+            }
+            AppError::NotFoundError(msg)
+            | AppError::UnauthorizedError(msg)
+            | AppError::ForbiddenError(msg)
+            | AppError::ExternalServiceError(msg)
+            | AppError::BadRequestError(msg)
+            | AppError::ConflictError(msg)
+            | AppError::InvalidReferenceError(msg)
+            | AppError::PayloadTooLargeError(msg)
+            | AppError::PreconditionRequiredError(msg)
+            | AppError::LockedError(msg) => msg.clone(),
+        };
+
+        Problem {
+            problem_type: "about:blank",
+            title: status.canonical_reason().unwrap_or("Error"),
+            status: status.as_u16(),
+            detail,
+            code: self.code(),
+            errors: match self {
+                AppError::ValidationError(errors) => Some(field_errors(errors)),
+                _ => None,
+            },
+            current: match self {
+                AppError::PreconditionFailedError(_, current) => Some(current.clone()),
+                _ => None,
+            },
+        }
     }
 }
 
 // Implement ResponseError for AppError
 impl ResponseError for AppError {
-    // create error message for error response json
+    // render the problem details
     fn error_response(&self) -> HttpResponse {
+        let mut response = HttpResponse::build(self.status_code());
+        response.content_type(PROBLEM_CONTENT_TYPE);
         match self {
-            AppError::DatabaseError(msg) => HttpResponse::InternalServerError().json(json!({
-                "error": msg
-            })),
-            AppError::NotFoundError(msg) => HttpResponse::NotFound().json(json!({
-                "error": msg
-            })),
-            AppError::UnauthorizedError(msg) => HttpResponse::Unauthorized().json(json!({
-                "error": msg
-            })),
-            AppError::ForbiddenError(msg) => HttpResponse::Forbidden().json(json!({
-                "error": msg
-            })),
+            AppError::RateLimitedError(retry_after) => {
+                response.insert_header((RETRY_AFTER, *retry_after));
+            }
+            AppError::PreconditionFailedError(etag, _) => {
+                response.insert_header((ETAG, etag.as_str()));
+            }
+            _ => {}
         }
+        response.json(self.problem())
     }
 
     // create error code
@@ -53,6 +214,51 @@ impl ResponseError for AppError {
             AppError::NotFoundError(_) => StatusCode::NOT_FOUND,
             AppError::UnauthorizedError(_) => StatusCode::UNAUTHORIZED,
             AppError::ForbiddenError(_) => StatusCode::FORBIDDEN,
+            AppError::ExternalServiceError(_) => StatusCode::BAD_GATEWAY,
+            AppError::BadRequestError(_) => StatusCode::BAD_REQUEST,
+            AppError::ValidationError(_) | AppError::InvalidReferenceError(_) => {
+                StatusCode::UNPROCESSABLE_ENTITY
+            }
+            AppError::ConflictError(_) => StatusCode::CONFLICT,
+            AppError::RateLimitedError(_) => StatusCode::TOO_MANY_REQUESTS,
+            AppError::PayloadTooLargeError(_) => StatusCode::PAYLOAD_TOO_LARGE,
+            AppError::PreconditionFailedError(..) => StatusCode::PRECONDITION_FAILED,
+            AppError::PreconditionRequiredError(_) => StatusCode::PRECONDITION_REQUIRED,
+            AppError::LockedError(_) => StatusCode::LOCKED,
         }
     }
 }
+
+// malformed or oversized JSON bodies
+fn json_error(error: JsonPayloadError, _req: &HttpRequest) -> actix_web::Error {
+    match error {
+        JsonPayloadError::Overflow { limit }
+        | JsonPayloadError::OverflowKnownLength { limit, .. } => {
+            AppError::PayloadTooLargeError(format!("Body is larger than {} bytes", limit)).into()
+        }
+        JsonPayloadError::ContentType => {
+            AppError::BadRequestError("Expected a JSON body".into()).into()
+        }
+        error => AppError::BadRequestError(format!("Invalid JSON body: {}", error)).into(),
+    }
+}
+
+async fn not_found() -> Result<HttpResponse, AppError> {
+    Err(AppError::NotFoundError("No such route".into()))
+}
+
+/// Extractor configs and the fallback route, so rejected requests also get problem details
+pub fn configure(cfg: &mut web::ServiceConfig, json_payload_bytes: usize) {
+    cfg.app_data(
+        web::JsonConfig::default()
+            .limit(json_payload_bytes)
+            .error_handler(json_error),
+    )
+    .app_data(web::PathConfig::default().error_handler(|error, _| {
+        AppError::BadRequestError(format!("Invalid path: {}", error)).into()
+    }))
+    .app_data(web::QueryConfig::default().error_handler(|error, _| {
+        AppError::BadRequestError(format!("Invalid query: {}", error)).into()
+    }))
+    .default_service(web::to(not_found));
+}
@@ -0,0 +1,79 @@
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use actix_web::http::header::{
+    EntityTag, Header, HttpDate, IfModifiedSince, IfNoneMatch, LastModified, CACHE_CONTROL, ETAG,
+    IF_NONE_MATCH,
+};
+use actix_web::http::StatusCode;
+use actix_web::{HttpRequest, HttpResponse, HttpResponseBuilder};
+use chrono::NaiveDateTime;
+
+use crate::config::HttpCacheConfig;
+
+const SURROGATE_KEY: &str = "Surrogate-Key";
+
+/// What a cached copy of a response is checked against.
+#[derive(Debug)]
+pub struct Validators {
+    pub etag: EntityTag,
+    // only for responses where every change moves it forward, deletions don't
+    pub last_modified: Option<NaiveDateTime>,
+}
+
+// HTTP dates have whole seconds
+fn http_date(at: NaiveDateTime) -> HttpDate {
+    let seconds = at.and_utc().timestamp().max(0) as u64;
+    HttpDate::from(UNIX_EPOCH + Duration::from_secs(seconds))
+}
+
+impl Validators {
+    /// True when the copy the client revalidates is still current. If-None-Match wins over
+    /// If-Modified-Since, and is compared weakly like RFC 9110 asks.
+    pub fn is_fresh(&self, req: &HttpRequest) -> bool {
+        if req.headers().contains_key(IF_NONE_MATCH) {
+            return match IfNoneMatch::parse(req) {
+                Ok(IfNoneMatch::Any) => true,
+                Ok(IfNoneMatch::Items(tags)) => tags.iter().any(|tag| tag.weak_eq(&self.etag)),
+                Err(_) => false,
+            };
+        }
+        match (self.last_modified, IfModifiedSince::parse(req)) {
+            (Some(last_modified), Ok(IfModifiedSince(since))) => {
+                SystemTime::from(http_date(last_modified)) <= SystemTime::from(since)
+            }
+            _ => false,
+        }
+    }
+
+    fn response(&self, status: StatusCode, cache_control: &str) -> HttpResponseBuilder {
+        let mut builder = HttpResponse::build(status);
+        builder.insert_header((ETAG, self.etag.clone()));
+        if let Some(last_modified) = self.last_modified {
+            builder.insert_header(LastModified(http_date(last_modified)));
+        }
+        if !cache_control.is_empty() {
+            builder.insert_header((CACHE_CONTROL, cache_control));
+        }
+        builder
+    }
+
+    /// 304 without a Surrogate-Key, so caches keep the keys they stored with the body.
+    pub fn not_modified(&self, cache_control: &str) -> HttpResponse {
+        self.response(StatusCode::NOT_MODIFIED, cache_control)
+            .finish()
+    }
+
+    /// 200 with the validators and cache headers, the body is up to the caller.
+    pub fn ok(&self, cache_control: &str, surrogate_key: Option<String>) -> HttpResponseBuilder {
+        let mut builder = self.response(StatusCode::OK, cache_control);
+        if let Some(surrogate_key) = surrogate_key {
+            builder.insert_header((SURROGATE_KEY, surrogate_key));
+        }
+        builder
+    }
+}
+
+/// The space separated Surrogate-Key header, None when it is turned off.
+pub fn surrogate_key(config: &HttpCacheConfig, keys: Vec<String>) -> Option<String> {
+    config.surrogate_keys.then(|| keys.join(" "))
+}
@@ -1,7 +1,7 @@
 use jsonwebtoken::{encode, decode, EncodingKey, DecodingKey, Header, Validation};
 use serde::{Deserialize, Serialize};
-use std::env;
 
+use crate::config::JwtConfig;
 
 // struct for JWT payload
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -10,13 +10,23 @@ pub struct Claims {
     pub username: String,
     pub is_admin: bool,
     pub exp: usize,
+    // login session the token belongs to, absent for API keys
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sid: Option<String>,
+}
+
+impl Claims {
+    // authors may change their own articles, admins any article
+    pub fn can_edit(&self, author_id: i32) -> bool {
+        self.is_admin || self.sub == author_id
+    }
 }
 
 // function generate JWT the token
-pub fn create_token(user_id: i32, username: &str, is_admin: bool) -> jsonwebtoken::errors::Result<String> {
+pub fn create_token(config: &JwtConfig, user_id: i32, username: &str, is_admin: bool, session_id: &str) -> jsonwebtoken::errors::Result<String> {
     // expiration token
     let expiration = chrono::Utc::now()
-        .checked_add_signed(chrono::Duration::hours(24))
+        .checked_add_signed(chrono::Duration::hours(config.token_lifetime_hours))
         .expect("Invalid timestamp!")
         .timestamp() as usize;
 
@@ -26,22 +36,19 @@ pub fn create_token(user_id: i32, username: &str, is_admin: bool) -> jsonwebtoke
         username: username.to_string(),
         is_admin,
         exp: expiration,
+        sid: Some(session_id.to_string()),
     };
 
     // generate JWT token
-    let secret = env::var("JWT_TOKEN").expect("JWT_TOKEN must be set!");
-    encode(&Header::default(), &claims, &EncodingKey::from_secret(secret.as_bytes()))
+    encode(&Header::default(), &claims, &EncodingKey::from_secret(config.secret.as_bytes()))
 }
 
 // funtion to token verification
-pub fn verify_token(token: &str) -> jsonwebtoken::errors::Result<Claims> {
-    // get the JWT token
-    let secret = env::var("JWT_TOKEN").expect("JWT_TOKEN must be set!");
-
+pub fn verify_token(config: &JwtConfig, token: &str) -> jsonwebtoken::errors::Result<Claims> {
     // validate the token 
     let decoded = decode::<Claims>(
             token,
-            &DecodingKey::from_secret(secret.as_bytes()),
+            &DecodingKey::from_secret(config.secret.as_bytes()),
             &Validation::default(),
         )?;
 
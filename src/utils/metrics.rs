@@ -0,0 +1,108 @@
+use once_cell::sync::Lazy;
+use prometheus::{
+    register_histogram_vec, register_int_counter_vec, register_int_gauge, Encoder, HistogramVec,
+    IntCounterVec, IntGauge, TextEncoder,
+};
+
+use crate::db::DBPool;
+
+pub static HTTP_REQUESTS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "http_requests_total",
+        "HTTP requests by route pattern and status",
+        &["method", "route", "status"]
+    )
+    .expect("Failed to register http_requests_total")
+});
+
+pub static HTTP_REQUEST_DURATION_SECONDS: Lazy<HistogramVec> = Lazy::new(|| {
+    register_histogram_vec!(
+        "http_request_duration_seconds",
+        "HTTP request latency by route pattern and status",
+        &["method", "route", "status"]
+    )
+    .expect("Failed to register http_request_duration_seconds")
+});
+
+pub static AUTH_FAILURES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "auth_failures_total",
+        "Rejected credentials by reason",
+        &["reason"]
+    )
+    .expect("Failed to register auth_failures_total")
+});
+
+pub static ARTICLES_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "articles_total",
+        "Articles created, published and deleted",
+        &["action"]
+    )
+    .expect("Failed to register articles_total")
+});
+
+pub static RESPONSE_CACHE_LOOKUPS_TOTAL: Lazy<IntCounterVec> = Lazy::new(|| {
+    register_int_counter_vec!(
+        "response_cache_lookups_total",
+        "Response cache lookups by cache and result",
+        &["cache", "result"]
+    )
+    .expect("Failed to register response_cache_lookups_total")
+});
+
+static DB_POOL_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("db_pool_connections", "Open database connections")
+        .expect("Failed to register db_pool_connections")
+});
+
+static DB_POOL_IDLE_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("db_pool_idle_connections", "Idle database connections")
+        .expect("Failed to register db_pool_idle_connections")
+});
+
+static DB_POOL_MAX_CONNECTIONS: Lazy<IntGauge> = Lazy::new(|| {
+    register_int_gauge!("db_pool_max_connections", "Database pool size limit")
+        .expect("Failed to register db_pool_max_connections")
+});
+
+// reasons used for auth_failures_total
+pub const AUTH_MISSING_CREDENTIALS: &str = "missing_credentials";
+pub const AUTH_INVALID_TOKEN: &str = "invalid_token";
+pub const AUTH_INVALID_SESSION: &str = "invalid_session";
+pub const AUTH_INVALID_API_KEY: &str = "invalid_api_key";
+pub const AUTH_MISSING_SCOPE: &str = "missing_scope";
+pub const AUTH_NOT_ADMIN: &str = "not_admin";
+
+pub fn record_auth_failure(reason: &str) {
+    AUTH_FAILURES_TOTAL.with_label_values(&[reason]).inc();
+}
+
+// articles go live as soon as they are created, so both are counted together
+pub fn record_article_created() {
+    ARTICLES_TOTAL.with_label_values(&["created"]).inc();
+    ARTICLES_TOTAL.with_label_values(&["published"]).inc();
+}
+
+pub fn record_article_deleted() {
+    ARTICLES_TOTAL.with_label_values(&["deleted"]).inc();
+}
+
+pub fn record_cache_lookup(cache: &str, hit: bool) {
+    let result = if hit { "hit" } else { "miss" };
+    RESPONSE_CACHE_LOOKUPS_TOTAL
+        .with_label_values(&[cache, result])
+        .inc();
+}
+
+// render every metric in the Prometheus text format, pool gauges are sampled now
+pub fn render(pool: &DBPool) -> Result<String, prometheus::Error> {
+    let state = pool.state();
+    DB_POOL_CONNECTIONS.set(state.connections.into());
+    DB_POOL_IDLE_CONNECTIONS.set(state.idle_connections.into());
+    DB_POOL_MAX_CONNECTIONS.set(pool.max_size().into());
+
+    let mut buffer = Vec::new();
+    TextEncoder::new().encode(&prometheus::gather(), &mut buffer)?;
+    String::from_utf8(buffer).map_err(|e| prometheus::Error::Msg(e.to_string()))
+}
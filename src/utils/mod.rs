@@ -1,2 +1,26 @@
 pub mod jwt;
 pub mod error_response;
+pub mod api_key;
+pub mod oidc;
+pub mod password;
+pub mod session;
+pub mod metrics;
+pub mod precondition;
+pub mod http_cache;
+pub mod response_cache;
+pub mod news_stream;
+pub mod edit_rooms;
+
+use serde::Serialize;
+use serde_json::Value;
+
+/// Serializes what the API sends or stores. Those are plain structs with string keys, so it
+/// can't fail.
+pub fn to_json<T: Serialize + ?Sized>(value: &T) -> String {
+    serde_json::to_string(value).expect("Failed to serialize to JSON")
+}
+
+/// Like `to_json`, for JSON columns.
+pub fn to_json_value<T: Serialize + ?Sized>(value: &T) -> Value {
+    serde_json::to_value(value).expect("Failed to serialize to JSON")
+}
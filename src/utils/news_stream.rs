@@ -0,0 +1,244 @@
+use std::collections::VecDeque;
+use std::sync::{Arc, Mutex};
+
+use actix_web::web::{self, Bytes};
+use chrono::Utc;
+use serde::Serialize;
+use tokio::sync::{broadcast, mpsc};
+use utoipa::ToSchema;
+
+use crate::config::NewsStreamConfig;
+use crate::events::{ContentEvent, Subscriber};
+use crate::models::news::News;
+use crate::repository::{self, NewsRepository};
+use crate::supervisor::{Shutdown, TaskResult};
+use crate::utils::to_json;
+
+/// Sent instead of the events a client missed, it should load the news list again.
+pub const RESET: &[u8] = b"event: reset\ndata: {}\n\n";
+pub const KEEP_ALIVE: &[u8] = b": keep-alive\n\n";
+
+/// What happened to the article, the `event` field of the stream.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StreamEventKind {
+    Published,
+    Updated,
+    Deleted,
+}
+
+impl StreamEventKind {
+    fn name(&self) -> &'static str {
+        match self {
+            StreamEventKind::Published => "published",
+            StreamEventKind::Updated => "updated",
+            StreamEventKind::Deleted => "deleted",
+        }
+    }
+}
+
+/// The `data` of an event.
+#[derive(Debug, Serialize, ToSchema)]
+pub struct StreamEvent {
+    // what clients send back as Last-Event-ID
+    #[serde(skip)]
+    pub id: u64,
+    #[serde(skip)]
+    pub kind: StreamEventKind,
+    pub news_id: i32,
+    // the article as it was when the event was sent, left out when it was deleted
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub news: Option<News>,
+    // the categories it is filed under, for deletions the ones it had in its last event
+    pub category_ids: Vec<i32>,
+}
+
+impl StreamEvent {
+    /// Whether a client following `category_id` wants it. Deletions of articles whose
+    /// categories are unknown go to everyone.
+    pub fn matches(&self, category_id: Option<i32>) -> bool {
+        category_id.is_none_or(|category_id| {
+            self.category_ids.contains(&category_id)
+                || (self.kind == StreamEventKind::Deleted && self.category_ids.is_empty())
+        })
+    }
+
+    pub fn frame(&self) -> Bytes {
+        Bytes::from(format!(
+            "id: {}\nevent: {}\ndata: {}\n\n",
+            self.id,
+            self.kind.name(),
+            to_json(self)
+        ))
+    }
+}
+
+/// Where a client picks up after connecting.
+#[derive(Debug)]
+pub enum Resume {
+    // the events after its Last-Event-ID, all of them were still kept
+    Backlog(Vec<Arc<StreamEvent>>),
+    // it missed events that are no longer kept
+    Reset,
+}
+
+struct Log {
+    events: VecDeque<Arc<StreamEvent>>,
+    last_id: u64,
+}
+
+/// Article changes for `GET /api/v1/news/stream`. The latest events are kept for clients
+/// that reconnect, every connected client gets the new ones.
+pub struct NewsStream {
+    changes: mpsc::UnboundedSender<ContentEvent>,
+    // taken by the task that looks up the changed articles
+    pending: tokio::sync::Mutex<mpsc::UnboundedReceiver<ContentEvent>>,
+    log: Mutex<Log>,
+    live: broadcast::Sender<Arc<StreamEvent>>,
+    log_size: usize,
+}
+
+impl NewsStream {
+    pub fn new(config: &NewsStreamConfig) -> Self {
+        let (changes, pending) = mpsc::unbounded_channel();
+        NewsStream {
+            changes,
+            pending: tokio::sync::Mutex::new(pending),
+            log: Mutex::new(Log {
+                events: VecDeque::new(),
+                // ids continue above the ones handed out before a restart, so a client coming
+                // back with one of those gets a reset instead of someone else's events
+                last_id: Utc::now().timestamp_millis().unsigned_abs(),
+            }),
+            live: broadcast::channel(config.log_size).0,
+            log_size: config.log_size,
+        }
+    }
+
+    /// The events after `last_event_id` and a receiver for the ones still to come, without
+    /// gaps or repeats between them.
+    pub fn subscribe(
+        &self,
+        last_event_id: Option<u64>,
+    ) -> (Resume, broadcast::Receiver<Arc<StreamEvent>>) {
+        let log = self.log.lock().unwrap();
+        let live = self.live.subscribe();
+        let Some(last_event_id) = last_event_id else {
+            return (Resume::Backlog(Vec::new()), live);
+        };
+
+        let kept = log
+            .events
+            .front()
+            .is_none_or(|first| first.id <= last_event_id.saturating_add(1));
+        let resume = if last_event_id <= log.last_id && kept {
+            Resume::Backlog(
+                log.events
+                    .iter()
+                    .filter(|event| event.id > last_event_id)
+                    .cloned()
+                    .collect(),
+            )
+        } else {
+            Resume::Reset
+        };
+        (resume, live)
+    }
+
+    // the categories of the latest kept event about the article
+    fn last_category_ids(&self, news_id: i32) -> Vec<i32> {
+        let log = self.log.lock().unwrap();
+        log.events
+            .iter()
+            .rev()
+            .find(|event| event.news_id == news_id)
+            .map(|event| event.category_ids.clone())
+            .unwrap_or_default()
+    }
+
+    fn push(
+        &self,
+        kind: StreamEventKind,
+        news_id: i32,
+        news: Option<News>,
+        category_ids: Vec<i32>,
+    ) {
+        let mut log = self.log.lock().unwrap();
+        log.last_id += 1;
+        let event = Arc::new(StreamEvent {
+            id: log.last_id,
+            kind,
+            news_id,
+            news,
+            category_ids,
+        });
+        log.events.push_back(event.clone());
+        if log.events.len() > self.log_size {
+            log.events.pop_front();
+        }
+        // nobody may be listening
+        let _ = self.live.send(event);
+    }
+
+    /// Turns the article changes into stream events until shutdown, looking up the article and
+    /// its categories in between. An article deleted before its change was looked up only
+    /// shows up as deleted.
+    pub async fn run(
+        &self,
+        repository: web::Data<dyn NewsRepository>,
+        mut shutdown: Shutdown,
+    ) -> TaskResult {
+        let mut pending = self.pending.lock().await;
+        loop {
+            let change = tokio::select! {
+                change = pending.recv() => change,
+                _ = shutdown.requested() => return Ok(()),
+            };
+            let (kind, news_id) = match change {
+                // restored articles are back like new ones
+                Some(ContentEvent::NewsCreated { id } | ContentEvent::NewsRestored { id }) => {
+                    (StreamEventKind::Published, id)
+                }
+                Some(ContentEvent::NewsUpdated { id }) => (StreamEventKind::Updated, id),
+                Some(ContentEvent::NewsDeleted { id }) => (StreamEventKind::Deleted, id),
+                Some(_) => continue,
+                // the stream holds the sender, this only happens once it is dropped
+                None => return Ok(()),
+            };
+
+            if kind == StreamEventKind::Deleted {
+                self.push(kind, news_id, None, self.last_category_ids(news_id));
+                continue;
+            }
+            let found = repository::run(&repository, move |repo| {
+                Ok((repo.find(news_id)?, repo.categories(news_id)?))
+            })
+            .await;
+            match found {
+                Ok((Some(news), categories)) => {
+                    let category_ids = categories.iter().map(|category| category.id).collect();
+                    self.push(kind, news_id, Some(news), category_ids);
+                }
+                // deleted right away, its deletion follows
+                Ok((None, _)) => {}
+                Err(e) => {
+                    tracing::warn!(news_id, error = %e, "Failed to load the article for the news stream");
+                }
+            }
+        }
+    }
+}
+
+impl Subscriber for NewsStream {
+    fn notify(&self, event: &ContentEvent) {
+        if matches!(
+            event,
+            ContentEvent::NewsCreated { .. }
+                | ContentEvent::NewsUpdated { .. }
+                | ContentEvent::NewsDeleted { .. }
+                | ContentEvent::NewsRestored { .. }
+        ) {
+            // the receiver lives as long as the stream
+            let _ = self.changes.send(*event);
+        }
+    }
+}
@@ -0,0 +1,349 @@
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use jsonwebtoken::jwk::JwkSet;
+use jsonwebtoken::Validation;
+use jsonwebtoken::{decode, decode_header, encode, Algorithm, DecodingKey, EncodingKey, Header};
+use serde::{Deserialize, Serialize};
+use serde_json::{Map, Value};
+use sha2::{Digest, Sha256};
+use tokio::sync::{OnceCell, RwLock};
+use uuid::Uuid;
+
+use crate::utils::error_response::AppError;
+
+// how long a user has to complete the login at the provider
+const PENDING_LOGIN_MINUTES: i64 = 10;
+
+/// Settings for the staff single sign-on provider.
+#[derive(Debug, Clone, Deserialize)]
+pub struct OidcConfig {
+    pub issuer_url: String,
+    pub client_id: String,
+    #[serde(default)]
+    pub client_secret: Option<String>,
+    pub redirect_url: String,
+    #[serde(default = "default_scopes")]
+    pub scopes: String,
+    #[serde(default = "default_groups_claim")]
+    pub groups_claim: String,
+    #[serde(default)]
+    pub admin_groups: Vec<String>,
+    #[serde(default)]
+    pub allowed_groups: Vec<String>,
+}
+
+fn default_scopes() -> String {
+    "openid profile email".to_string()
+}
+
+fn default_groups_claim() -> String {
+    "groups".to_string()
+}
+
+// subset of the discovery document we rely on
+#[derive(Debug, Clone, Deserialize)]
+pub struct ProviderMetadata {
+    pub issuer: String,
+    pub authorization_endpoint: String,
+    pub token_endpoint: String,
+    pub jwks_uri: Option<String>,
+}
+
+/// Login in progress, kept in a signed cookie between the redirect and the callback.
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PendingLogin {
+    pub state: String,
+    pub nonce: String,
+    pub code_verifier: String,
+    pub exp: usize,
+}
+
+/// Who the provider says signed in.
+#[derive(Debug, Clone)]
+pub struct OidcIdentity {
+    pub issuer: String,
+    pub subject: String,
+    pub username: String,
+    pub groups: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct TokenResponse {
+    id_token: String,
+}
+
+pub struct OidcClient {
+    pub config: OidcConfig,
+    http: reqwest::Client,
+    metadata: OnceCell<ProviderMetadata>,
+    jwks: RwLock<Option<JwkSet>>,
+    // the pending login is signed with the same secret as our own tokens
+    cookie_secret: String,
+}
+
+fn random_token() -> String {
+    format!("{}{}", Uuid::new_v4().simple(), Uuid::new_v4().simple())
+}
+
+// PKCE S256 challenge for a verifier
+pub fn code_challenge(code_verifier: &str) -> String {
+    URL_SAFE_NO_PAD.encode(Sha256::digest(code_verifier.as_bytes()))
+}
+
+fn provider_error(context: &str, e: impl std::fmt::Display) -> AppError {
+    log::error!("{}: {}", context, e);
+    AppError::ExternalServiceError(context.to_string())
+}
+
+impl OidcClient {
+    pub fn new(config: OidcConfig, cookie_secret: String) -> Self {
+        OidcClient {
+            config,
+            cookie_secret,
+            http: reqwest::Client::new(),
+            metadata: OnceCell::new(),
+            jwks: RwLock::new(None),
+        }
+    }
+
+    // fetch the discovery document once and cache it
+    pub async fn metadata(&self) -> Result<&ProviderMetadata, AppError> {
+        self.metadata
+            .get_or_try_init(|| async {
+                let url = format!(
+                    "{}/.well-known/openid-configuration",
+                    self.config.issuer_url.trim_end_matches('/')
+                );
+                self.http
+                    .get(url)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status())
+                    .map_err(|e| provider_error("Failed to discover identity provider", e))?
+                    .json::<ProviderMetadata>()
+                    .await
+                    .map_err(|e| provider_error("Invalid identity provider metadata", e))
+            })
+            .await
+    }
+
+    /// Builds the provider redirect URL together with the signed pending login cookie value.
+    pub async fn authorization_request(&self) -> Result<(String, String), AppError> {
+        let metadata = self.metadata().await?;
+        let pending = PendingLogin {
+            state: random_token(),
+            nonce: random_token(),
+            code_verifier: random_token(),
+            exp: (chrono::Utc::now() + chrono::Duration::minutes(PENDING_LOGIN_MINUTES)).timestamp()
+                as usize,
+        };
+
+        let mut url = reqwest::Url::parse(&metadata.authorization_endpoint)
+            .map_err(|e| provider_error("Invalid authorization endpoint", e))?;
+        url.query_pairs_mut()
+            .append_pair("response_type", "code")
+            .append_pair("client_id", &self.config.client_id)
+            .append_pair("redirect_uri", &self.config.redirect_url)
+            .append_pair("scope", &self.config.scopes)
+            .append_pair("state", &pending.state)
+            .append_pair("nonce", &pending.nonce)
+            .append_pair("code_challenge", &code_challenge(&pending.code_verifier))
+            .append_pair("code_challenge_method", "S256");
+
+        let cookie = encode(
+            &Header::default(),
+            &pending,
+            &EncodingKey::from_secret(self.cookie_secret.as_bytes()),
+        )
+        .map_err(|e| provider_error("Failed to sign login state", e))?;
+
+        Ok((url.to_string(), cookie))
+    }
+
+    // check the cookie signature and that it belongs to this callback
+    pub fn pending_login(&self, cookie: &str, state: &str) -> Result<PendingLogin, AppError> {
+        let pending = decode::<PendingLogin>(
+            cookie,
+            &DecodingKey::from_secret(self.cookie_secret.as_bytes()),
+            &Validation::default(),
+        )
+        .map_err(|_| AppError::UnauthorizedError("Login session expired".into()))?
+        .claims;
+
+        if pending.state != state {
+            return Err(AppError::UnauthorizedError("Invalid login state".into()));
+        }
+
+        Ok(pending)
+    }
+
+    /// Redeems the authorization code and validates the returned ID token.
+    pub async fn exchange_code(
+        &self,
+        code: &str,
+        pending: &PendingLogin,
+    ) -> Result<OidcIdentity, AppError> {
+        let metadata = self.metadata().await?;
+
+        let mut form = vec![
+            ("grant_type", "authorization_code"),
+            ("code", code),
+            ("redirect_uri", self.config.redirect_url.as_str()),
+            ("client_id", self.config.client_id.as_str()),
+            ("code_verifier", pending.code_verifier.as_str()),
+        ];
+        if let Some(secret) = &self.config.client_secret {
+            form.push(("client_secret", secret));
+        }
+
+        let tokens = self
+            .http
+            .post(&metadata.token_endpoint)
+            .form(&form)
+            .send()
+            .await
+            .map_err(|e| provider_error("Failed to reach token endpoint", e))?;
+
+        if tokens.status().is_client_error() {
+            log::warn!("Token endpoint rejected code: {}", tokens.status());
+            return Err(AppError::UnauthorizedError(
+                "Invalid authorization code".into(),
+            ));
+        }
+
+        let tokens = tokens
+            .error_for_status()
+            .map_err(|e| provider_error("Token endpoint failed", e))?
+            .json::<TokenResponse>()
+            .await
+            .map_err(|e| provider_error("Invalid token response", e))?;
+
+        let claims = self.verify_id_token(&tokens.id_token, metadata).await?;
+
+        if claims.get("nonce").and_then(Value::as_str) != Some(pending.nonce.as_str()) {
+            return Err(AppError::UnauthorizedError("Invalid ID token nonce".into()));
+        }
+
+        self.identity(&metadata.issuer, &claims)
+    }
+
+    // validate signature, issuer, audience and expiry of the ID token
+    async fn verify_id_token(
+        &self,
+        id_token: &str,
+        metadata: &ProviderMetadata,
+    ) -> Result<Map<String, Value>, AppError> {
+        let header = decode_header(id_token)
+            .map_err(|_| AppError::UnauthorizedError("Malformed ID token".into()))?;
+
+        let key = match header.alg {
+            // symmetric tokens are signed with our client secret
+            Algorithm::HS256 | Algorithm::HS384 | Algorithm::HS512 => {
+                let secret = self.config.client_secret.as_deref().ok_or_else(|| {
+                    AppError::UnauthorizedError("Unexpected ID token algorithm".into())
+                })?;
+                DecodingKey::from_secret(secret.as_bytes())
+            }
+            _ => self.signing_key(header.kid.as_deref(), metadata).await?,
+        };
+
+        let mut validation = Validation::new(header.alg);
+        validation.set_issuer(&[&metadata.issuer]);
+        validation.set_audience(&[&self.config.client_id]);
+
+        decode::<Map<String, Value>>(id_token, &key, &validation)
+            .map(|data| data.claims)
+            .map_err(|e| {
+                log::warn!("ID token rejected: {}", e);
+                AppError::UnauthorizedError("Invalid ID token".into())
+            })
+    }
+
+    // find the provider key, refreshing the cached set once for rotated keys
+    async fn signing_key(
+        &self,
+        kid: Option<&str>,
+        metadata: &ProviderMetadata,
+    ) -> Result<DecodingKey, AppError> {
+        for refresh in [false, true] {
+            if refresh || self.jwks.read().await.is_none() {
+                let jwks_uri = metadata.jwks_uri.as_deref().ok_or_else(|| {
+                    AppError::ExternalServiceError("Identity provider has no JWKS".into())
+                })?;
+                let jwks = self
+                    .http
+                    .get(jwks_uri)
+                    .send()
+                    .await
+                    .and_then(|res| res.error_for_status())
+                    .map_err(|e| provider_error("Failed to fetch provider keys", e))?
+                    .json::<JwkSet>()
+                    .await
+                    .map_err(|e| provider_error("Invalid provider keys", e))?;
+                *self.jwks.write().await = Some(jwks);
+            }
+
+            let jwks = self.jwks.read().await;
+            let jwk = match kid {
+                Some(kid) => jwks.as_ref().and_then(|set| set.find(kid)),
+                None => jwks.as_ref().and_then(|set| set.keys.first()),
+            };
+            if let Some(jwk) = jwk {
+                return DecodingKey::from_jwk(jwk)
+                    .map_err(|e| provider_error("Unsupported provider key", e));
+            }
+        }
+
+        Err(AppError::UnauthorizedError(
+            "Unknown ID token signing key".into(),
+        ))
+    }
+
+    fn identity(
+        &self,
+        issuer: &str,
+        claims: &Map<String, Value>,
+    ) -> Result<OidcIdentity, AppError> {
+        let claim = |name: &str| claims.get(name).and_then(Value::as_str).map(str::to_owned);
+
+        let subject = claim("sub")
+            .ok_or_else(|| AppError::UnauthorizedError("ID token has no subject".into()))?;
+        let username = claim("preferred_username")
+            .or_else(|| claim("email"))
+            .unwrap_or_else(|| subject.clone());
+
+        // providers send groups as a list, some as a single string
+        let groups = match claims.get(&self.config.groups_claim) {
+            Some(Value::Array(values)) => values
+                .iter()
+                .filter_map(Value::as_str)
+                .map(str::to_owned)
+                .collect(),
+            Some(Value::String(group)) => vec![group.clone()],
+            _ => Vec::new(),
+        };
+
+        Ok(OidcIdentity {
+            issuer: issuer.to_string(),
+            subject,
+            username,
+            groups,
+        })
+    }
+
+    // staff must belong to one of the allowed groups when any are configured
+    pub fn is_allowed(&self, identity: &OidcIdentity) -> bool {
+        self.config.allowed_groups.is_empty()
+            || identity
+                .groups
+                .iter()
+                .any(|group| self.config.allowed_groups.contains(group))
+    }
+
+    pub fn is_admin(&self, identity: &OidcIdentity) -> bool {
+        identity
+            .groups
+            .iter()
+            .any(|group| self.config.admin_groups.contains(group))
+    }
+}
@@ -0,0 +1,168 @@
+use argon2::password_hash::{rand_core::OsRng, PasswordHash, PasswordHasher, SaltString};
+use argon2::{Algorithm, Argon2, Params, PasswordVerifier, Version};
+use once_cell::sync::Lazy;
+use std::collections::HashSet;
+use thiserror::Error;
+
+use crate::config::PasswordConfig;
+
+// common passwords shipped with the binary
+const BUILTIN_BREACHED_PASSWORDS: &str = include_str!("breached_passwords.txt");
+
+// hash verified when the user doesn't exist, so timing doesn't reveal usernames
+static DUMMY_HASH: Lazy<String> =
+    Lazy::new(|| hash_password("dummy password").expect("Failed to hash dummy password"));
+
+#[derive(Debug, Error)]
+pub enum PasswordError {
+    #[error("password hashing failed: {0}")]
+    Hash(String),
+    #[error("stored password hash is invalid")]
+    InvalidHash,
+    #[error("password task failed: {0}")]
+    Blocking(#[from] actix_web::error::BlockingError),
+}
+
+/// Result of checking a password against a stored hash.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PasswordVerification {
+    pub valid: bool,
+    // the hash uses bcrypt or outdated argon2 parameters
+    pub needs_rehash: bool,
+}
+
+// argon2id with the current OWASP recommended parameters
+fn argon2() -> Argon2<'static> {
+    Argon2::new(Algorithm::Argon2id, Version::V0x13, Params::DEFAULT)
+}
+
+// hash a password for storage
+pub fn hash_password(password: &str) -> Result<String, PasswordError> {
+    let salt = SaltString::generate(&mut OsRng);
+    argon2()
+        .hash_password(password.as_bytes(), &salt)
+        .map(|hash| hash.to_string())
+        .map_err(|e| PasswordError::Hash(e.to_string()))
+}
+
+// verify a password against an argon2 or legacy bcrypt hash
+pub fn verify_password(
+    password: &str,
+    stored_hash: &str,
+) -> Result<PasswordVerification, PasswordError> {
+    if stored_hash.starts_with("$2") {
+        let valid =
+            bcrypt::verify(password, stored_hash).map_err(|_| PasswordError::InvalidHash)?;
+        return Ok(PasswordVerification {
+            valid,
+            needs_rehash: true,
+        });
+    }
+
+    let parsed = PasswordHash::new(stored_hash).map_err(|_| PasswordError::InvalidHash)?;
+    let valid = argon2()
+        .verify_password(password.as_bytes(), &parsed)
+        .is_ok();
+
+    let current = parsed.algorithm == Algorithm::Argon2id.ident()
+        && parsed.version == Some(Version::V0x13.into())
+        && Params::try_from(&parsed).is_ok_and(|params| {
+            params.m_cost() == Params::DEFAULT.m_cost()
+                && params.t_cost() == Params::DEFAULT.t_cost()
+                && params.p_cost() == Params::DEFAULT.p_cost()
+        });
+
+    Ok(PasswordVerification {
+        valid,
+        needs_rehash: !current,
+    })
+}
+
+// burn the same time as a real verification for unknown users
+pub fn verify_dummy_password(password: &str) {
+    let _ = verify_password(password, &DUMMY_HASH);
+}
+
+// hashing takes tens of milliseconds, keep it off the async workers
+pub async fn hash_password_blocking(password: String) -> Result<String, PasswordError> {
+    actix_web::web::block(move || hash_password(&password)).await?
+}
+
+pub async fn verify_password_blocking(
+    password: String,
+    stored_hash: String,
+) -> Result<PasswordVerification, PasswordError> {
+    actix_web::web::block(move || verify_password(&password, &stored_hash)).await?
+}
+
+pub async fn verify_dummy_password_blocking(password: String) {
+    let _ = actix_web::web::block(move || verify_dummy_password(&password)).await;
+}
+
+/// Rules new passwords have to follow.
+pub struct PasswordPolicy {
+    pub min_length: usize,
+    pub max_length: usize,
+    breached: HashSet<String>,
+}
+
+impl PasswordPolicy {
+    pub fn new(min_length: usize, max_length: usize, breached: &str) -> Self {
+        PasswordPolicy {
+            min_length,
+            max_length,
+            breached: breached
+                .lines()
+                .map(str::trim)
+                .filter(|line| !line.is_empty() && !line.starts_with('#'))
+                .map(str::to_lowercase)
+                .collect(),
+        }
+    }
+
+    // build the configured policy, extending the builtin list with the breached passwords file
+    pub fn from_config(config: &PasswordConfig) -> std::io::Result<Self> {
+        let mut breached = BUILTIN_BREACHED_PASSWORDS.to_string();
+        if let Some(path) = &config.breached_passwords_file {
+            let extra = std::fs::read_to_string(path).map_err(|e| {
+                std::io::Error::new(
+                    e.kind(),
+                    format!("Failed to read {}: {}", path.display(), e),
+                )
+            })?;
+            breached.push('\n');
+            breached.push_str(&extra);
+        }
+
+        Ok(PasswordPolicy::new(
+            config.min_length,
+            config.max_length,
+            &breached,
+        ))
+    }
+
+    // check a new password, returning the first rule it breaks
+    pub fn check(&self, username: &str, password: &str) -> Result<(), String> {
+        let length = password.chars().count();
+        if length < self.min_length {
+            return Err(format!(
+                "Password must be at least {} characters",
+                self.min_length
+            ));
+        }
+        if length > self.max_length {
+            return Err(format!(
+                "Password must be at most {} characters",
+                self.max_length
+            ));
+        }
+        if password.eq_ignore_ascii_case(username) {
+            return Err("Password must not match the username".to_string());
+        }
+        if self.breached.contains(&password.to_lowercase()) {
+            return Err("Password appears in a list of breached passwords".to_string());
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,76 @@
+use actix_web::http::header::{EntityTag, Header, IfMatch, IF_MATCH};
+use actix_web::HttpRequest;
+use chrono::NaiveDateTime;
+use serde::Serialize;
+
+use crate::models::category::Category;
+use crate::models::news::News;
+use crate::utils::error_response::AppError;
+
+/// Rows whose `updated_at` is their version.
+pub trait Versioned: Serialize {
+    fn updated_at(&self) -> NaiveDateTime;
+}
+
+impl Versioned for News {
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+}
+
+impl Versioned for Category {
+    fn updated_at(&self) -> NaiveDateTime {
+        self.updated_at
+    }
+}
+
+/// Strong validator of a row, every write sets `updated_at` so it changes with each of them.
+pub fn etag(updated_at: NaiveDateTime) -> EntityTag {
+    EntityTag::new_strong(updated_at.and_utc().timestamp_micros().to_string())
+}
+
+/// The `If-Match` header, None when it wasn't sent.
+pub fn if_match(req: &HttpRequest) -> Result<Option<IfMatch>, AppError> {
+    if !req.headers().contains_key(IF_MATCH) {
+        return Ok(None);
+    }
+    IfMatch::parse(req)
+        .map(Some)
+        .map_err(|_| AppError::BadRequestError("Invalid If-Match header".into()))
+}
+
+/// Compares what the client last read with the row as it is now. Returns the version the
+/// write has to find, None when the client didn't name one.
+pub fn check<T: Versioned>(
+    if_match: Option<IfMatch>,
+    required: bool,
+    current: &T,
+) -> Result<Option<NaiveDateTime>, AppError> {
+    let updated_at = current.updated_at();
+    match if_match {
+        None if required => Err(AppError::PreconditionRequiredError(
+            "Send If-Match with the ETag you last read".into(),
+        )),
+        None | Some(IfMatch::Any) => Ok(None),
+        Some(IfMatch::Items(tags)) if tags.iter().any(|tag| tag.strong_eq(&etag(updated_at))) => {
+            Ok(Some(updated_at))
+        }
+        Some(IfMatch::Items(_)) => Err(stale(current)),
+    }
+}
+
+/// 412 with the version the client has to merge their changes with.
+pub fn stale<T: Versioned>(current: &T) -> AppError {
+    AppError::PreconditionFailedError(
+        etag(current.updated_at()).to_string(),
+        serde_json::to_value(current).unwrap_or_default(),
+    )
+}
+
+/// A conditional write found nothing: the row is gone, or someone else saved first.
+pub fn missed<T: Versioned>(current: Option<T>, not_found: &str) -> AppError {
+    match current {
+        Some(current) => stale(&current),
+        None => AppError::NotFoundError(not_found.into()),
+    }
+}
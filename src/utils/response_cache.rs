@@ -0,0 +1,180 @@
+use std::num::NonZeroUsize;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use actix_web::http::header::ContentType;
+use actix_web::web::Bytes;
+use actix_web::{HttpRequest, HttpResponse};
+use lru::LruCache;
+
+use crate::config::ResponseCacheConfig;
+use crate::events::{ContentEvent, Subscriber};
+use crate::utils::http_cache::Validators;
+use crate::utils::metrics::record_cache_lookup;
+use crate::utils::to_json;
+
+/// The read responses worth keeping.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum CacheKey {
+    NewsList,
+    News(i32),
+    CategoryList,
+    Category(i32),
+}
+
+impl CacheKey {
+    // the `cache` label of the metrics
+    fn name(&self) -> &'static str {
+        match self {
+            CacheKey::NewsList => "news_list",
+            CacheKey::News(_) => "news",
+            CacheKey::CategoryList => "category_list",
+            CacheKey::Category(_) => "category",
+        }
+    }
+}
+
+/// A serialized JSON response with what it is revalidated against.
+#[derive(Debug)]
+pub struct CachedResponse {
+    pub validators: Validators,
+    pub surrogate_key: Option<String>,
+    pub body: Bytes,
+}
+
+impl CachedResponse {
+    pub fn json<T: serde::Serialize>(
+        validators: Validators,
+        surrogate_key: Option<String>,
+        value: &T,
+    ) -> Self {
+        CachedResponse {
+            validators,
+            surrogate_key,
+            body: Bytes::from(to_json(value)),
+        }
+    }
+
+    /// 304 when the client's copy is current, the body otherwise.
+    pub fn respond(&self, req: &HttpRequest, cache_control: &str) -> HttpResponse {
+        if self.validators.is_fresh(req) {
+            return self.validators.not_modified(cache_control);
+        }
+        self.validators
+            .ok(cache_control, self.surrogate_key.clone())
+            .content_type(ContentType::json())
+            .body(self.body.clone())
+    }
+}
+
+struct Entry {
+    response: Arc<CachedResponse>,
+    // surrogate keys, the events name the ones they make stale
+    tags: Vec<String>,
+    stored: Instant,
+}
+
+struct Entries {
+    lru: LruCache<CacheKey, Entry>,
+    // counts purges, a response read before one of them may already be stale
+    generation: u64,
+}
+
+/// A lookup that found nothing, the response that is read instead may be kept with it.
+pub struct Miss {
+    key: CacheKey,
+    generation: u64,
+}
+
+/// Least recently used responses, each kept until it expires or an event purges it. Disabled
+/// caches store nothing.
+pub struct ResponseCache {
+    entries: Option<Mutex<Entries>>,
+    ttl: Duration,
+}
+
+impl ResponseCache {
+    pub fn new(config: &ResponseCacheConfig) -> Self {
+        let entries = NonZeroUsize::new(config.capacity)
+            .filter(|_| config.enabled)
+            .map(|capacity| {
+                Mutex::new(Entries {
+                    lru: LruCache::new(capacity),
+                    generation: 0,
+                })
+            });
+        ResponseCache {
+            entries,
+            ttl: Duration::from_secs(config.ttl_secs),
+        }
+    }
+
+    pub fn get(&self, key: CacheKey) -> Result<Arc<CachedResponse>, Miss> {
+        let Some(entries) = &self.entries else {
+            return Err(Miss { key, generation: 0 });
+        };
+        let mut entries = entries.lock().unwrap();
+        let response = match entries.lru.get(&key) {
+            Some(entry) if entry.stored.elapsed() < self.ttl => Some(entry.response.clone()),
+            Some(_) => {
+                entries.lru.pop(&key);
+                None
+            }
+            None => None,
+        };
+        record_cache_lookup(key.name(), response.is_some());
+        response.ok_or(Miss {
+            key,
+            generation: entries.generation,
+        })
+    }
+
+    /// Keeps the response read after a miss and hands it back, tagged with the surrogate keys
+    /// of what it shows. It isn't kept when something was purged meanwhile.
+    pub fn insert(
+        &self,
+        miss: Miss,
+        response: CachedResponse,
+        tags: Vec<String>,
+    ) -> Arc<CachedResponse> {
+        let response = Arc::new(response);
+        if let Some(entries) = &self.entries {
+            let mut entries = entries.lock().unwrap();
+            if entries.generation == miss.generation {
+                entries.lru.put(
+                    miss.key,
+                    Entry {
+                        response: response.clone(),
+                        tags,
+                        stored: Instant::now(),
+                    },
+                );
+            }
+        }
+        response
+    }
+
+    /// Drops every response tagged with one of the keys.
+    pub fn purge(&self, keys: &[String]) {
+        let Some(entries) = &self.entries else {
+            return;
+        };
+        let mut entries = entries.lock().unwrap();
+        entries.generation += 1;
+        let stale: Vec<CacheKey> = entries
+            .lru
+            .iter()
+            .filter(|(_, entry)| entry.tags.iter().any(|tag| keys.contains(tag)))
+            .map(|(key, _)| *key)
+            .collect();
+        for key in stale {
+            entries.lru.pop(&key);
+        }
+    }
+}
+
+impl Subscriber for ResponseCache {
+    fn notify(&self, event: &ContentEvent) {
+        self.purge(&event.keys());
+    }
+}
@@ -0,0 +1,104 @@
+use actix_web::http::header::USER_AGENT;
+use actix_web::HttpRequest;
+use chrono::Utc;
+use diesel::prelude::*;
+use uuid::Uuid;
+
+use crate::config::JwtConfig;
+use crate::models::session::{NewSession, Session};
+use crate::schema::sessions;
+use crate::utils::error_response::AppError;
+use crate::utils::jwt::create_token;
+
+// longest device label / user agent we keep
+const MAX_FIELD_LENGTH: usize = 255;
+
+/// Where a login came from, recorded with its session.
+#[derive(Debug, Default, Clone)]
+pub struct SessionInfo {
+    pub device_label: Option<String>,
+    pub user_agent: Option<String>,
+    pub ip_address: Option<String>,
+}
+
+fn truncate(value: &str) -> String {
+    value.chars().take(MAX_FIELD_LENGTH).collect()
+}
+
+impl SessionInfo {
+    pub fn from_request(req: &HttpRequest, device_label: Option<&str>) -> Self {
+        SessionInfo {
+            device_label: device_label.map(truncate),
+            user_agent: req
+                .headers()
+                .get(USER_AGENT)
+                .and_then(|value| value.to_str().ok())
+                .map(truncate),
+            ip_address: req.peer_addr().map(|addr| addr.ip().to_string()),
+        }
+    }
+}
+
+// start a session for the user and issue a token bound to it
+pub fn issue_session_token(
+    conn: &mut PgConnection,
+    jwt: &JwtConfig,
+    user_id: i32,
+    username: &str,
+    is_admin: bool,
+    info: SessionInfo,
+) -> Result<String, AppError> {
+    let session = NewSession {
+        id: Uuid::new_v4().simple().to_string(),
+        user_id,
+        device_label: info.device_label,
+        user_agent: info.user_agent,
+        ip_address: info.ip_address,
+        expires_at: Utc::now().naive_utc() + chrono::Duration::hours(jwt.token_lifetime_hours),
+    };
+
+    diesel::insert_into(sessions::table)
+        .values(&session)
+        .execute(conn)?;
+
+    create_token(jwt, user_id, username, is_admin, &session.id).map_err(|e| {
+        log::error!("Token creation failed: {}", e);
+        AppError::DatabaseError("Token creation failed".into())
+    })
+}
+
+// funtion to check that a token's session is still active
+pub fn validate_session(
+    conn: &mut PgConnection,
+    session_id: &str,
+    user_id: i32,
+) -> Result<(), AppError> {
+    let now = Utc::now().naive_utc();
+
+    let session = sessions::table
+        .find(session_id)
+        .select(Session::as_select())
+        .first::<Session>(conn)
+        .optional()?
+        .filter(|session| session.user_id == user_id)
+        .ok_or_else(|| AppError::UnauthorizedError("Session not found".into()))?;
+
+    if session.revoked_at.is_some() {
+        return Err(AppError::UnauthorizedError(
+            "Session has been revoked".into(),
+        ));
+    }
+
+    if session.expires_at <= now {
+        return Err(AppError::UnauthorizedError("Session has expired".into()));
+    }
+
+    // record activity, at most once a minute to keep writes down
+    if session.last_seen_at < now - chrono::Duration::minutes(1) {
+        diesel::update(sessions::table.find(session_id))
+            .set(sessions::last_seen_at.eq(now))
+            .execute(conn)?;
+    }
+
+    Ok(())
+}
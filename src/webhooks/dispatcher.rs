@@ -0,0 +1,252 @@
+use std::time::Duration;
+
+use chrono::{NaiveDateTime, Utc};
+use diesel::prelude::*;
+use futures::future::join_all;
+use serde_json::{json, Value};
+
+use crate::config::WebhookConfig;
+use crate::db::{self, DBPool};
+use crate::models::webhook::{NewDelivery, DELIVERY_DEAD, DELIVERY_DELIVERED, DELIVERY_PENDING};
+use crate::schema::{outbox_events, webhook_deliveries, webhooks};
+use crate::utils::error_response::AppError;
+use crate::webhooks::{
+    is_subscribed, sign, DELIVERY_HEADER, EVENT_HEADER, SIGNATURE_HEADER, TIMESTAMP_HEADER,
+};
+
+/// Queues a delivery of each new outbox event to every webhook subscribed to it, returns how
+/// many were queued. Replicas running this together each take different events.
+pub fn fan_out(conn: &mut PgConnection, batch_size: i64) -> QueryResult<usize> {
+    conn.transaction(|conn| {
+        let events = outbox_events::table
+            .filter(outbox_events::dispatched_at.is_null())
+            .order(outbox_events::id.asc())
+            .limit(batch_size)
+            .select((outbox_events::id, outbox_events::event_type))
+            .for_update()
+            .skip_locked()
+            .load::<(i64, String)>(conn)?;
+        if events.is_empty() {
+            return Ok(0);
+        }
+
+        let hooks = webhooks::table
+            .select((webhooks::id, webhooks::events))
+            .load::<(i32, Vec<String>)>(conn)?;
+        let deliveries: Vec<NewDelivery> = events
+            .iter()
+            .flat_map(|(event_id, event_type)| {
+                hooks
+                    .iter()
+                    .filter(|(_, patterns)| is_subscribed(patterns, event_type))
+                    .map(|(webhook_id, _)| NewDelivery {
+                        webhook_id: *webhook_id,
+                        event_id: *event_id,
+                        event_type: event_type.clone(),
+                    })
+            })
+            .collect();
+        diesel::insert_into(webhook_deliveries::table)
+            .values(&deliveries)
+            .execute(conn)?;
+
+        let ids: Vec<i64> = events.iter().map(|(id, _)| *id).collect();
+        diesel::update(outbox_events::table.filter(outbox_events::id.eq_any(ids)))
+            .set(outbox_events::dispatched_at.eq(Utc::now().naive_utc()))
+            .execute(conn)?;
+        Ok(deliveries.len())
+    })
+}
+
+/// A delivery whose turn it is, with what sending it takes.
+#[derive(Debug)]
+pub struct Due {
+    pub id: i64,
+    pub attempts: i32,
+    pub url: String,
+    pub secret: String,
+    pub event_type: String,
+    pub body: Vec<u8>,
+}
+
+/// Takes the deliveries that are due for `lease`, so other replicas skip them while they are
+/// sent. One that is never recorded is tried again once the lease ran out.
+pub fn claim(conn: &mut PgConnection, batch_size: i64, lease: Duration) -> QueryResult<Vec<Due>> {
+    conn.transaction(|conn| {
+        let now = Utc::now().naive_utc();
+        let ids = webhook_deliveries::table
+            .filter(webhook_deliveries::status.eq(DELIVERY_PENDING))
+            .filter(webhook_deliveries::next_attempt_at.le(now))
+            .order(webhook_deliveries::next_attempt_at.asc())
+            .limit(batch_size)
+            .select(webhook_deliveries::id)
+            .for_update()
+            .skip_locked()
+            .load::<i64>(conn)?;
+        diesel::update(webhook_deliveries::table.filter(webhook_deliveries::id.eq_any(&ids)))
+            .set(webhook_deliveries::next_attempt_at.eq(now + lease))
+            .execute(conn)?;
+
+        let rows = webhook_deliveries::table
+            .inner_join(webhooks::table)
+            .inner_join(outbox_events::table)
+            .filter(webhook_deliveries::id.eq_any(&ids))
+            .select((
+                webhook_deliveries::id,
+                webhook_deliveries::attempts,
+                webhooks::url,
+                webhooks::secret,
+                outbox_events::id,
+                outbox_events::event_type,
+                outbox_events::payload,
+                outbox_events::created_at,
+            ))
+            .load::<(i64, i32, String, String, i64, String, Value, NaiveDateTime)>(conn)?;
+
+        Ok(rows
+            .into_iter()
+            .map(
+                |(id, attempts, url, secret, event_id, event_type, data, created_at)| {
+                    let body = json!({
+                        "id": event_id,
+                        "type": event_type,
+                        "created_at": created_at,
+                        "data": data,
+                    });
+                    Due {
+                        id,
+                        attempts,
+                        url,
+                        secret,
+                        event_type,
+                        body: serde_json::to_vec(&body).unwrap_or_default(),
+                    }
+                },
+            )
+            .collect())
+    })
+}
+
+// the wait before the next attempt, after `attempts` failed ones
+fn backoff(config: &WebhookConfig, attempts: i32) -> Duration {
+    let doublings = attempts.saturating_sub(1).clamp(0, 31) as u32;
+    Duration::from_secs(
+        config
+            .backoff_secs
+            .saturating_mul(2u64.saturating_pow(doublings))
+            .min(config.max_backoff_secs),
+    )
+}
+
+/// Stores the outcome of an attempt: the status code of the response, or why there was none.
+pub fn record(
+    conn: &mut PgConnection,
+    config: &WebhookConfig,
+    due: &Due,
+    outcome: Result<u16, String>,
+) -> QueryResult<()> {
+    let now = Utc::now().naive_utc();
+    let attempts = due.attempts + 1;
+    let (status_code, error) = match &outcome {
+        Ok(code) => (Some(i32::from(*code)), None),
+        Err(error) => (None, Some(error.clone())),
+    };
+    let delivered = matches!(outcome, Ok(code) if (200..300).contains(&code));
+    let status = if delivered {
+        DELIVERY_DELIVERED
+    } else if attempts >= config.max_attempts {
+        DELIVERY_DEAD
+    } else {
+        DELIVERY_PENDING
+    };
+
+    diesel::update(webhook_deliveries::table.find(due.id))
+        .set((
+            webhook_deliveries::status.eq(status),
+            webhook_deliveries::attempts.eq(attempts),
+            webhook_deliveries::next_attempt_at.eq(now + backoff(config, attempts)),
+            webhook_deliveries::last_status_code.eq(status_code),
+            webhook_deliveries::last_error.eq(error),
+            webhook_deliveries::delivered_at.eq(delivered.then_some(now)),
+        ))
+        .execute(conn)?;
+    Ok(())
+}
+
+/// Sends the queued content events to the webhooks, see `run_once`.
+pub struct Dispatcher {
+    pool: DBPool,
+    client: reqwest::Client,
+    config: WebhookConfig,
+}
+
+impl Dispatcher {
+    pub fn new(pool: DBPool, config: WebhookConfig) -> Self {
+        let client = reqwest::Client::builder()
+            .timeout(Duration::from_secs(config.timeout_secs))
+            // a redirect could point the signed payload somewhere else
+            .redirect(reqwest::redirect::Policy::none())
+            .build()
+            .expect("Failed to build the webhook client");
+        Dispatcher {
+            pool,
+            client,
+            config,
+        }
+    }
+
+    async fn send(&self, due: &Due) -> Result<u16, String> {
+        let timestamp = Utc::now().timestamp();
+        let response = self
+            .client
+            .post(&due.url)
+            .header(reqwest::header::CONTENT_TYPE, "application/json")
+            .header(EVENT_HEADER, &due.event_type)
+            .header(DELIVERY_HEADER, due.id.to_string())
+            .header(TIMESTAMP_HEADER, timestamp.to_string())
+            .header(SIGNATURE_HEADER, sign(&due.secret, timestamp, &due.body))
+            .body(due.body.clone())
+            .send()
+            .await
+            .map_err(|e| e.to_string())?;
+        Ok(response.status().as_u16())
+    }
+
+    /// Queues new events, sends what is due and records how it went. Returns how many
+    /// deliveries were attempted.
+    pub async fn run_once(&self) -> Result<usize, AppError> {
+        let batch_size = self.config.batch_size;
+        // long enough for every request of the batch to time out
+        let lease = Duration::from_secs(self.config.timeout_secs * 2);
+
+        db::run(&self.pool, move |conn| {
+            fan_out(conn, batch_size).map_err(AppError::from)
+        })
+        .await?;
+        let due = db::run(&self.pool, move |conn| {
+            claim(conn, batch_size, lease).map_err(AppError::from)
+        })
+        .await?;
+        if due.is_empty() {
+            return Ok(0);
+        }
+
+        let outcomes = join_all(due.iter().map(|due| self.send(due))).await;
+        for (due, outcome) in due.iter().zip(&outcomes) {
+            if let Err(error) = outcome {
+                tracing::warn!(delivery = due.id, url = %due.url, %error, "Webhook delivery failed");
+            }
+        }
+
+        let config = self.config.clone();
+        let attempted = due.len();
+        db::run(&self.pool, move |conn| {
+            for (due, outcome) in due.iter().zip(outcomes) {
+                record(conn, &config, due, outcome)?;
+            }
+            Ok::<_, AppError>(())
+        })
+        .await?;
+        Ok(attempted)
+    }
+}
@@ -0,0 +1,88 @@
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use serde_json::Value;
+use sha2::Sha256;
+use uuid::Uuid;
+
+use crate::utils::to_json_value;
+
+pub mod dispatcher;
+
+// articles go live as soon as they are created, so both are sent together
+pub const NEWS_CREATED: &str = "news.created";
+pub const NEWS_PUBLISHED: &str = "news.published";
+pub const NEWS_UPDATED: &str = "news.updated";
+// deletions move to the trash, restoring takes them out again
+pub const NEWS_DELETED: &str = "news.deleted";
+pub const NEWS_RESTORED: &str = "news.restored";
+pub const CATEGORY_CREATED: &str = "category.created";
+pub const CATEGORY_UPDATED: &str = "category.updated";
+pub const CATEGORY_DELETED: &str = "category.deleted";
+pub const CATEGORY_RESTORED: &str = "category.restored";
+pub const EVENT_TYPES: [&str; 9] = [
+    NEWS_CREATED,
+    NEWS_PUBLISHED,
+    NEWS_UPDATED,
+    NEWS_DELETED,
+    NEWS_RESTORED,
+    CATEGORY_CREATED,
+    CATEGORY_UPDATED,
+    CATEGORY_DELETED,
+    CATEGORY_RESTORED,
+];
+
+pub const SIGNATURE_HEADER: &str = "X-Webhook-Signature";
+pub const TIMESTAMP_HEADER: &str = "X-Webhook-Timestamp";
+pub const EVENT_HEADER: &str = "X-Webhook-Event";
+// the same for every retry, receivers can drop duplicates by it
+pub const DELIVERY_HEADER: &str = "X-Webhook-Delivery";
+
+// marker so leaked secrets are easy to recognise, like API keys
+const SECRET_MARKER: &str = "whsec_";
+
+/// True for `*`, `<resource>.*` and the event types themselves.
+pub fn is_valid_pattern(pattern: &str) -> bool {
+    pattern == "*"
+        || EVENT_TYPES.contains(&pattern)
+        || pattern.strip_suffix(".*").is_some_and(|resource| {
+            EVENT_TYPES
+                .iter()
+                .any(|event| event.split('.').next() == Some(resource))
+        })
+}
+
+/// Whether a webhook registered for `patterns` wants `event_type`.
+pub fn is_subscribed(patterns: &[String], event_type: &str) -> bool {
+    patterns.iter().any(|pattern| {
+        pattern == "*"
+            || pattern == event_type
+            || pattern
+                .strip_suffix('*')
+                .is_some_and(|prefix| prefix.ends_with('.') && event_type.starts_with(prefix))
+    })
+}
+
+/// The `data` of an event, the row it is about.
+pub fn payload<T: Serialize>(row: &T) -> Value {
+    to_json_value(row)
+}
+
+pub fn generate_secret() -> String {
+    format!(
+        "{}{}{}",
+        SECRET_MARKER,
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// `sha256=` and the hex HMAC-SHA256 of `<timestamp>.<body>`, the timestamp is signed too so
+/// captured requests can't be replayed later.
+pub fn sign(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC takes keys of any length");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    format!("sha256={:x}", mac.finalize().into_bytes())
+}